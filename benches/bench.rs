@@ -12,19 +12,18 @@
     html_root_url = "https://docs.rs/parsec"
 )]
 #![forbid(
-    exceeding_bitshifts,
+    arithmetic_overflow,
     mutable_transmutes,
     no_mangle_const_items,
-    unknown_crate_types,
-    warnings
+    unknown_crate_types
 )]
 #![deny(
+    warnings,
     bad_style,
     deprecated,
     improper_ctypes,
     non_shorthand_field_patterns,
     overflowing_literals,
-    plugin_as_library,
     stable_features,
     unconditional_recursion,
     unknown_lints,
@@ -45,7 +44,6 @@
     unused_results
 )]
 #![allow(
-    box_pointers,
     missing_copy_implementations,
     missing_debug_implementations,
     variant_size_differences
@@ -62,7 +60,9 @@ extern crate unwrap;
 #[cfg(feature = "testing")]
 use criterion::Criterion;
 #[cfg(feature = "testing")]
-use parsec::dev_utils::Record;
+use parsec::{dev_utils::Record, FileEventStore};
+#[cfg(feature = "testing")]
+use std::{env, fs};
 
 #[cfg(feature = "testing")]
 fn bench(c: &mut Criterion) {
@@ -115,6 +115,12 @@ fn bench(c: &mut Criterion) {
         bench_dot_file(c, "bench_section_size_evt8192_interleave", name);
     }
 
+    // The same graphs with their events held in a file, only 1024 of them being cached in memory, to
+    // compare with the in-memory store above.
+    for name in &["a_node4_opaque_evt8192", "a_node8_opaque_evt8192"] {
+        bench_dot_file_with_event_store(c, "bench_section_size_evt8192_interleave", name, 1024);
+    }
+
     for name in &[
         "a_node4_opaque_evt65536",
         "a_node8_opaque_evt65536",
@@ -200,6 +206,36 @@ fn bench_dot_file(c: &mut Criterion, group_name: &'static str, name: &'static st
     });
 }
 
+#[cfg(feature = "testing")]
+fn bench_dot_file_with_event_store(
+    c: &mut Criterion,
+    group_name: &'static str,
+    name: &'static str,
+    cache_capacity: usize,
+) {
+    let test_name = format!("{} - {} - file store", name, group_name);
+    let path = env::temp_dir().join(format!("parsec_bench_{}", name));
+    let store_path = path.clone();
+    let _ = c.bench_function(&test_name, move |b| {
+        let record = unwrap!(Record::parse(format!(
+            "input_graphs/{}/{}.dot",
+            group_name, name
+        )));
+        b.iter_with_setup(
+            || (record.clone(), unwrap!(FileEventStore::create(&store_path))),
+            |(record, store)| {
+                let expected_history = record.consensus_history();
+
+                let parsec = record.play_with_event_store(Box::new(store), cache_capacity);
+                let actual_history = parsec.meta_election_consensus_history_hash();
+
+                assert_eq!(expected_history, actual_history);
+            },
+        )
+    });
+    let _ = fs::remove_file(&path);
+}
+
 #[cfg(feature = "testing")]
 criterion_group! {
     name = benches;
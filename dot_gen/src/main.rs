@@ -52,20 +52,19 @@
 //!
 
 #![forbid(
-    exceeding_bitshifts,
+    arithmetic_overflow,
     mutable_transmutes,
     no_mangle_const_items,
-    unknown_crate_types,
-    warnings
+    unknown_crate_types
 )]
 #![deny(
+    warnings,
     bad_style,
     deprecated,
     improper_ctypes,
     missing_docs,
     non_shorthand_field_patterns,
     overflowing_literals,
-    plugin_as_library,
     stable_features,
     unconditional_recursion,
     unknown_lints,
@@ -86,7 +85,6 @@
     unused_results
 )]
 #![allow(
-    box_pointers,
     missing_copy_implementations,
     missing_debug_implementations,
     variant_size_differences,
@@ -362,7 +360,7 @@ fn add_dev_utils_record_smoke_tests(scenarios: &mut Scenarios) {
         })
         .seed([1, 2, 3, 4])
         .file("Alice", "alice.dot")
-        .dump_mode(DumpGraphMode::OnParsecDrop);;
+        .dump_mode(DumpGraphMode::OnParsecDrop);
 
     let _ = scenarios
         .add("dev_utils::record::tests::smoke_partial_dkg", |env| {
@@ -560,7 +558,7 @@ fn add_bench_scalability_common(
 struct Scenario {
     name: String,
     seed: RngChoice,
-    schedule_fn: Box<FnMut(&mut Environment) -> Schedule>,
+    schedule_fn: Box<dyn FnMut(&mut Environment) -> Schedule>,
     files: BTreeMap<String, String>,
     consensus_mode: ConsensusMode,
     dump_mode: DumpGraphMode,
@@ -715,11 +713,11 @@ impl Scenarios {
         unwrap!(self.0.last_mut())
     }
 
-    fn iter(&self) -> slice::Iter<Scenario> {
+    fn iter(&self) -> slice::Iter<'_, Scenario> {
         self.0.iter()
     }
 
-    fn iter_mut(&mut self) -> slice::IterMut<Scenario> {
+    fn iter_mut(&mut self) -> slice::IterMut<'_, Scenario> {
         self.0.iter_mut()
     }
 }
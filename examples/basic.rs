@@ -9,20 +9,19 @@
 //! A basic example of running some nodes which reach consensus on the order of some random events.
 
 #![forbid(
-    exceeding_bitshifts,
+    arithmetic_overflow,
     mutable_transmutes,
     no_mangle_const_items,
-    unknown_crate_types,
-    warnings
+    unknown_crate_types
 )]
 #![deny(
+    warnings,
     bad_style,
     deprecated,
     improper_ctypes,
     missing_docs,
     non_shorthand_field_patterns,
     overflowing_literals,
-    plugin_as_library,
     stable_features,
     unconditional_recursion,
     unknown_lints,
@@ -44,7 +43,6 @@
     unused_results
 )]
 #![allow(
-    box_pointers,
     missing_copy_implementations,
     missing_debug_implementations,
     variant_size_differences
@@ -66,7 +64,7 @@ use std::{
     cmp,
     collections::BTreeSet,
     fmt::{self, Debug, Formatter},
-    process, usize,
+    process,
 };
 
 // When we are down to 3 peers, if we attempt to remove one peer, and it becomes unresponsive
@@ -434,7 +432,7 @@ fn parse_seed(seed_str: &str) -> Result<Seed, ()> {
     let mut seed = [0; 4];
     for (index, part) in parts.iter().enumerate() {
         seed[index] = part
-            .trim_matches(|c: char| !c.is_digit(10))
+            .trim_matches(|c: char| !c.is_ascii_digit())
             .parse::<u32>()
             .map_err(|_| ())?;
     }
@@ -521,7 +519,7 @@ impl Environment {
             assert!(self.peers_removed_count < self.params.remove_peers_count);
             let respect_remove_peers_count =
                 self.params.remove_peers_count - self.peers_removed_count;
-            let strictly_less_than_a_third_plus_one = (self.peers.len() + 2) / 3;
+            let strictly_less_than_a_third_plus_one = self.peers.len().div_ceil(3);
             let max_peers_to_remove = cmp::min(
                 respect_remove_peers_count,
                 strictly_less_than_a_third_plus_one,
@@ -0,0 +1,178 @@
+// Copyright 2019 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    block::Block,
+    error::Result,
+    gossip::{Request, Response},
+    id::SecretId,
+    network_event::NetworkEvent,
+    observation::Observation,
+    parsec::Parsec,
+};
+use futures::{
+    stream::Stream,
+    task::{Context, Poll, Waker},
+};
+use std::{cell::RefCell, collections::VecDeque, pin::Pin, rc::Rc};
+
+/// Async wrapper around a `Parsec` instance, for use with a local (single-threaded) executor.
+///
+/// The consensused blocks are output through the `Blocks` stream rather than `Parsec::poll`: the
+/// stream's task is woken whenever a call on the wrapper makes new blocks available.
+pub struct AsyncParsec<T: NetworkEvent, S: SecretId> {
+    inner: Rc<RefCell<Inner<T, S>>>,
+}
+
+struct Inner<T: NetworkEvent, S: SecretId> {
+    // Taken out while `with_parsec` runs its closure, so the stream stays usable meanwhile.
+    parsec: Option<Parsec<T, S>>,
+    // Blocks polled from `parsec` but not yet taken from the stream.
+    blocks: VecDeque<Block<T, S::PublicId>>,
+    // Task waiting on the stream, if any.
+    waker: Option<Waker>,
+}
+
+impl<T: NetworkEvent, S: SecretId> AsyncParsec<T, S> {
+    /// Wraps `parsec`. Any block it already holds is output by the stream.
+    pub fn new(parsec: Parsec<T, S>) -> Self {
+        let result = Self {
+            inner: Rc::new(RefCell::new(Inner {
+                parsec: Some(parsec),
+                blocks: VecDeque::new(),
+                waker: None,
+            })),
+        };
+        result.with_parsec(|_| ());
+        result
+    }
+
+    /// Returns the stream of consensused blocks. It never ends.
+    ///
+    /// All the streams of the same instance share the blocks, so there should only be one.
+    pub fn blocks(&self) -> Blocks<T, S> {
+        Blocks {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+
+    /// See `Parsec::vote_for`.
+    pub async fn vote_for(&self, observation: Observation<T, S::PublicId>) -> Result<()> {
+        self.with_parsec(|parsec| parsec.vote_for(observation))
+    }
+
+    /// See `Parsec::create_gossip`.
+    pub async fn create_gossip(&self, peer_id: &S::PublicId) -> Result<Request<T, S::PublicId>> {
+        self.with_parsec(|parsec| parsec.create_gossip(peer_id))
+    }
+
+    /// See `Parsec::handle_request`.
+    pub async fn handle_request(
+        &self,
+        src: &S::PublicId,
+        req: Request<T, S::PublicId>,
+    ) -> Result<Response<T, S::PublicId>> {
+        self.with_parsec(|parsec| parsec.handle_request(src, req))
+    }
+
+    /// See `Parsec::handle_response`.
+    pub async fn handle_response(
+        &self,
+        src: &S::PublicId,
+        resp: Response<T, S::PublicId>,
+    ) -> Result<()> {
+        self.with_parsec(|parsec| parsec.handle_response(src, resp))
+    }
+
+    /// Calls `f` with the wrapped instance, for the methods without an async counterpart. The
+    /// blocks it makes available are output by the stream, so `f` shouldn't call `Parsec::poll`.
+    ///
+    /// `f` may poll the stream, but mustn't call back into the wrapper. If `f` panics, the instance
+    /// is put back as `f` left it, and the blocks it made available are output by the next call.
+    pub fn with_parsec<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Parsec<T, S>) -> R,
+    {
+        let mut guard = match self.inner.borrow_mut().parsec.take() {
+            Some(parsec) => ParsecGuard {
+                inner: &self.inner,
+                parsec: Some(parsec),
+            },
+            None => panic!("AsyncParsec::with_parsec called recursively"),
+        };
+        let result = f(guard.parsec_mut());
+
+        let waker = {
+            let mut inner = self.inner.borrow_mut();
+            let len = inner.blocks.len();
+            while let Some(block) = guard.parsec_mut().poll() {
+                inner.blocks.push_back(block);
+            }
+            inner.parsec = guard.parsec.take();
+
+            if inner.blocks.len() > len {
+                inner.waker.take()
+            } else {
+                None
+            }
+        };
+
+        // Woken only once the borrow is released, as the waker may poll the stream straight away.
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+
+        result
+    }
+}
+
+// Puts the instance taken out by `with_parsec` back when dropped, so a panicking closure doesn't
+// leave the wrapper without one.
+struct ParsecGuard<'a, T: NetworkEvent, S: SecretId> {
+    inner: &'a RefCell<Inner<T, S>>,
+    parsec: Option<Parsec<T, S>>,
+}
+
+impl<T: NetworkEvent, S: SecretId> ParsecGuard<'_, T, S> {
+    fn parsec_mut(&mut self) -> &mut Parsec<T, S> {
+        match self.parsec.as_mut() {
+            Some(parsec) => parsec,
+            None => unreachable!("ParsecGuard used after its instance was put back"),
+        }
+    }
+}
+
+impl<T: NetworkEvent, S: SecretId> Drop for ParsecGuard<'_, T, S> {
+    fn drop(&mut self) {
+        if let Some(parsec) = self.parsec.take() {
+            // The closure may have panicked while holding a borrow, e.g. from polling the stream.
+            if let Ok(mut inner) = self.inner.try_borrow_mut() {
+                inner.parsec = Some(parsec);
+            }
+        }
+    }
+}
+
+/// Stream of the blocks consensused by an `AsyncParsec`, in the order `Parsec::poll` returns them.
+pub struct Blocks<T: NetworkEvent, S: SecretId> {
+    inner: Rc<RefCell<Inner<T, S>>>,
+}
+
+impl<T: NetworkEvent, S: SecretId> Stream for Blocks<T, S> {
+    type Item = Block<T, S::PublicId>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let mut inner = self.inner.borrow_mut();
+        if let Some(block) = inner.blocks.pop_front() {
+            Poll::Ready(Some(block))
+        } else {
+            inner.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
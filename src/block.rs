@@ -8,9 +8,11 @@
 
 use crate::{
     error::Error,
+    hash::Hash,
     id::{Proof, PublicId},
     network_event::NetworkEvent,
-    observation::Observation,
+    observation::{Observation, ObservationHash},
+    serialise,
     vote::Vote,
     DkgResult, DkgResultWrapper,
 };
@@ -20,8 +22,8 @@ use std::{
 };
 
 /// A struct representing a collection of votes by peers for an `Observation`.
-#[serde(bound = "")]
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
 pub struct Block<T: NetworkEvent, P: PublicId> {
     payload: Observation<T, P>,
     proofs: BTreeSet<Proof<P>>,
@@ -39,6 +41,36 @@ impl<T: NetworkEvent, P: PublicId> Block<T, P> {
         }
     }
 
+    /// Create a `Block` with no signatures for the revealed plaintext of an `EncryptedPayload`
+    pub(crate) fn new_decrypted_block(payload: T) -> Self {
+        Self {
+            payload: Observation::OpaquePayload(payload),
+            proofs: BTreeSet::new(),
+        }
+    }
+
+    /// Create a `Block` with no signatures for an `EncryptedPayload` which can never be revealed
+    pub(crate) fn new_undecryptable_block(key_gen: usize, ciphertext: Vec<u8>) -> Self {
+        Self {
+            payload: Observation::UndecryptablePayload {
+                key_gen,
+                ciphertext,
+            },
+            proofs: BTreeSet::new(),
+        }
+    }
+
+    /// Create a `Block` with no signatures for an `OpaquePayload` bigger than allowed
+    pub(crate) fn new_oversized_block(payload: &Observation<T, P>, size: usize) -> Self {
+        Self {
+            payload: Observation::OversizedPayload {
+                hash: BlockHash::new(payload),
+                size,
+            },
+            proofs: BTreeSet::new(),
+        }
+    }
+
     /// Creates a `Block` from `votes`.
     pub fn new(votes: &BTreeMap<P, Vote<T, P>>) -> Result<Self, Error> {
         let payload = if let Some(vote) = votes.values().next() {
@@ -72,6 +104,11 @@ impl<T: NetworkEvent, P: PublicId> Block<T, P> {
         &self.proofs
     }
 
+    /// Returns the hash of the payload of this block.
+    pub fn hash(&self) -> BlockHash {
+        BlockHash::new(&self.payload)
+    }
+
     /// Is this block signed by the given peer?
     pub fn is_signed_by(&self, peer_id: &P) -> bool {
         self.proofs.iter().any(|proof| proof.public_id() == peer_id)
@@ -90,6 +127,22 @@ impl<T: NetworkEvent, P: PublicId> Block<T, P> {
     }
 }
 
+/// Hash identifying a `Block` by its payload.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Debug)]
+pub struct BlockHash(Hash);
+
+impl BlockHash {
+    /// Hash of the block with the given payload.
+    pub fn new<T: NetworkEvent, P: PublicId>(payload: &Observation<T, P>) -> Self {
+        BlockHash(Hash::from(serialise(payload).as_slice()))
+    }
+
+    // Hash of the block with the payload hashed to `hash`.
+    pub(crate) fn from_observation_hash(hash: &ObservationHash) -> Self {
+        BlockHash(hash.0)
+    }
+}
+
 /// Group of blocks that were all created within the same meta-election.
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub(crate) struct BlockGroup<T: NetworkEvent, P: PublicId>(pub VecDeque<Block<T, P>>);
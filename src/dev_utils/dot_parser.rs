@@ -363,6 +363,7 @@ fn parse_event_details() -> Parser<u8, BTreeMap<String, EventDetails>> {
 #[derive(Debug)]
 struct EventDetails {
     cause: CauseInput,
+    #[allow(dead_code)]
     last_ancestors: BTreeMap<PeerId, usize>,
 }
 
@@ -389,16 +390,20 @@ fn parse_single_event_detail() -> Parser<u8, (String, EventDetails)> {
 fn parse_cause() -> Parser<u8, CauseInput> {
     let prefix = comment_prefix() * seq(b"cause: ");
     let initial = seq(b"Initial").map(|_| CauseInput::Initial);
+    let rejoined = (seq(b"Rejoined(") * parse_usize() - sym(b')')).map(CauseInput::Rejoined);
     let requesting =
         (seq(b"Requesting(") * parse_peer_id() - sym(b')')).map(CauseInput::Requesting);
     let request = seq(b"Request").map(|_| CauseInput::Request);
     let response = seq(b"Response").map(|_| CauseInput::Response);
     let observation =
         (seq(b"Observation(") * parse_observation() - sym(b')')).map(CauseInput::Observation);
+    let batch =
+        (seq(b"Batch(") * list(parse_observation(), seq(b"; ")) - sym(b')')).map(CauseInput::Batch);
     let malice = (seq(b"Observation(") * parse_accusation() - sym(b')'))
         .map(|(offender, malice_input)| CauseInput::Malice(offender, malice_input));
 
-    prefix * (initial | requesting | request | response | observation | malice) - newline()
+    prefix * (initial | rejoined | requesting | request | response | observation | malice | batch)
+        - newline()
 }
 
 fn parse_last_ancestors() -> Parser<u8, BTreeMap<PeerId, usize>> {
@@ -442,6 +447,7 @@ fn parse_hash() -> Parser<u8, Hash> {
 struct ParsedMetaElection {
     interesting_events: BTreeMap<PeerId, Vec<String>>,
     voters: BTreeSet<PeerId>,
+    #[allow(dead_code)]
     payload: Option<Observation<Transaction, PeerId>>,
     unconsensused_events: BTreeSet<String>,
     observation_map: BTreeMap<ObservationKey, Observation<Transaction, PeerId>>,
@@ -532,8 +538,7 @@ fn parse_unconsensused_events() -> Parser<u8, BTreeSet<String>> {
         * list(sym(b'"') * parse_event_id() - sym(b'"'), seq(b", "))
         - seq(b"}")
         - next_line();
-    line.opt()
-        .map(|ids| ids.into_iter().flat_map(|ids| ids).collect())
+    line.opt().map(|ids| ids.into_iter().flatten().collect())
 }
 
 fn parse_observation() -> Parser<u8, Observation<Transaction, PeerId>> {
@@ -765,6 +770,7 @@ fn parse_end() -> Parser<u8, ()> {
 
 /// The event graph and associated info that were parsed from the dumped dot file.
 pub(crate) struct ParsedContents {
+    #[cfg_attr(not(feature = "mock"), allow(dead_code))]
     pub our_id: PeerId,
     pub graph: Graph<PeerId>,
     pub meta_election: MetaElection,
@@ -809,7 +815,7 @@ impl ParsedContents {
     /// Insert event into the `ParsedContents`. Note this does not perform any validations
     /// whatsoever, so this is useful for simulating all kinds of invalid or malicious situations.
     pub fn add_event(&mut self, event: Event<PeerId>) -> EventIndex {
-        let indexed_event = self.graph.insert(event);
+        let indexed_event = unwrap!(self.graph.insert(event));
         self.peer_list.add_event(indexed_event);
 
         let start_index = indexed_event.event_index().topological_index() + 1;
@@ -820,7 +826,7 @@ impl ParsedContents {
     }
 
     #[cfg(any(all(test, feature = "mock"), feature = "testing"))]
-    pub fn event_context(&self) -> EventContextRef<Transaction, PeerId> {
+    pub fn event_context(&self) -> EventContextRef<'_, Transaction, PeerId> {
         EventContextRef {
             graph: &self.graph,
             peer_list: &self.peer_list,
@@ -882,10 +888,7 @@ fn derive_test_name_from_current_thread_name() -> String {
 
         const THREADS_ARG: &str = "--test-threads";
         let mut args_itr = env::args().skip_while(|arg| !arg.starts_with(THREADS_ARG));
-        match (
-            args_itr.next().as_ref().map(|x| &**x),
-            args_itr.next().as_ref().map(|x| &**x),
-        ) {
+        match (args_itr.next().as_deref(), args_itr.next().as_deref()) {
             (Some(THREADS_ARG), Some("1")) | (Some("--test-threads=1"), _) => {
                 panic(THREADS_ARG);
             }
@@ -1035,13 +1038,13 @@ fn convert_to_meta_election(
     let unconsensused_events_keyed_indices = unconsensused_events_indices
         .iter()
         .filter_map(|index| graph.get(*index))
-        .map(|indexed_event| {
-            (
-                indexed_event.event_index(),
-                indexed_event.payload_key().cloned(),
-            )
+        .flat_map(|indexed_event| {
+            let event_index = indexed_event.event_index();
+            indexed_event
+                .inner()
+                .payload_keys()
+                .map(move |payload_key| (event_index, *payload_key))
         })
-        .filter_map(|(event_index, payload_key)| payload_key.map(|key| (event_index, key)))
         .fold(
             FnvHashMap::default(),
             |mut map, (event_index, payload_key)| {
@@ -1058,13 +1061,19 @@ fn convert_to_meta_election(
     };
 
     MetaElection {
-        meta_events,
+        // Meta-events of events missing from a partial graph can't be looked up by index anyway.
+        meta_events: meta_events
+            .into_iter()
+            .filter(|(event_index, _)| *event_index != EventIndex::PHONY)
+            .collect(),
         voters: convert_peer_id_set(meta_election.voters, peer_list),
         interesting_events,
         unconsensused_events,
         consensus_history: meta_election.consensus_history,
         continue_consensus_start_index: 0,
         new_consensus_start_index: 0,
+        election: 0,
+        ended_elections: BTreeMap::new(),
     }
 }
 
@@ -1073,6 +1082,7 @@ fn convert_to_meta_event(meta_event: ParsedMetaEvent, peer_list: &PeerList<PeerI
 
     MetaEvent {
         observer: Observer::new(observees),
+        observer_inputs: None,
         interesting_content: meta_event.interesting_content,
         meta_votes: convert_peer_id_map(meta_event.meta_votes, peer_list),
     }
@@ -1102,7 +1112,7 @@ fn create_events(
 ) -> BTreeMap<String, EventIndex> {
     let mut event_indices = BTreeMap::new();
 
-    let graph = std::mem::replace(graph, BTreeMap::new());
+    let graph = std::mem::take(graph);
     let mut graph = in_peer_order(graph, peer_list);
 
     while !graph.is_empty() {
@@ -1156,7 +1166,7 @@ fn create_events(
             &mut parsed_contents.observations,
         );
 
-        let indexed_event_ref = parsed_contents.graph.insert(next_event);
+        let indexed_event_ref = unwrap!(parsed_contents.graph.insert(next_event));
         peer_list.add_event(indexed_event_ref);
         let event_index = indexed_event_ref.event_index();
         peer_list.record_gossiped_event_by(indexed_event_ref.creator(), event_index);
@@ -8,10 +8,6 @@
 
 #[cfg(all(test, feature = "mock"))]
 macro_rules! btree_set {
-    () => {
-        BTreeSet::new()
-    };
-
     ($($item:expr),*) => {{
         let mut set = BTreeSet::new();
         $(
@@ -199,7 +199,7 @@ impl Network {
         }
         self.msg_queue
             .entry(dst.clone())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(QueueEntry {
                 sender: src,
                 message,
@@ -566,11 +566,11 @@ impl Network {
                             new_rng(rng2),
                         )
                     })
-                    .collect_vec();;
+                    .collect_vec();
 
                 self.peers = good_peers
                     .into_iter()
-                    .chain(malicious_peers.into_iter())
+                    .chain(malicious_peers)
                     .map(|peer| (peer.id().clone(), peer))
                     .collect();
 
@@ -649,18 +649,18 @@ impl Network {
 
                 match observation {
                     ParsecObservation::Remove { ref peer_id, .. } => {
-                        if self.allow_removal_of_peer(&peer_id) {
-                            (*self.peer_mut(&peer_id)).mark_network_view_as_leaving();
+                        if self.allow_removal_of_peer(peer_id) {
+                            (*self.peer_mut(peer_id)).mark_network_view_as_leaving();
                         } else {
                             return Ok(false);
                         }
                     }
-                    ParsecObservation::Add { ref peer_id, .. } => {
+                    ParsecObservation::Add { ref peer_id, .. }
+                        if !self.peers.contains_key(peer_id) =>
+                    {
                         // If the peer to be added hasn't yet been inserted into `self.peers`, it
                         // means we should postpone voting for its addition until it is inserted.
-                        if !self.peers.contains_key(peer_id) {
-                            return Ok(false);
-                        }
+                        return Ok(false);
                     }
                     _ => (),
                 }
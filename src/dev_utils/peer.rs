@@ -208,8 +208,8 @@ impl Deref for WrappedParsec {
 
     fn deref(&self) -> &Self::Target {
         match self {
-            WrappedParsec::Good(parsec) => &parsec,
-            WrappedParsec::Malicious(MaliciousComponents { test_parsec, .. }) => &*test_parsec,
+            WrappedParsec::Good(parsec) => parsec,
+            WrappedParsec::Malicious(MaliciousComponents { test_parsec, .. }) => test_parsec,
         }
     }
 }
@@ -138,8 +138,8 @@ impl PeerStatuses {
     }
 }
 
-impl Into<BTreeMap<PeerId, PeerStatus>> for PeerStatuses {
-    fn into(self) -> BTreeMap<PeerId, PeerStatus> {
-        self.statuses
+impl From<PeerStatuses> for BTreeMap<PeerId, PeerStatus> {
+    fn from(val: PeerStatuses) -> Self {
+        val.statuses
     }
 }
@@ -143,7 +143,7 @@ impl Strategy for ScheduleOptionsStrategy {
 pub struct ScheduleOptionsValueTree {
     max_sched: ScheduleOptions,
     min_sched: ScheduleOptions,
-    generator: Box<ValueTree<Value = ScheduleOptions>>,
+    generator: Box<dyn ValueTree<Value = ScheduleOptions>>,
 }
 
 impl Bounded for ScheduleOptionsValueTree {
@@ -195,7 +195,7 @@ impl Strategy for ScheduleStrategy {
         };
         self.opts
             .new_tree(runner)
-            .and_then(|o| Ok(ScheduleValueTree::new(seed, o)))
+            .map(|o| ScheduleValueTree::new(seed, o))
     }
 }
 
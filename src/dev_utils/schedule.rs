@@ -109,6 +109,7 @@ pub enum AddPeerType {
 /// The simulation proceeds in steps. During every global step, every node has some probability
 /// of being scheduled to perform a local step, consisting of receiving messages that reached it
 /// by this time, generating appropriate responses and optionally sending a gossip request.
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug)]
 pub enum ScheduleEvent {
     /// Event storing the names of the initial nodes
@@ -182,14 +183,11 @@ impl PendingObservations {
         let peers: Vec<_> = peers.into_iter().collect();
         let peers = sample(rng, &peers, strategy);
         for peer in peers {
-            let observations = self
-                .queues
-                .entry(peer.clone())
-                .or_insert_with(BTreeMap::new);
+            let observations = self.queues.entry(peer.clone()).or_default();
             let tgt_step = step
                 + self.min_delay
                 + binomial(rng, self.max_delay - self.min_delay, self.p_delay);
-            let step_observations = observations.entry(tgt_step).or_insert_with(Vec::new);
+            let step_observations = observations.entry(tgt_step).or_default();
             step_observations.push(observation.clone());
 
             if observation.is_opaque() {
@@ -205,7 +203,7 @@ impl PendingObservations {
             let to_leave = queue.split_off(&(step + 1));
             let popped = mem::replace(queue, to_leave);
             for (_, observations) in popped {
-                result.extend(observations.into_iter());
+                result.extend(observations);
             }
             result
         } else {
@@ -215,7 +213,7 @@ impl PendingObservations {
 
     /// Returns true if no more peers have pending observations
     pub fn queues_empty<'a, I: Iterator<Item = &'a PeerId>>(&self, mut peers: I) -> bool {
-        peers.all(|id| self.queues.get(id).map_or(true, BTreeMap::is_empty))
+        peers.all(|id| self.queues.get(id).is_none_or(BTreeMap::is_empty))
     }
 
     /// Number of `OpaquePayload` observations scheduled to be made by the given peer.
@@ -454,10 +452,10 @@ impl ObservationSchedule {
     }
 
     fn extract_opaque(&mut self) -> Vec<Observation> {
-        let schedule = mem::replace(&mut self.schedule, vec![]);
+        let schedule = mem::take(&mut self.schedule);
         let (opaque, rest): (Vec<_>, _) = schedule
             .into_iter()
-            .partition(|&(_, ref observation)| observation.is_opaque());
+            .partition(|(_, observation)| observation.is_opaque());
         self.schedule = rest;
         opaque
             .into_iter()
@@ -468,7 +466,7 @@ impl ObservationSchedule {
     fn count_observations(&self) -> usize {
         self.schedule
             .iter()
-            .filter(|&(_, ref event)| match *event {
+            .filter(|(_, event)| match *event {
                 ObservationEvent::Fail(_) => false,
                 _ => true,
             })
@@ -33,6 +33,7 @@ pub enum DumpGraphContext {
     DroppingParsec,
 }
 
+#[cfg_attr(not(feature = "dump-graphs"), allow(dead_code))]
 pub(crate) struct ToFileInfo<'a, T: NetworkEvent, S: SecretId> {
     pub owner_id: &'a S::PublicId,
     pub consensus_mode: ConsensusMode,
@@ -91,18 +92,18 @@ mod detail {
     };
 
     lazy_static! {
-        static ref ROOT_DIR_PREFIX: PathBuf = { env::temp_dir().join("parsec_graphs") };
+        static ref ROOT_DIR_PREFIX: PathBuf = env::temp_dir().join("parsec_graphs");
         static ref ROOT_DIR_SUFFIX: String = {
             rand::thread_rng()
                 .gen_ascii_chars()
                 .take(6)
                 .collect::<String>()
         };
-        static ref ROOT_DIR: PathBuf = { ROOT_DIR_PREFIX.join(&*ROOT_DIR_SUFFIX) };
+        static ref ROOT_DIR: PathBuf = ROOT_DIR_PREFIX.join(&*ROOT_DIR_SUFFIX);
 
         static ref GENERATE_SVG: bool = {
             // PARSEC_DUMP_GRAPH_SVG=0 to disable svg file generation
-            env::var("PARSEC_DUMP_GRAPH_SVG").ok().map_or(true, |x| x != "0")
+            env::var("PARSEC_DUMP_GRAPH_SVG").ok().is_none_or(|x| x != "0")
         };
 
         static ref FILTER_PEERS: Option<Vec<String>> = {
@@ -149,7 +150,7 @@ mod detail {
     );
 
     thread_local!(static DUMP_COUNTS: RefCell<BTreeMap<String, usize>> =
-        RefCell::new(BTreeMap::new()));
+        const { RefCell::new(BTreeMap::new()) });
 
     /// To control the dump graph behaviour.
     /// In all modes, also dump when parsec is dropped if panicking.
@@ -231,9 +232,9 @@ mod detail {
                     secure_rng: info.secure_rng,
                     key_gens_and_next_id: info.key_gens_and_next_id,
                     observations: &DotObservation::from_observations(
-                        &info.observations,
+                        info.observations,
                         info.gossip_graph,
-                        &info.peer_list,
+                        info.peer_list,
                         &short_peer_ids,
                     ),
                     peer_ids: &peer_ids,
@@ -251,7 +252,7 @@ mod detail {
         // Try to generate an SVG file from the dot file, but we don't care about failure here.
         if *GENERATE_SVG {
             if let Ok(mut child) = Command::new("dot")
-                .args(&["-Tsvg", file_path.to_string_lossy().as_ref(), "-O"])
+                .args(["-Tsvg", file_path.to_string_lossy().as_ref(), "-O"])
                 .spawn()
             {
                 let _ = child.wait();
@@ -389,7 +390,7 @@ mod detail {
         }
 
         fn event_to_short_name(&self, event: IndexedEventRef<S::PublicId>) -> String {
-            sanitise_event_short_name(event, &self.peer_list, &self.short_peer_ids)
+            sanitise_event_short_name(event, self.peer_list, self.short_peer_ids)
         }
 
         fn writeln(&mut self, args: fmt::Arguments) -> io::Result<()> {
@@ -494,8 +495,7 @@ mod detail {
         }
 
         fn calculate_positions(&self) -> Vec<usize> {
-            let mut positions = Vec::new();
-            positions.resize(self.gossip_graph.len(), 0);
+            let mut positions = vec![0; self.gossip_graph.len()];
 
             // gossip_graph in topological order: parent processed before children:
             for event in self.gossip_graph.iter() {
@@ -637,14 +637,14 @@ mod detail {
                     let attr = EventAttributes::new(
                         event.inner(),
                         self.event_to_short_name(event),
-                        meta_events.get(&event_index),
+                        meta_events.get(event_index),
                         self.observations,
-                        &self.short_peer_ids,
+                        self.short_peer_ids,
                     );
                     self.writeln(format_args!(
-                        "  \"{}\" {}",
+                        "  \"{}\" {:?}",
                         self.event_to_short_name(event),
-                        attr.to_string()
+                        attr
                     ))?;
 
                     self.write_cause_to_dot_format(&event)?;
@@ -663,7 +663,7 @@ mod detail {
             &mut self,
             event: &IndexedEventRef<S::PublicId>,
         ) -> io::Result<()> {
-            let mut buffer;
+            let buffer;
             let cause = match event.cause() {
                 Cause::Requesting { recipient, .. } => {
                     buffer = format!(
@@ -691,7 +691,24 @@ mod detail {
                         "Observation(?)"
                     }
                 }
+                Cause::Batch { ref votes, .. } => {
+                    buffer = format!(
+                        "Batch({})",
+                        votes
+                            .iter()
+                            .map(|vote| match self.observations.get(vote.payload_key()) {
+                                Some(observation) => format!("{:?}", observation),
+                                None => "?".to_string(),
+                            })
+                            .join("; ")
+                    );
+                    buffer.as_str()
+                }
                 Cause::Initial => "Initial",
+                Cause::Rejoined { incarnation } => {
+                    buffer = format!("Rejoined({})", incarnation);
+                    buffer.as_str()
+                }
             };
 
             writeln!(&mut self.file, "/// cause: {}", cause)
@@ -783,7 +800,7 @@ mod detail {
                 .meta_events
                 .iter()
                 .filter_map(|(index, mev)| {
-                    let event = self.gossip_graph.get(*index)?;
+                    let event = self.gossip_graph.get(index)?;
                     let creator_id = self.peer_ids.get(event.creator())?;
 
                     let creator_and_index = (creator_id, event.index_by_creator());
@@ -832,7 +849,7 @@ mod detail {
                     ));
                     self.indent();
                     lines.extend(
-                        dump_meta_votes(&self.short_peer_ids, &mev.meta_votes, true)
+                        dump_meta_votes(self.short_peer_ids, &mev.meta_votes, true)
                             .into_iter()
                             .map(|s| format!("{}{}{}", Self::COMMENT, self.indentation(), s)),
                     );
@@ -896,7 +913,7 @@ mod detail {
                 attr.label
             );
 
-            if let Some(event_payload) = event.payload_key().and_then(|key| observations.get(key)) {
+            for event_payload in event.payload_keys().filter_map(|key| observations.get(key)) {
                 attr.label = format!(
                     "{}<tr><td colspan=\"6\">{:?}</td></tr>\n",
                     attr.label, event_payload
@@ -935,9 +952,12 @@ mod detail {
             attr.label = format!("{}</table>", attr.label);
             attr
         }
+    }
 
-        fn to_string(&self) -> String {
-            format!(
+    impl Debug for EventAttributes {
+        fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+            write!(
+                formatter,
                 "[{}, {}label=<{}>]",
                 self.fillcolor,
                 if self.is_rectangle {
@@ -993,7 +1013,7 @@ mod detail {
                 .iter()
                 .map(|(key, info)| {
                     let observation = DotObservation::new(
-                        &key,
+                        key,
                         &info.observation,
                         graph,
                         peer_list,
@@ -1031,6 +1051,27 @@ mod detail {
                     dkg_result,
                 } => format!("DkgResult({:?}, {:?})", participants, dkg_result),
                 Observation::DkgMessage(msg) => format!("DkgMessage({:?})", msg),
+                Observation::EncryptedPayload { key_gen, .. } => {
+                    format!("EncryptedPayload({}, {:?})", key_gen, key.hash())
+                }
+                Observation::UndecryptablePayload { key_gen, .. } => {
+                    format!("UndecryptablePayload({}, {:?})", key_gen, key.hash())
+                }
+                Observation::OversizedPayload { size, .. } => {
+                    format!("OversizedPayload({}, {:?})", size, key.hash())
+                }
+                Observation::DecryptionShare(msg) => format!("{:?}", msg),
+                Observation::Split { left, right } => format!("Split({:?}, {:?})", left, right),
+                Observation::Merge { other_section, .. } => format!("Merge({:?})", other_section),
+                Observation::SetParameters(parameters) => {
+                    format!("SetParameters({:?})", parameters)
+                }
+                Observation::Retract(observation) => format!("Retract({:?})", observation),
+                Observation::RotateKey { old, new, .. } => format!(
+                    "RotateKey({:?} -> {:?})",
+                    sanitise_peer_id(old),
+                    sanitise_peer_id(new)
+                ),
                 Observation::OpaquePayload(payload) => {
                     let max_length = 16;
                     let mut payload_str = sanitise_string(format!("{:?}", payload));
@@ -1086,7 +1127,8 @@ mod detail {
                 get_short_name_by_hash(event_hash),
                 write_malice_to_string(boxed_malice, graph, peer_list, short_peer_ids),
             ),
-            _ => panic!("unsupported yet"),
+            // The dot parser can't read these back yet, but they are still worth looking at.
+            _ => format!("{:?}", malice),
         }
     }
 
@@ -1179,7 +1221,7 @@ mod detail {
     fn find_mismatch(s1: &[u8], s2: &[u8]) -> usize {
         s1.iter()
             .enumerate()
-            .position(|(index, c)| s2.get(index) != Some(&c))
+            .position(|(index, c)| s2.get(index) != Some(c))
             .unwrap_or_else(|| cmp::min(s1.len(), s2.len()))
     }
 
@@ -1247,10 +1289,7 @@ mod detail {
             //
             // Act
             //
-            let actual = names
-                .iter()
-                .map(|ids| short_peer_id_names(&ids))
-                .collect::<Vec<_>>();
+            let actual = names.iter().map(short_peer_id_names).collect::<Vec<_>>();
 
             //
             // Assert
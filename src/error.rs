@@ -55,6 +55,36 @@ pub enum Error {
     DuplicateMessage,
     /// Faild DKG process
     FailedDkg,
+    /// No DKG has completed yet, so there is no section key to encrypt to.
+    MissingSectionKey,
+    /// Failed to seal or unseal a `DkgResult`.
+    SealingFailure,
+    /// Our node is already rotating its key.
+    KeyRotationInProgress,
+    /// The section has split, so this instance takes no more votes.
+    SectionSplit,
+    /// No section split has been consensused yet.
+    MissingSplit,
+    /// Our node is on neither side of the section split.
+    NotInSplit,
+    /// The section has merged, so this instance takes no more votes.
+    SectionMerged,
+    /// No section merge has been consensused yet.
+    MissingMerge,
+    /// The other section's `Merge` doesn't mirror ours.
+    InvalidMerge,
+    /// The payload is bigger than allowed by the consensus parameters.
+    PayloadTooLarge,
+    /// The observation has already been consensused.
+    AlreadyConsensused,
+    /// Failed to read or write the event log, or it doesn't match this instance.
+    EventLog,
+    /// The event store given to `Parsec::set_event_store` isn't empty.
+    EventStore,
+    /// A memory limit set by `Parsec::set_limits` has been reached.
+    Backpressure,
+    /// The peer hashes with a different algorithm than ours.
+    HashAlgorithmMismatch,
     /// Logic error.
     Logic,
 }
@@ -101,6 +131,23 @@ impl Display for Error {
             Error::InvalidMessage => write!(f, "This non-empty message is invalid."),
             Error::DuplicateMessage => write!(f, "This message has already been handled."),
             Error::FailedDkg => write!(f, "The requested DKG could not proceed."),
+            Error::MissingSectionKey => write!(f, "No DKG has completed yet."),
+            Error::SealingFailure => write!(f, "Failed to seal or unseal the DKG result."),
+            Error::KeyRotationInProgress => write!(f, "Our node is already rotating its key."),
+            Error::SectionSplit => write!(f, "The section has split: use the split instance."),
+            Error::MissingSplit => write!(f, "No section split has been consensused yet."),
+            Error::NotInSplit => write!(f, "Our node is on neither side of the section split."),
+            Error::SectionMerged => write!(f, "The section has merged: use the joint instance."),
+            Error::MissingMerge => write!(f, "No section merge has been consensused yet."),
+            Error::InvalidMerge => write!(f, "The other section's merge doesn't mirror ours."),
+            Error::PayloadTooLarge => write!(f, "The payload is too large."),
+            Error::AlreadyConsensused => write!(f, "The observation is already consensused."),
+            Error::EventLog => write!(f, "The event log is unusable."),
+            Error::EventStore => write!(f, "The event store isn't empty."),
+            Error::Backpressure => write!(f, "A memory limit has been reached."),
+            Error::HashAlgorithmMismatch => {
+                write!(f, "The peer hashes with a different algorithm than ours.")
+            }
             Error::Logic => write!(
                 f,
                 "This is a logic error and represents a flaw in the code."
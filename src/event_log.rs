@@ -0,0 +1,108 @@
+// Copyright 2019 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    error::{Error, Result},
+    gossip::PackedEvent,
+    id::PublicId,
+    network_event::NetworkEvent,
+    serialise,
+};
+use maidsafe_utilities::serialisation;
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// Append-only file holding the gossip events of a `Parsec` instance, in the order they were added
+/// to its graph. See `Parsec::set_event_log`.
+///
+/// Each entry is the serialised `PackedEvent`, prefixed by its length as a little-endian `u64`.
+pub struct EventLog {
+    file: File,
+    // Whether some entries were written since the last sync to disk.
+    dirty: bool,
+}
+
+impl EventLog {
+    /// Opens the log stored at `path`, creating an empty one if there is no such file.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        Ok(Self { file, dirty: false })
+    }
+
+    // Reads all the entries of the log. An incomplete last entry, left by a crash in the middle of
+    // writing it, is dropped from the file.
+    pub(crate) fn read<T: NetworkEvent, P: PublicId>(&mut self) -> Result<Vec<PackedEvent<T, P>>> {
+        let _ = self
+            .file
+            .seek(SeekFrom::Start(0))
+            .map_err(|_| Error::EventLog)?;
+
+        let mut events = Vec::new();
+        let mut end = 0;
+        loop {
+            let mut len = [0; 8];
+            match self.file.read_exact(&mut len) {
+                Ok(()) => (),
+                Err(ref error) if error.kind() == ErrorKind::UnexpectedEof => break,
+                Err(_) => return Err(Error::EventLog),
+            }
+
+            let len = u64::from_le_bytes(len);
+            let mut bytes = Vec::new();
+            let _ = (&mut self.file)
+                .take(len)
+                .read_to_end(&mut bytes)
+                .map_err(|_| Error::EventLog)?;
+            if (bytes.len() as u64) < len {
+                break;
+            }
+
+            events.push(serialisation::deserialise(&bytes).map_err(|_| Error::EventLog)?);
+            end += 8 + len;
+        }
+
+        self.file.set_len(end).map_err(|_| Error::EventLog)?;
+        let _ = self
+            .file
+            .seek(SeekFrom::End(0))
+            .map_err(|_| Error::EventLog)?;
+        Ok(events)
+    }
+
+    // Appends `event` to the log. It isn't guaranteed to be on disk until the next `sync`.
+    pub(crate) fn append<T: NetworkEvent, P: PublicId>(
+        &mut self,
+        event: &PackedEvent<T, P>,
+    ) -> Result<()> {
+        let bytes = serialise(event);
+        self.file
+            .write_all(&(bytes.len() as u64).to_le_bytes())
+            .and_then(|()| self.file.write_all(&bytes))
+            .map_err(|_| Error::EventLog)?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    // Makes sure all the appended entries are on disk.
+    pub(crate) fn sync(&mut self) -> Result<()> {
+        if self.dirty {
+            self.file.sync_data().map_err(|_| Error::EventLog)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+}
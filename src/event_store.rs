@@ -0,0 +1,245 @@
+// Copyright 2019 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    gossip::{Event, EventHash},
+    id::PublicId,
+    serialise,
+};
+use maidsafe_utilities::serialisation;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Debug, Formatter},
+    fs::{File, OpenOptions},
+    io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    path::Path,
+};
+
+/// Storage backend for the events of the gossip graph of a `Parsec` instance, so that histories
+/// too large for the memory can be handled. See `Parsec::set_event_store`.
+///
+/// Events are addressed by their index, which is the order they were added to the graph in, and
+/// which is a topological order: the parents of an event always have lower indices. Unlike the
+/// `EventLog`, the store only holds the graph while the instance is running: it doesn't have to
+/// survive a restart.
+pub trait EventStore<P: PublicId> {
+    /// Appends `event`, whose index is the number of events stored before it.
+    fn insert(&mut self, event: &StoredEvent<P>) -> io::Result<()>;
+
+    /// Removes the last event, and returns it.
+    fn pop(&mut self) -> io::Result<Option<StoredEvent<P>>>;
+
+    /// Replaces the event at `index` with `event`, which has the same hash.
+    fn replace(&mut self, index: usize, event: &StoredEvent<P>) -> io::Result<()>;
+
+    /// Gets the event at `index`, if it exists.
+    fn get(&self, index: usize) -> io::Result<Option<StoredEvent<P>>>;
+
+    /// Gets the index of the event with the given hash, if it exists.
+    fn index_of(&self, hash: &EventHash) -> Option<usize>;
+
+    /// Number of events stored.
+    fn len(&self) -> usize;
+
+    /// Returns whether no events are stored.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the event with the given hash, along with its index, if it exists.
+    fn get_by_hash(&self, hash: &EventHash) -> io::Result<Option<(usize, StoredEvent<P>)>> {
+        match self.index_of(hash) {
+            Some(index) => Ok(self.get(index)?.map(|event| (index, event))),
+            None => Ok(None),
+        }
+    }
+
+    /// Iterator over the event at `index` and all its ancestors, along with their indices, in
+    /// reverse topological order (children before parents).
+    fn ancestors<'a>(&'a self, index: usize) -> StoredAncestors<'a, P>
+    where
+        Self: Sized,
+    {
+        StoredAncestors::new(self, index)
+    }
+}
+
+/// Event of the gossip graph, as held by an `EventStore`.
+///
+/// It is serialisable so that the store can keep it in any form. Its cached properties, such as
+/// its hash and the info about its ancestors, are serialised along with it: they can't be
+/// recomputed without its parents.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct StoredEvent<P: PublicId>(pub(crate) Event<P>);
+
+impl<P: PublicId> StoredEvent<P> {
+    /// Hash of this event.
+    pub fn hash(&self) -> &EventHash {
+        self.0.hash()
+    }
+
+    /// Index of the self-parent of this event, if any.
+    pub fn self_parent(&self) -> Option<usize> {
+        self.0.self_parent().map(|index| index.topological_index())
+    }
+
+    /// Index of the other-parent of this event, if any.
+    pub fn other_parent(&self) -> Option<usize> {
+        self.0.other_parent().map(|index| index.topological_index())
+    }
+}
+
+impl<P: PublicId> Debug for StoredEvent<P> {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        self.0.fmt(formatter)
+    }
+}
+
+/// Iterator over the ancestors of an event held by an `EventStore`. See `EventStore::ancestors`.
+///
+/// A failure to read the store is yielded as an error, after which the iteration stops.
+pub struct StoredAncestors<'a, P: PublicId> {
+    store: &'a dyn EventStore<P>,
+    // Indices of the events to visit, the highest first so children come before their parents.
+    queue: BTreeSet<usize>,
+}
+
+impl<'a, P: PublicId> StoredAncestors<'a, P> {
+    /// Creates an iterator over the event at `index` in `store` and all its ancestors.
+    pub fn new(store: &'a dyn EventStore<P>, index: usize) -> Self {
+        let mut queue = BTreeSet::new();
+        let _ = queue.insert(index);
+        Self { store, queue }
+    }
+}
+
+impl<'a, P: PublicId> Iterator for StoredAncestors<'a, P> {
+    type Item = io::Result<(usize, StoredEvent<P>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = *self.queue.iter().next_back()?;
+        let _ = self.queue.remove(&index);
+
+        let event = match self.store.get(index) {
+            Ok(Some(event)) => event,
+            Ok(None) => {
+                self.queue.clear();
+                return Some(Err(io::Error::from(ErrorKind::NotFound)));
+            }
+            Err(error) => {
+                self.queue.clear();
+                return Some(Err(error));
+            }
+        };
+        self.queue
+            .extend(event.self_parent().into_iter().chain(event.other_parent()));
+        Some(Ok((index, event)))
+    }
+}
+
+/// `EventStore` keeping the events in a scratch file.
+///
+/// Events are only ever appended to the file: a replaced one is written again at its end. Only
+/// the position of each event in the file and its hash are kept in memory.
+pub struct FileEventStore<P: PublicId> {
+    file: File,
+    // Offset and length of each event in the file.
+    records: Vec<(u64, u64)>,
+    indices: BTreeMap<EventHash, usize>,
+    // Offset of the end of the last event written.
+    end: u64,
+    _phantom: PhantomData<P>,
+}
+
+impl<P: PublicId> FileEventStore<P> {
+    /// Creates an empty store in the file at `path`, truncating it if it exists.
+    pub fn create<Q: AsRef<Path>>(path: Q) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        Ok(Self {
+            file,
+            records: Vec::new(),
+            indices: BTreeMap::new(),
+            end: 0,
+            _phantom: PhantomData,
+        })
+    }
+
+    fn append(&mut self, event: &StoredEvent<P>) -> io::Result<(u64, u64)> {
+        let record = serialise(event);
+        let offset = self.end;
+        let _ = self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(&record)?;
+        self.end += record.len() as u64;
+        Ok((offset, record.len() as u64))
+    }
+
+    fn read(&self, (offset, len): (u64, u64)) -> io::Result<StoredEvent<P>> {
+        let mut file = &self.file;
+        let _ = file.seek(SeekFrom::Start(offset))?;
+        let mut record = vec![0; len as usize];
+        file.read_exact(&mut record)?;
+        serialisation::deserialise(&record)
+            .map_err(|error| io::Error::new(ErrorKind::InvalidData, error))
+    }
+}
+
+impl<P: PublicId> EventStore<P> for FileEventStore<P> {
+    fn insert(&mut self, event: &StoredEvent<P>) -> io::Result<()> {
+        let position = self.append(event)?;
+        let _ = self.indices.insert(*event.hash(), self.records.len());
+        self.records.push(position);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> io::Result<Option<StoredEvent<P>>> {
+        let (offset, len) = match self.records.last() {
+            Some(position) => *position,
+            None => return Ok(None),
+        };
+        let event = self.read((offset, len))?;
+        let _ = self.records.pop();
+        let _ = self.indices.remove(event.hash());
+        // Reclaim the space if it was the last event written.
+        if offset + len == self.end {
+            self.file.set_len(offset)?;
+            self.end = offset;
+        }
+        Ok(Some(event))
+    }
+
+    fn replace(&mut self, index: usize, event: &StoredEvent<P>) -> io::Result<()> {
+        if index >= self.records.len() {
+            return Err(io::Error::from(ErrorKind::NotFound));
+        }
+        self.records[index] = self.append(event)?;
+        Ok(())
+    }
+
+    fn get(&self, index: usize) -> io::Result<Option<StoredEvent<P>>> {
+        match self.records.get(index) {
+            Some(position) => self.read(*position).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn index_of(&self, hash: &EventHash) -> Option<usize> {
+        self.indices.get(hash).cloned()
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+}
@@ -7,18 +7,27 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use crate::{
-    block::Block,
+    block::{Block, BlockHash},
     dev_utils::{new_common_rng, new_rng, parse_test_dot_file, Record, RngChoice, TestIterator},
     error::Error,
-    gossip::{Event, Graph, GraphSnapshot},
-    id::{Proof, PublicId},
+    event_log::EventLog,
+    event_store::{EventStore, FileEventStore, StoredEvent},
+    gossip::{Event, EventIndex, Graph, GraphSnapshot, PackedEvent},
+    handle::ParsecHandle,
+    hash::Hash,
+    id::{KeyRotationProof, Proof, PublicId},
+    inactivity::InactivityPolicy,
+    limits::Limits,
     meta_voting::MetaElectionSnapshot,
     mock::{self, PeerId, Transaction},
-    observation::{ConsensusMode, Observation},
-    parsec::TestParsec,
-    peer_list::{PeerListSnapshot, PeerState},
+    observation::{ConsensusMode, Observation, Parameters},
+    parsec::{Parsec, TestParsec},
+    peer_list::{Peer, PeerIndex, PeerListSnapshot, PeerState},
+    serialise,
 };
-use std::collections::BTreeSet;
+use maidsafe_utilities::serialisation::deserialise;
+use rand::Rng;
+use std::{cell::RefCell, cmp, collections::BTreeSet, env, fs, iter, mem, process, rc::Rc};
 
 // Use Fixed seed for functional tests: No randomization.
 static SEED: RngChoice = RngChoice::SeededXor([1, 2, 3, 4]);
@@ -170,11 +179,11 @@ fn from_genesis() {
     // initial event + genesis_observation
     assert_eq!(parsec.graph().len(), 2);
     let initial_event = nth_event(parsec.graph(), 0);
-    assert_eq!(*parsec.event_creator_id(&initial_event), our_id);
+    assert_eq!(*parsec.event_creator_id(initial_event), our_id);
     assert!(initial_event.is_initial());
     let genesis_observation = nth_event(parsec.graph(), 1);
-    assert_eq!(*parsec.event_creator_id(&genesis_observation), our_id);
-    match parsec.event_payload(&genesis_observation) {
+    assert_eq!(*parsec.event_creator_id(genesis_observation), our_id);
+    match parsec.event_payload(genesis_observation) {
         Some(payload) => {
             assert_eq!(
                 *payload,
@@ -206,6 +215,13 @@ fn from_genesis_requires_the_genesis_group_contains_us() {
     );
 }
 
+// Whether both graphs hold equal events at equal indices.
+fn same_events(lhs: &Graph<PeerId>, rhs: &Graph<PeerId>) -> bool {
+    lhs.iter()
+        .map(|event| event.inner())
+        .eq(rhs.iter().map(|event| event.inner()))
+}
+
 #[test]
 fn from_parsed_contents() {
     let mut common_rng = new_common_rng(SEED);
@@ -213,14 +229,17 @@ fn from_parsed_contents() {
     let parsed_contents = parse_test_dot_file(input_file);
     let parsed_contents_comparison = parse_test_dot_file(input_file);
     let parsec = TestParsec::from_parsed_contents(parsed_contents, new_rng(&mut common_rng));
-    assert_eq!(parsed_contents_comparison.graph, *parsec.graph());
+    assert!(same_events(
+        &parsed_contents_comparison.graph,
+        parsec.graph()
+    ));
     assert_eq!(
         parsed_contents_comparison.meta_election,
         *parsec.meta_election()
     );
 
     let parsed_contents_other = parse_test_dot_file("1.dot");
-    assert_ne!(parsed_contents_other.graph, *parsec.graph());
+    assert!(!same_events(&parsed_contents_other.graph, parsec.graph()));
     assert_ne!(parsed_contents_other.meta_election, *parsec.meta_election());
 }
 
@@ -350,6 +369,201 @@ fn remove_peer() {
     );
 }
 
+#[test]
+fn rejoin_removed_peer() {
+    let mut common_rng = new_common_rng(SEED);
+    let mut parsed_contents = parse_test_dot_file("alice.dot");
+
+    // The final decision to remove Eric is reached in the last event of Alice.
+    let a_last = unwrap!(parsed_contents.remove_last_event());
+
+    let mut alice = TestParsec::from_parsed_contents(parsed_contents, new_rng(&mut common_rng));
+    unwrap!(alice.add_event(a_last));
+
+    let eric_id = PeerId::new("Eric");
+    let old_index = unwrap!(alice.peer_list().get_index(&eric_id));
+    let old_events: Vec<_> = alice.peer_list().peer_events(old_index).collect();
+    assert!(unwrap!(alice.peer_list().get(old_index))
+        .removal_event()
+        .is_some());
+
+    // Eric rejoins with the same id. He gets a fresh index, while his old events stay with the
+    // old one.
+    alice.add_peer(
+        eric_id.clone(),
+        PeerState::VOTE | PeerState::SEND | PeerState::RECV,
+    );
+    let new_index = unwrap!(alice.peer_list().get_index(&eric_id));
+    assert_ne!(new_index, old_index);
+    assert_eq!(alice.peer_list().peer_events(new_index).count(), 0);
+    assert_eq!(
+        alice.peer_list().peer_events(old_index).collect::<Vec<_>>(),
+        old_events
+    );
+    assert!(alice.create_gossip(&eric_id).is_ok());
+}
+
+#[test]
+fn rejoin_removed_peer_and_receive_its_old_events() {
+    let mut common_rng = new_common_rng(SEED);
+    let mut peers = create_genesis_peers(3, ConsensusMode::Supermajority, &mut common_rng);
+    let dave_id = PeerId::new("Dave");
+
+    // Dave is added, removed before any of his events reaches the others, and added again.
+    let observations = vec![
+        Observation::Add {
+            peer_id: dave_id.clone(),
+            related_info: vec![],
+        },
+        Observation::Remove {
+            peer_id: dave_id.clone(),
+            related_info: vec![],
+        },
+        Observation::Add {
+            peer_id: dave_id.clone(),
+            related_info: b"rejoin".to_vec(),
+        },
+    ];
+    let mut indices = vec![];
+    for observation in &observations {
+        for peer in &mut peers {
+            unwrap!(peer.vote_for(observation.clone()));
+        }
+        gossip_until(&mut peers, |peer| {
+            peer.consensused_blocks()
+                .any(|block| block.payload() == observation)
+        });
+        indices.push(unwrap!(peers[0].peer_list().get_index(&dave_id)));
+    }
+    let old_index = indices[0];
+    let new_index = indices[2];
+    assert_eq!(indices[1], old_index);
+    assert_ne!(new_index, old_index);
+
+    // Only now do the events Dave created in his first membership reach Alice. They go to his
+    // old chain, starting with his initial event, which has no self-parent to follow.
+    let old_initial = PackedEvent::new_initial(dave_id.clone());
+    let old_vote = PackedEvent::new_observation(
+        dave_id.clone(),
+        old_initial.compute_hash(),
+        Observation::OpaquePayload(Transaction::new("old")),
+    );
+    unwrap!(peers[0].unpack_and_add_event(old_initial));
+    unwrap!(peers[0].unpack_and_add_event(old_vote));
+
+    assert_eq!(peers[0].peer_list().peer_events(old_index).count(), 2);
+    assert_eq!(peers[0].peer_list().peer_events(new_index).count(), 0);
+    assert!(peers[0]
+        .graph()
+        .iter()
+        .filter(|event| event.creator() == old_index)
+        .all(|event| !event.descends_from_fork(old_index)));
+}
+
+#[test]
+fn rejoin_with_a_new_instance() {
+    let mut common_rng = new_common_rng(SEED);
+    let genesis_ids: BTreeSet<_> = mock::create_ids(4).into_iter().collect();
+    let mut peers = create_peers(&genesis_ids, ConsensusMode::Supermajority, &mut common_rng);
+    let dave_id = PeerId::new("Dave");
+
+    let has_block = |peer: &TestPeer, observation: &Observation<Transaction, PeerId>| {
+        peer.consensused_blocks()
+            .any(|block| block.payload() == observation)
+    };
+
+    // Dave takes part in the consensus of `old`, then gets removed.
+    let old = Observation::OpaquePayload(Transaction::new("old"));
+    let remove = Observation::Remove {
+        peer_id: dave_id.clone(),
+        related_info: vec![],
+    };
+    for peer in &mut peers {
+        unwrap!(peer.vote_for(old.clone()));
+        unwrap!(peer.vote_for(remove.clone()));
+    }
+    gossip_until(&mut peers, |peer| {
+        has_block(peer, &old) && has_block(peer, &remove)
+    });
+    let old_dave = unwrap!(peers.pop());
+    assert!(old_dave.has_left());
+    let num_old_events = old_dave.peer_list().our_events().count();
+
+    // Dave is added again, and rejoins with a new instance.
+    let add = Observation::Add {
+        peer_id: dave_id.clone(),
+        related_info: vec![],
+    };
+    for peer in &mut peers {
+        unwrap!(peer.vote_for(add.clone()));
+    }
+    gossip_until(&mut peers, |peer| has_block(peer, &add));
+    let section = peers.iter().map(|peer| peer.our_pub_id().clone()).collect();
+    peers.push(TestParsec::from(Parsec::rejoin_existing(
+        dave_id.clone(),
+        &genesis_ids,
+        &section,
+        1,
+        ConsensusMode::Supermajority,
+        new_rng(&mut common_rng),
+    )));
+
+    // Dave can vote once he has replayed his removal and addition.
+    let new = Observation::OpaquePayload(Transaction::new("new"));
+    gossip_until(&mut peers, |peer| {
+        peer.peer_list().our_events().next().is_some()
+    });
+    for peer in &mut peers {
+        unwrap!(peer.vote_for(new.clone()));
+    }
+    gossip_until(&mut peers, |peer| has_block(peer, &new));
+
+    // Everyone, Dave included, outputs the same blocks, and nobody gets accused.
+    let payloads: Vec<Vec<_>> = peers
+        .iter()
+        .map(|peer| {
+            peer.consensused_blocks()
+                .map(|block| block.payload().clone())
+                .collect()
+        })
+        .collect();
+    let num_common = unwrap!(payloads.iter().map(Vec::len).min());
+    for peer_payloads in &payloads {
+        assert_eq!(peer_payloads[..num_common], payloads[0][..num_common]);
+        assert!(peer_payloads
+            .iter()
+            .all(|payload| !matches!(*payload, Observation::Accusation { .. })));
+    }
+
+    // Dave's chains of each membership start with their own initial event, everywhere.
+    for peer in &peers {
+        let old_index = unwrap!(peer.peer_list().membership_index(&dave_id, 0));
+        let new_index = unwrap!(peer.peer_list().membership_index(&dave_id, 1));
+        assert_ne!(old_index, new_index);
+        assert_eq!(
+            peer.peer_list().peer_events(old_index).count(),
+            num_old_events
+        );
+
+        for (index, incarnation) in &[(old_index, 0), (new_index, 1)] {
+            let first = unwrap!(peer.peer_list().peer_events(*index).next());
+            let first = unwrap!(peer.graph().get(first));
+            assert_eq!(first.cause().incarnation(), Some(*incarnation));
+            assert!(peer
+                .graph()
+                .iter()
+                .filter(|event| event.creator() == *index)
+                .all(|event| !event.descends_from_fork(*index)));
+        }
+    }
+    assert_eq!(
+        peers[3].peer_list().membership_index(&dave_id, 1),
+        Some(PeerIndex::OUR)
+    );
+}
+
+// The consensus in the dot file depends on its events' SHA3-256 hashes.
+#[cfg(not(feature = "blake2b"))]
 #[test]
 fn unpolled_observations() {
     let mut common_rng = new_common_rng(SEED);
@@ -417,6 +631,1359 @@ fn unpolled_observations() {
     assert!(unpolled_observations.next().is_none());
 }
 
+#[test]
+fn request_leave() {
+    let mut common_rng = new_common_rng(SEED);
+    let mut peers = create_genesis_peers(4, ConsensusMode::Supermajority, &mut common_rng);
+
+    // Only Alice votes for this one, so it can't be consensused.
+    let vote = Observation::OpaquePayload(Transaction::new("ABCD"));
+    unwrap!(peers[0].vote_for(vote.clone()));
+
+    // Nothing is abandoned until we request to leave.
+    assert!(peers[0].our_abandoned_observations().next().is_none());
+
+    unwrap!(peers[0].request_leave());
+    assert_eq!(peers[0].request_leave(), Err(Error::DuplicateVote));
+
+    let alice_id = peers[0].our_pub_id().clone();
+    let remove_alice = Observation::Remove {
+        peer_id: alice_id.clone(),
+        related_info: vec![],
+    };
+    assert!(peers[0].have_voted_for(&remove_alice));
+    assert!(!peers[0].has_left());
+
+    // Our own removal is not reported as abandoned.
+    let abandoned: Vec<_> = peers[0].our_abandoned_observations().cloned().collect();
+    assert!(abandoned.contains(&vote));
+    assert!(!abandoned.contains(&remove_alice));
+
+    // The others agree to let Alice go, and gossip until everyone has consensused her removal.
+    for peer in &mut peers[1..] {
+        unwrap!(peer.vote_for(remove_alice.clone()));
+    }
+    let has_removed_alice = |peer: &TestPeer| {
+        peer.consensused_blocks()
+            .any(|block| *block.payload() == remove_alice)
+    };
+    gossip_until(&mut peers, has_removed_alice);
+
+    // Alice is told she has left, with the removal as her last block, and her vote is abandoned.
+    assert!(peers[0].has_left());
+    assert_eq!(
+        peers[0].consensused_blocks().last().map(Block::payload),
+        Some(&remove_alice)
+    );
+    assert_eq!(
+        peers[0].our_abandoned_observations().collect::<Vec<_>>(),
+        vec![&vote]
+    );
+
+    // The remaining peers no longer count her as a voter, nor gossip with her.
+    for peer in &mut peers[1..] {
+        assert!(!peer.has_left());
+        assert!(peer
+            .peer_list()
+            .voters()
+            .all(|(_, voter)| *voter.id() != alice_id));
+        assert!(peer.gossip_recipients().all(|id| *id != alice_id));
+        assert!(peer.create_gossip(&alice_id).is_err());
+    }
+}
+
+#[test]
+fn rotate_key() {
+    let mut common_rng = new_common_rng(SEED);
+    let mut peers = create_genesis_peers(4, ConsensusMode::Supermajority, &mut common_rng);
+
+    let old_id = peers[0].our_pub_id().clone();
+    let new_id = PeerId::new("Rotated");
+    let rotate_key = Observation::RotateKey {
+        old: old_id.clone(),
+        new: new_id.clone(),
+        proof: KeyRotationProof::new(&old_id, &new_id),
+    };
+    assert_eq!(
+        peers[0].vote_for_batch(vec![
+            rotate_key,
+            Observation::OpaquePayload(Transaction::new("A")),
+        ]),
+        Err(Error::InvalidEvent)
+    );
+
+    unwrap!(peers[0].rotate_key(new_id.clone()));
+    assert_eq!(
+        peers[0].rotate_key(PeerId::new("Other")),
+        Err(Error::KeyRotationInProgress)
+    );
+
+    // Bob receives the vote, but doesn't accept the new key until the rotation is consensused.
+    let bob_id = peers[1].our_pub_id().clone();
+    let request = unwrap!(peers[0].create_gossip(&bob_id));
+    let response = unwrap!(peers[1].handle_request(&old_id, request));
+    unwrap!(peers[0].handle_response(&bob_id, response));
+    assert!(peers[1].peer_list().get_index(&new_id).is_none());
+
+    let is_rotated = |peer: &TestPeer| {
+        if *peer.our_pub_id() == new_id {
+            return true;
+        }
+
+        let index = peer.peer_list().get_index(&new_id);
+        index.is_some()
+            && index == peer.peer_list().get_index(&old_id)
+            && index
+                .and_then(|index| peer.peer_list().get(index))
+                .map(Peer::id)
+                == Some(&new_id)
+    };
+    gossip_until(&mut peers, is_rotated);
+
+    // The rotated peer kept its event chain and voting rights.
+    assert!(peers[0].peer_list().our_state().can_vote());
+    assert!(peers[1].graph().iter().any(|event| {
+        event.creator() != PeerIndex::OUR
+            && peers[1]
+                .peer_list()
+                .id_at(event.creator(), event.index_by_creator())
+                == Some(&old_id)
+    }));
+}
+
+#[test]
+fn inactivity_policy() {
+    let mut common_rng = new_common_rng(SEED);
+    let mut peers = create_genesis_peers(5, ConsensusMode::Supermajority, &mut common_rng);
+    let silent_id = unwrap!(peers.pop()).our_pub_id().clone();
+
+    // Alice vetoes the first decision, then lets the vote through.
+    let decisions = Rc::new(RefCell::new(vec![]));
+    let hook_decisions = Rc::clone(&decisions);
+    peers[0].set_inactivity_policy(Some(
+        InactivityPolicy::new(3)
+            .with_grace_period(2)
+            .with_hook(move |peer_id: &PeerId, silent| {
+                hook_decisions.borrow_mut().push((peer_id.clone(), silent));
+                hook_decisions.borrow().len() > 1
+            }),
+    ));
+
+    let remove_silent = Observation::Remove {
+        peer_id: silent_id.clone(),
+        related_info: vec![],
+    };
+
+    // The peers gossip among themselves only, so no new event of the silent one reaches Alice.
+    let alice_id = peers[0].our_pub_id().clone();
+    for _ in 0..4 {
+        for dst in 1..peers.len() {
+            let dst_id = peers[dst].our_pub_id().clone();
+            let request = unwrap!(peers[0].create_gossip(&dst_id));
+            let response = unwrap!(peers[dst].handle_request(&alice_id, request));
+            unwrap!(peers[0].handle_response(&dst_id, response));
+        }
+    }
+
+    assert!(peers[0].have_voted_for(&remove_silent));
+    let decisions = decisions.borrow();
+    assert_eq!(decisions.len(), 2);
+    assert!(decisions
+        .iter()
+        .all(|(peer_id, silent)| *peer_id == silent_id && *silent >= 3));
+
+    // Only the silent peer is deemed unresponsive.
+    for peer in &peers[1..] {
+        assert!(!peers[0].have_voted_for(&Observation::Remove {
+            peer_id: peer.our_pub_id().clone(),
+            related_info: vec![],
+        }));
+    }
+}
+
+#[test]
+fn inactivity_policy_after_split() {
+    let mut common_rng = new_common_rng(SEED);
+    let mut peers = create_genesis_peers(5, ConsensusMode::Supermajority, &mut common_rng);
+    let genesis_ids: BTreeSet<_> = peers.iter().map(|peer| peer.our_pub_id().clone()).collect();
+    let silent_id = unwrap!(peers.pop()).our_pub_id().clone();
+
+    let split = Observation::Split {
+        left: genesis_ids.iter().take(2).cloned().collect(),
+        right: genesis_ids.iter().skip(2).cloned().collect(),
+    };
+    for peer in &mut peers {
+        unwrap!(peer.vote_for(split.clone()));
+    }
+    gossip_until(&mut peers, |peer| {
+        peer.consensused_blocks()
+            .any(|block| *block.payload() == split)
+    });
+
+    // Alice can no longer vote, but that mustn't make her refuse gossip.
+    peers[0].set_inactivity_policy(Some(InactivityPolicy::new(1)));
+    let alice_id = peers[0].our_pub_id().clone();
+    for _ in 0..3 {
+        for dst in 1..peers.len() {
+            let dst_id = peers[dst].our_pub_id().clone();
+            let request = unwrap!(peers[0].create_gossip(&dst_id));
+            let response = unwrap!(peers[dst].handle_request(&alice_id, request));
+            unwrap!(peers[0].handle_response(&dst_id, response));
+        }
+    }
+
+    assert!(!peers[0].have_voted_for(&Observation::Remove {
+        peer_id: silent_id,
+        related_info: vec![],
+    }));
+}
+
+#[test]
+fn split() {
+    let mut common_rng = new_common_rng(SEED);
+    let mut peers = create_genesis_peers(4, ConsensusMode::Supermajority, &mut common_rng);
+    let genesis_ids: BTreeSet<_> = peers.iter().map(|peer| peer.our_pub_id().clone()).collect();
+
+    // A learner follows the section, without being on either side of the split.
+    let learner_id = PeerId::new("Eric");
+    let mut learner = TestParsec::from(Parsec::new_learner(
+        learner_id.clone(),
+        &genesis_ids,
+        &genesis_ids,
+        ConsensusMode::Supermajority,
+        new_rng(&mut common_rng),
+    ));
+    for peer in &mut peers {
+        unwrap!(peer.add_learner(learner_id.clone()));
+    }
+
+    let left: BTreeSet<_> = genesis_ids.iter().take(2).cloned().collect();
+    let right: BTreeSet<_> = genesis_ids.iter().skip(2).cloned().collect();
+    let split = Observation::Split {
+        left: left.clone(),
+        right: right.clone(),
+    };
+
+    // Only Alice votes for this one, so it won't be consensused before the split.
+    let leftover = Observation::OpaquePayload(Transaction::new("leftover"));
+    unwrap!(peers[0].vote_for(leftover.clone()));
+    for peer in &mut peers {
+        unwrap!(peer.vote_for(split.clone()));
+    }
+    assert_eq!(
+        peers[0].split(new_rng(&mut common_rng)).err(),
+        Some(Error::MissingSplit)
+    );
+
+    let has_split = |peer: &TestPeer| {
+        peer.consensused_blocks()
+            .any(|block| *block.payload() == split)
+    };
+    gossip_until(&mut peers, has_split);
+
+    // The split block is the last one.
+    for peer in &peers {
+        assert_eq!(
+            peer.consensused_blocks().last().map(Block::payload),
+            Some(&split)
+        );
+    }
+    assert_eq!(
+        peers[0].vote_for(Observation::OpaquePayload(Transaction::new("late"))),
+        Err(Error::SectionSplit)
+    );
+
+    // Both children are seeded from the same point of the parent history: the split block, and
+    // the hash of the blocks before it.
+    let split_block_hash = unwrap!(peers[0].consensused_blocks().last()).hash();
+    let mut seeds = BTreeSet::new();
+    for (peer, side) in peers.iter().zip(vec![&left, &left, &right, &right]) {
+        let new_parsec = TestParsec::from(unwrap!(peer.split(new_rng(&mut common_rng))));
+        let voters: BTreeSet<_> = new_parsec
+            .peer_list()
+            .voters()
+            .map(|(_, peer)| peer.id().clone())
+            .collect();
+        assert_eq!(voters, *side);
+
+        let related_info = unwrap!(new_parsec
+            .our_unpolled_observations()
+            .filter_map(|observation| match observation {
+                Observation::Genesis { related_info, .. } => Some(related_info.clone()),
+                _ => None,
+            })
+            .next());
+        let (seed_left, seed_right, block_hash, history_hash): (
+            BTreeSet<PeerId>,
+            BTreeSet<PeerId>,
+            BlockHash,
+            Hash,
+        ) = unwrap!(deserialise(&related_info));
+        assert_eq!((&seed_left, &seed_right), (&left, &right));
+        assert_eq!(block_hash, split_block_hash);
+        let _ = seeds.insert(history_hash);
+    }
+    assert_eq!(seeds.len(), 1);
+
+    // Alice's unconsensused vote is carried over.
+    let alice = TestParsec::from(unwrap!(peers[0].split(new_rng(&mut common_rng))));
+    assert!(alice.have_voted_for(&leftover));
+
+    // The learner reaches the split too, but has no side to follow.
+    for peer in &mut peers {
+        let request = unwrap!(peer.create_gossip(&learner_id));
+        let response = unwrap!(learner.handle_request(peer.our_pub_id(), request));
+        unwrap!(peer.handle_response(&learner_id, response));
+    }
+    assert!(has_split(&learner));
+    assert_eq!(
+        learner.split(new_rng(&mut common_rng)).err(),
+        Some(Error::NotInSplit)
+    );
+}
+
+// Creates the given number of peers, all members of the genesis group.
+fn create_genesis_peers<R: Rng>(
+    count: usize,
+    consensus_mode: ConsensusMode,
+    rng: &mut R,
+) -> Vec<TestPeer> {
+    let genesis_ids = mock::create_ids(count).into_iter().collect();
+    create_peers(&genesis_ids, consensus_mode, rng)
+}
+
+// Creates a peer for each member of the genesis group.
+fn create_peers<R: Rng>(
+    genesis_ids: &BTreeSet<PeerId>,
+    consensus_mode: ConsensusMode,
+    rng: &mut R,
+) -> Vec<TestPeer> {
+    genesis_ids
+        .iter()
+        .map(|id| TestParsec::from_genesis(id.clone(), genesis_ids, consensus_mode, new_rng(rng)))
+        .collect()
+}
+
+// Makes every peer gossip to every other one it can send to, as long as the recipient knows it by
+// its current id, until `done` holds for all of them.
+fn gossip_until<F>(peers: &mut [TestPeer], done: F)
+where
+    F: Fn(&TestPeer) -> bool,
+{
+    for _ in 0..100 {
+        if peers.iter().all(&done) {
+            return;
+        }
+
+        for src in 0..peers.len() {
+            for dst in 0..peers.len() {
+                let src_id = peers[src].our_pub_id().clone();
+                let dst_id = peers[dst].our_pub_id().clone();
+                if src == dst
+                    || peers[src].has_left()
+                    || !peers[src].gossip_recipients().any(|id| *id == dst_id)
+                    || peers[dst].peer_list().get_index(&src_id).is_none()
+                {
+                    continue;
+                }
+
+                let request = unwrap!(peers[src].create_gossip(&dst_id));
+                let response = match peers[dst].handle_request(&src_id, request) {
+                    Ok(response) => response,
+                    // `dst` throttles `src`, but still pulls its events.
+                    Err(Error::Backpressure) => continue,
+                    Err(error) => panic!("Unexpected error: {:?}", error),
+                };
+                unwrap!(peers[src].handle_response(&dst_id, response));
+            }
+        }
+    }
+
+    panic!("Gossip didn't reach the expected state");
+}
+
+#[test]
+fn merge() {
+    let mut common_rng = new_common_rng(SEED);
+    let ids = mock::create_ids(6);
+    let section_ids: Vec<BTreeSet<_>> = vec![
+        ids.iter().take(3).cloned().collect(),
+        ids.iter().skip(3).cloned().collect(),
+    ];
+    let mut sections: Vec<_> = section_ids
+        .iter()
+        .map(|section| create_peers(section, ConsensusMode::Supermajority, &mut common_rng))
+        .collect();
+
+    // Wait for the genesis blocks, so each section has a last block to name.
+    for peers in &mut sections {
+        gossip_until(peers, |peer| peer.last_block_hash().is_some());
+    }
+    let last_blocks: Vec<_> = sections
+        .iter()
+        .map(|peers| unwrap!(peers[0].last_block_hash()))
+        .collect();
+
+    // The first section consensuses another block before voting to merge, so the block named by
+    // the other section is no longer its last one.
+    let interleaved = Observation::OpaquePayload(Transaction::new("interleaved"));
+    for peer in &mut sections[0] {
+        unwrap!(peer.vote_for(interleaved.clone()));
+    }
+    gossip_until(&mut sections[0], |peer| {
+        peer.consensused_blocks()
+            .any(|block| *block.payload() == interleaved)
+    });
+    assert_ne!(sections[0][0].last_block_hash(), Some(last_blocks[0]));
+
+    // Each section votes to merge with the other one. Alice also has a vote of her own which
+    // won't be consensused before the merge.
+    let merges: Vec<_> = (0..2)
+        .map(|index| Observation::Merge {
+            other_section: section_ids[1 - index].clone(),
+            other_last_block: last_blocks[1 - index],
+        })
+        .collect();
+    let leftover = Observation::OpaquePayload(Transaction::new("leftover"));
+    unwrap!(sections[0][0].vote_for(leftover.clone()));
+    for (peers, merge) in sections.iter_mut().zip(&merges) {
+        for peer in peers.iter_mut() {
+            unwrap!(peer.vote_for(merge.clone()));
+        }
+        assert_eq!(
+            peers[0].merge(&merges[0], new_rng(&mut common_rng)).err(),
+            Some(Error::MissingMerge)
+        );
+        gossip_until(peers, |peer| {
+            peer.consensused_blocks()
+                .any(|block| block.payload() == merge)
+        });
+    }
+
+    assert_eq!(
+        sections[0][0].vote_for(Observation::OpaquePayload(Transaction::new("late"))),
+        Err(Error::SectionMerged)
+    );
+    // Our own `Merge` doesn't mirror ours, and neither does one naming a block we don't have.
+    assert_eq!(
+        sections[0][0]
+            .merge(&merges[0], new_rng(&mut common_rng))
+            .err(),
+        Some(Error::InvalidMerge)
+    );
+    let unknown_block = Observation::Merge {
+        other_section: section_ids[0].clone(),
+        other_last_block: last_blocks[1],
+    };
+    assert_eq!(
+        sections[0][0]
+            .merge(&unknown_block, new_rng(&mut common_rng))
+            .err(),
+        Some(Error::InvalidMerge)
+    );
+
+    // The joint genesis is the same for everyone, listing both groups and the `Merge` blocks
+    // ending each parent history.
+    let genesis_group: BTreeSet<_> = ids.iter().cloned().collect();
+    let parents: BTreeSet<_> = section_ids
+        .iter()
+        .zip(&merges)
+        .map(|(section, merge)| (section, BlockHash::new(merge)))
+        .collect();
+    let genesis = Observation::Genesis {
+        group: genesis_group.clone(),
+        related_info: serialise(&parents),
+    };
+
+    let mut joint: Vec<_> = sections
+        .iter()
+        .enumerate()
+        .flat_map(|(index, peers)| {
+            let their_merge = &merges[1 - index];
+            peers
+                .iter()
+                .map(|peer| {
+                    TestParsec::from(unwrap!(peer.merge(their_merge, new_rng(&mut common_rng))))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    for peer in &joint {
+        assert!(peer.have_voted_for(&genesis));
+    }
+    assert!(joint[0].have_voted_for(&leftover));
+
+    // The joint section reaches consensus on its genesis.
+    gossip_until(&mut joint, |peer| {
+        peer.consensused_blocks()
+            .any(|block| *block.payload() == genesis)
+    });
+}
+
+#[test]
+fn set_parameters() {
+    let mut common_rng = new_common_rng(SEED);
+    let mut peers = create_genesis_peers(4, ConsensusMode::Supermajority, &mut common_rng);
+
+    // Only Alice votes for these, so they can't be consensused in `Supermajority` mode.
+    let early = Observation::OpaquePayload(Transaction::new("early"));
+    unwrap!(peers[0].vote_for(early.clone()));
+    let early_large = Observation::OpaquePayload(Transaction::new("early but too large a payload"));
+    unwrap!(peers[0].vote_for(early_large.clone()));
+
+    let parameters = Parameters {
+        consensus_mode: ConsensusMode::Single,
+        max_payload_size: Some(16),
+        max_inactive_sync_events: None,
+    };
+    let set_parameters = Observation::SetParameters(parameters);
+    for peer in &mut peers {
+        unwrap!(peer.vote_for(set_parameters.clone()));
+    }
+
+    // Once switched to `Single` mode, Alice's votes are enough. The large payload is output as
+    // oversized, as the limit is in force by then.
+    let oversized = Observation::OversizedPayload {
+        hash: BlockHash::new(&early_large),
+        size: serialise(&Transaction::new("early but too large a payload")).len(),
+    };
+    let has_early = |peer: &TestPeer| {
+        let has = |observation: &Observation<Transaction, PeerId>| {
+            peer.consensused_blocks()
+                .any(|block| block.payload() == observation)
+        };
+        has(&early) && has(&oversized)
+    };
+    gossip_until(&mut peers, has_early);
+
+    let payloads: Vec<Vec<_>> = peers
+        .iter()
+        .map(|peer| {
+            peer.consensused_blocks()
+                .map(|block| block.payload().clone())
+                .collect()
+        })
+        .collect();
+    for peer_payloads in &payloads {
+        let switch = unwrap!(peer_payloads
+            .iter()
+            .position(|payload| *payload == set_parameters));
+        let early_position = unwrap!(peer_payloads.iter().position(|payload| *payload == early));
+        assert!(switch < early_position);
+        assert_eq!(
+            peer_payloads[..=early_position],
+            payloads[0][..=early_position]
+        );
+        assert!(!peer_payloads.contains(&early_large));
+    }
+    for peer in &peers {
+        let oversized_block = unwrap!(peer
+            .consensused_blocks()
+            .find(|block| *block.payload() == oversized));
+        assert!(oversized_block.proofs().is_empty());
+    }
+
+    for peer in &peers {
+        assert_eq!(*peer.parameters(), parameters);
+    }
+
+    let large = Observation::OpaquePayload(Transaction::new("far too large a payload"));
+    assert_eq!(peers[1].vote_for(large), Err(Error::PayloadTooLarge));
+}
+
+#[test]
+fn set_parameters_during_election() {
+    let path = env::temp_dir().join(format!("parsec_set_parameters_{}", process::id()));
+
+    let mut common_rng = new_common_rng(SEED);
+    let mut peers = create_genesis_peers(4, ConsensusMode::Supermajority, &mut common_rng);
+
+    // Alice holds her events in a file, with only a few of them in memory, so the votes rekeyed by
+    // the switch have to be written back to it.
+    unwrap!(peers[0].set_event_store(Box::new(unwrap!(FileEventStore::create(&path))), 4));
+
+    // Two votes aren't enough for `pending` to be consensused in `Supermajority` mode, so they are
+    // still part of the meta-election under way when the switch to `Single` mode rekeys them.
+    let pending = Observation::OpaquePayload(Transaction::new("pending"));
+    let set_parameters = Observation::SetParameters(Parameters::new(ConsensusMode::Single));
+    for peer in &mut peers[..2] {
+        unwrap!(peer.vote_for(pending.clone()));
+    }
+    for peer in &mut peers {
+        unwrap!(peer.vote_for(set_parameters.clone()));
+    }
+
+    // Each vote for `pending` is an observation of its own in `Single` mode.
+    let num_pending = |peer: &TestPeer| {
+        peer.consensused_blocks()
+            .filter(|block| *block.payload() == pending)
+            .count()
+    };
+    gossip_until(&mut peers, |peer| num_pending(peer) == 2);
+
+    let payloads: Vec<Vec<_>> = peers
+        .iter()
+        .map(|peer| {
+            peer.consensused_blocks()
+                .map(|block| block.payload().clone())
+                .collect()
+        })
+        .collect();
+    let num_common = unwrap!(payloads.iter().map(Vec::len).min());
+    for peer_payloads in &payloads {
+        assert_eq!(peer_payloads[..num_common], payloads[0][..num_common]);
+        let switch = unwrap!(peer_payloads
+            .iter()
+            .position(|payload| *payload == set_parameters));
+        assert!(peer_payloads[..switch]
+            .iter()
+            .all(|payload| *payload != pending));
+    }
+    let history = peers[0].meta_election_consensus_history_hash();
+    for peer in &peers {
+        assert_eq!(*peer.parameters(), Parameters::new(ConsensusMode::Single));
+        let peer_history = peer.meta_election_consensus_history_hash();
+        let num_common = cmp::min(history.len(), peer_history.len());
+        assert_eq!(peer_history[..num_common], history[..num_common]);
+    }
+
+    drop(peers);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn retract_vote() {
+    let mut common_rng = new_common_rng(SEED);
+    let mut peers = create_genesis_peers(4, ConsensusMode::Supermajority, &mut common_rng);
+
+    let stale = Observation::OpaquePayload(Transaction::new("stale"));
+    let fresh = Observation::OpaquePayload(Transaction::new("fresh"));
+    for peer in &mut peers[..3] {
+        unwrap!(peer.vote_for(stale.clone()));
+    }
+    for peer in &mut peers {
+        unwrap!(peer.vote_for(fresh.clone()));
+    }
+
+    // Without Carol's vote, `stale` lacks a supermajority.
+    unwrap!(peers[2].retract_vote(&stale));
+    assert!(!peers[2]
+        .our_unpolled_observations()
+        .any(|obs| *obs == stale));
+    assert!(peers[2]
+        .our_unpolled_observations()
+        .any(|obs| *obs == fresh));
+    assert_eq!(peers[2].retract_vote(&stale), Err(Error::DuplicateVote));
+    assert_eq!(peers[3].retract_vote(&stale), Err(Error::UnknownPayload));
+
+    let has = |peer: &TestParsec<Transaction, PeerId>, observation| {
+        peer.consensused_blocks()
+            .any(|block| block.payload() == observation)
+    };
+    gossip_until(&mut peers, |peer| has(peer, &fresh));
+    for peer in &peers {
+        assert!(!has(peer, &stale));
+    }
+
+    assert_eq!(
+        peers[0].retract_vote(&fresh),
+        Err(Error::AlreadyConsensused)
+    );
+}
+
+#[test]
+fn learner() {
+    let mut common_rng = new_common_rng(SEED);
+    let genesis_ids = mock::create_ids(4).into_iter().collect();
+    let mut voters = create_peers(&genesis_ids, ConsensusMode::Supermajority, &mut common_rng);
+
+    let learner_id = PeerId::new("Eric");
+    let mut learner = TestParsec::from(Parsec::new_learner(
+        learner_id.clone(),
+        &genesis_ids,
+        &genesis_ids,
+        ConsensusMode::Supermajority,
+        new_rng(&mut common_rng),
+    ));
+    assert!(learner.is_learner());
+    assert_eq!(learner.gossip_recipients().count(), 0);
+    match learner.vote_for(Observation::OpaquePayload(Transaction::new("nope"))) {
+        Err(Error::InvalidSelfState { .. }) => (),
+        x => panic!("Unexpected {:?}", x),
+    }
+
+    let bob_id = voters[1].our_pub_id().clone();
+    match voters[0].add_learner(bob_id) {
+        Err(Error::InvalidPeerState { .. }) => (),
+        x => panic!("Unexpected {:?}", x),
+    }
+    for voter in &mut voters {
+        unwrap!(voter.add_learner(learner_id.clone()));
+        assert!(voter.gossip_recipients().any(|id| *id == learner_id));
+    }
+
+    let votes: Vec<_> = ["A", "B", "C", "D"]
+        .iter()
+        .map(|name| Observation::OpaquePayload(Transaction::new(*name)))
+        .collect();
+    for voter in &mut voters {
+        for vote in &votes {
+            unwrap!(voter.vote_for(vote.clone()));
+        }
+    }
+
+    // Only the voters gossip among themselves, so the learner can't hold back consensus.
+    let has_all_votes = |peer: &TestPeer| {
+        votes.iter().all(|vote| {
+            peer.consensused_blocks()
+                .any(|block| block.payload() == vote)
+        })
+    };
+    gossip_until(&mut voters, has_all_votes);
+
+    // Push gossip to the learner, twice to check it only gets new events the second time.
+    for _ in 0..2 {
+        for voter in &mut voters {
+            let request = unwrap!(voter.create_gossip(&learner_id));
+            let response = unwrap!(learner.handle_request(voter.our_pub_id(), request));
+            unwrap!(voter.handle_response(&learner_id, response));
+        }
+    }
+    assert!(has_all_votes(&learner));
+
+    let payloads = |peer: &TestPeer| {
+        peer.consensused_blocks()
+            .map(|block| block.payload().clone())
+            .collect::<Vec<_>>()
+    };
+    let learnt = payloads(&learner);
+    for voter in &voters {
+        let voted = payloads(voter);
+        let len = cmp::min(learnt.len(), voted.len());
+        assert_eq!(learnt[..len], voted[..len]);
+    }
+
+    // The learner never created an event.
+    assert!(learner.peer_list().our_events().next().is_none());
+}
+
+#[test]
+fn vote_for_batch() {
+    let mut common_rng = new_common_rng(SEED);
+    let mut peers = create_genesis_peers(4, ConsensusMode::Supermajority, &mut common_rng);
+
+    let batch: Vec<_> = ["a", "b", "c"]
+        .iter()
+        .map(|value| Observation::OpaquePayload(Transaction::new(*value)))
+        .collect();
+    for peer in &mut peers {
+        unwrap!(peer.vote_for_batch(batch.clone()));
+    }
+
+    assert_eq!(
+        peers[0].vote_for_batch(vec![batch[0].clone()]),
+        Err(Error::DuplicateVote)
+    );
+    let d = Observation::OpaquePayload(Transaction::new("d"));
+    assert_eq!(
+        peers[0].vote_for_batch(vec![d.clone(), d.clone()]),
+        Err(Error::DuplicateVote)
+    );
+
+    let batch_blocks = |peer: &TestPeer| {
+        peer.consensused_blocks()
+            .filter(|block| block.payload().is_opaque())
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    gossip_until(&mut peers, |peer| batch_blocks(peer).len() == batch.len());
+
+    for peer in &peers {
+        let blocks = batch_blocks(peer);
+        let payloads: BTreeSet<_> = blocks.iter().map(|block| block.payload().clone()).collect();
+        assert_eq!(payloads, batch.iter().cloned().collect());
+
+        // Every vote of the batch is individually signed and counted.
+        for block in &blocks {
+            assert!(block.proofs().len() >= 3);
+        }
+
+        // Besides the genesis vote, a single event carried all our votes.
+        let vote_events = peer
+            .peer_list()
+            .our_events()
+            .filter_map(|event_index| peer.graph().get(event_index))
+            .filter(|event| event.payload_keys().next().is_some())
+            .count();
+        assert_eq!(vote_events, 2);
+    }
+}
+
+#[test]
+fn event_log() {
+    let path = env::temp_dir().join(format!("parsec_event_log_{}", process::id()));
+    let _ = fs::remove_file(&path);
+
+    let mut common_rng = new_common_rng(SEED);
+    let genesis_ids = mock::create_ids(4).into_iter().collect();
+    let mut peers = create_peers(&genesis_ids, ConsensusMode::Supermajority, &mut common_rng);
+    unwrap!(peers[0].set_event_log(unwrap!(EventLog::open(&path))));
+
+    let has = |peer: &TestPeer, value: &str| {
+        let observation = Observation::OpaquePayload(Transaction::new(value));
+        peer.consensused_blocks()
+            .any(|block| *block.payload() == observation)
+    };
+    for peer in &mut peers {
+        unwrap!(peer.vote_for(Observation::OpaquePayload(Transaction::new("before"))));
+    }
+    gossip_until(&mut peers, |peer| has(peer, "before"));
+
+    // Alice restarts from her log: she ends up with the same graph and blocks.
+    let alice_id = peers[0].our_pub_id().clone();
+    let mut alice = TestParsec::<Transaction, _>::from_genesis(
+        alice_id,
+        &genesis_ids,
+        ConsensusMode::Supermajority,
+        new_rng(&mut common_rng),
+    );
+    unwrap!(alice.set_event_log(unwrap!(EventLog::open(&path))));
+    let crashed = mem::replace(&mut peers[0], alice);
+
+    assert_eq!(
+        GraphSnapshot::new(peers[0].graph()),
+        GraphSnapshot::new(crashed.graph())
+    );
+    assert_eq!(
+        peers[0].peer_list().last_event(PeerIndex::OUR),
+        crashed.peer_list().last_event(PeerIndex::OUR)
+    );
+    assert!(has(&peers[0], "before"));
+    drop(crashed);
+
+    // Her next events follow the logged ones, so the section keeps reaching consensus.
+    for peer in &mut peers {
+        unwrap!(peer.vote_for(Observation::OpaquePayload(Transaction::new("after"))));
+    }
+    gossip_until(&mut peers, |peer| has(peer, "after"));
+
+    // A log written by a different instance is rejected.
+    let mut bob = TestParsec::<Transaction, _>::from_genesis(
+        peers[1].our_pub_id().clone(),
+        &genesis_ids,
+        ConsensusMode::Supermajority,
+        new_rng(&mut common_rng),
+    );
+    assert_eq!(
+        bob.set_event_log(unwrap!(EventLog::open(&path))),
+        Err(Error::EventLog)
+    );
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn event_store() {
+    let path = env::temp_dir().join(format!("parsec_event_store_{}", process::id()));
+
+    let mut common_rng = new_common_rng(SEED);
+    let mut peers = create_genesis_peers(4, ConsensusMode::Supermajority, &mut common_rng);
+
+    // Returns a copy of `event` to be put in a store.
+    let to_stored =
+        |event: &Event<PeerId>| -> StoredEvent<PeerId> { unwrap!(deserialise(&serialise(event))) };
+
+    // Alice's events move to a file, with only a few of them kept in memory, while Bob's stay in
+    // memory.
+    let mut store = unwrap!(FileEventStore::create(&path));
+    let event = unwrap!(peers[1].graph().iter().next());
+    unwrap!(store.insert(&to_stored(event.inner())));
+    assert_eq!(
+        peers[0].set_event_store(Box::new(store), 4),
+        Err(Error::EventStore)
+    );
+    let num_events = peers[0].graph().len();
+    unwrap!(peers[0].set_event_store(Box::new(unwrap!(FileEventStore::create(&path))), 4));
+    assert_eq!(peers[0].graph().len(), num_events);
+
+    let observations: Vec<_> = ["a", "b", "c"]
+        .iter()
+        .map(|value| Observation::OpaquePayload(Transaction::new(*value)))
+        .collect();
+    for peer in &mut peers {
+        unwrap!(peer.vote_for_batch(observations.clone()));
+    }
+    let has_all = |peer: &TestPeer| {
+        observations.iter().all(|observation| {
+            peer.consensused_blocks()
+                .any(|block| block.payload() == observation)
+        })
+    };
+    gossip_until(&mut peers, has_all);
+
+    // Alice reaches the same consensus as Bob, and can still find all her events, most of which
+    // were evicted from memory.
+    let graph = peers[0].graph();
+    assert!(graph.len() > 4);
+    assert!(graph.num_cached_events() <= 4);
+    for event in graph {
+        assert_eq!(
+            graph
+                .get_by_hash(event.hash())
+                .map(|event| event.event_index()),
+            Some(event.event_index())
+        );
+    }
+    let payloads = |peer: &TestPeer| {
+        peer.consensused_blocks()
+            .map(|block| block.payload().clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(payloads(&peers[0]), payloads(&peers[1]));
+
+    // A store holding Bob's events finds them by index and by hash, and iterates over their
+    // ancestors as the graph does.
+    drop(peers.remove(0));
+    let graph = peers[0].graph();
+    let mut store = unwrap!(FileEventStore::create(&path));
+    for event in graph {
+        unwrap!(store.insert(&to_stored(event.inner())));
+    }
+    assert_eq!(store.len(), graph.len());
+    for event in graph {
+        let index = event.topological_index();
+        let stored = unwrap!(unwrap!(store.get(index)));
+        assert_eq!(stored.hash(), event.hash());
+        assert_eq!(
+            stored.self_parent(),
+            event.self_parent().map(EventIndex::topological_index)
+        );
+        assert_eq!(
+            stored.other_parent(),
+            event.other_parent().map(EventIndex::topological_index)
+        );
+        let (found_index, found) = unwrap!(unwrap!(store.get_by_hash(event.hash())));
+        assert_eq!((found_index, found.hash()), (index, event.hash()));
+    }
+    let last_event = unwrap!(graph.iter().last());
+    let ancestors: Vec<_> = graph
+        .ancestors(last_event)
+        .map(|event| (event.topological_index(), *event.hash()))
+        .collect();
+    let stored_ancestors: Vec<_> = store
+        .ancestors(last_event.topological_index())
+        .map(|result| {
+            let (index, event) = unwrap!(result);
+            (index, *event.hash())
+        })
+        .collect();
+    assert_eq!(stored_ancestors, ancestors);
+
+    drop(peers);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn limits() {
+    let mut common_rng = new_common_rng(SEED);
+    let mut peers = create_genesis_peers(4, ConsensusMode::Supermajority, &mut common_rng);
+    for peer in &mut peers[..2] {
+        peer.set_limits(Limits {
+            max_unconsensused_votes: Some(3),
+            ..Limits::default()
+        });
+    }
+
+    // Alice and Bob both reach their limit.
+    let observation = |value: &str| Observation::OpaquePayload(Transaction::new(value));
+    for peer in &mut peers[..2] {
+        for value in &["a", "b", "c"] {
+            unwrap!(peer.vote_for(observation(value)));
+        }
+    }
+    assert_eq!(
+        peers[0].vote_for(observation("d")),
+        Err(Error::Backpressure)
+    );
+    assert_eq!(
+        peers[0].vote_for_batch(vec![observation("d")]),
+        Err(Error::Backpressure)
+    );
+
+    // Gossip is still accepted, including from Bob who sits exactly at the limit.
+    let alice_id = peers[0].our_pub_id().clone();
+    let bob_id = peers[1].our_pub_id().clone();
+    let request = unwrap!(peers[1].create_gossip(&alice_id));
+    let response = unwrap!(peers[0].handle_request(&bob_id, request));
+    unwrap!(peers[1].handle_response(&alice_id, response));
+
+    // Once some of her votes are consensused, Alice recovers and can vote again.
+    for peer in &mut peers[2..] {
+        unwrap!(peer.vote_for(observation("a")));
+        unwrap!(peer.vote_for(observation("b")));
+    }
+    let has = |peer: &TestPeer, value| {
+        peer.consensused_blocks()
+            .any(|block| *block.payload() == observation(value))
+    };
+    gossip_until(&mut peers, |peer| has(peer, "a") && has(peer, "b"));
+    unwrap!(peers[0].vote_for(observation("d")));
+    unwrap!(peers[0].vote_for(observation("e")));
+    assert_eq!(
+        peers[0].vote_for(observation("f")),
+        Err(Error::Backpressure)
+    );
+
+    // Retracting a vote makes room too.
+    unwrap!(peers[0].retract_vote(&observation("c")));
+    unwrap!(peers[0].vote_for(observation("f")));
+
+    // Carol, who has no limits, floods the section with more votes than Alice's limit. Alice
+    // refuses her requests once she holds them, without any side effect, but still pulls Carol's
+    // events with her own requests.
+    let carol_id = peers[2].our_pub_id().clone();
+    for value in &["g", "h", "i", "j"] {
+        unwrap!(peers[2].vote_for(observation(value)));
+    }
+    let request = unwrap!(peers[2].create_gossip(&alice_id));
+    let response = unwrap!(peers[0].handle_request(&carol_id, request));
+    unwrap!(peers[2].handle_response(&alice_id, response));
+
+    let graph_len = peers[0].graph().len();
+    let request = unwrap!(peers[2].create_gossip(&alice_id));
+    assert_eq!(
+        peers[0].handle_request(&carol_id, request),
+        Err(Error::Backpressure)
+    );
+    assert_eq!(peers[0].graph().len(), graph_len);
+
+    let request = unwrap!(peers[0].create_gossip(&carol_id));
+    let response = unwrap!(peers[2].handle_request(&alice_id, request));
+    unwrap!(peers[0].handle_response(&carol_id, response));
+
+    // Too many events left for the consensus to go through stop Alice from voting, until
+    // consensus catches up.
+    peers[0].set_limits(Limits {
+        max_graph_size: Some(1),
+        ..Limits::default()
+    });
+    peers[1].set_limits(Limits::default());
+    assert_eq!(
+        peers[0].vote_for(observation("k")),
+        Err(Error::Backpressure)
+    );
+
+    let values = ["c", "d", "e", "f", "g", "h", "i", "j"];
+    for peer in &mut peers[1..] {
+        for value in &values {
+            match peer.vote_for(observation(value)) {
+                Ok(()) | Err(Error::DuplicateVote) => (),
+                Err(error) => panic!("Unexpected error: {:?}", error),
+            }
+        }
+    }
+    gossip_until(&mut peers, |peer| {
+        values.iter().all(|value| has(peer, value))
+    });
+    unwrap!(peers[0].vote_for(observation("k")));
+}
+
+#[test]
+fn encrypted_payload_across_key_generations() {
+    let mut common_rng = new_common_rng(SEED);
+    let mut peers = create_genesis_peers(5, ConsensusMode::Single, &mut common_rng);
+    let ids: Vec<_> = peers.iter().map(|peer| peer.our_pub_id().clone()).collect();
+    let genesis_ids: BTreeSet<_> = ids.iter().cloned().collect();
+    let dave_id = ids[3].clone();
+
+    let dkg_results = |peer: &TestPeer| {
+        peer.consensused_blocks()
+            .filter(|block| block.payload().is_dkg_result())
+            .count()
+    };
+    let has = |peer: &TestPeer, value: &str| {
+        peer.consensused_blocks()
+            .any(|block| *block.payload() == Observation::OpaquePayload(Transaction::new(value)))
+    };
+    let has_encrypted = |peer: &TestPeer, expected_key_gen: usize| {
+        peer.consensused_blocks()
+            .any(|block| match block.payload() {
+                Observation::EncryptedPayload { key_gen, .. } => *key_gen == expected_key_gen,
+                _ => false,
+            })
+    };
+    let has_undecryptable = |peer: &TestPeer, expected_key_gen: usize| {
+        peer.consensused_blocks()
+            .any(|block| match block.payload() {
+                Observation::UndecryptablePayload { key_gen, .. } => *key_gen == expected_key_gen,
+                _ => false,
+            })
+    };
+
+    for peer in &mut peers {
+        unwrap!(peer.vote_for(Observation::StartDkg(genesis_ids.clone())));
+    }
+    gossip_until(&mut peers, |peer| dkg_results(peer) == 1);
+
+    // Eric encrypts a payload to the first key, then falls behind while Dave is removed and a new
+    // DKG among the remaining members supersedes it.
+    unwrap!(peers[4].vote_for_encrypted(&Transaction::new("a")));
+    let new_participants: BTreeSet<_> = ids[..3].iter().cloned().collect();
+    for peer in &mut peers[..4] {
+        unwrap!(peer.vote_for(Observation::Remove {
+            peer_id: dave_id.clone(),
+            related_info: vec![],
+        }));
+        unwrap!(peer.vote_for(Observation::StartDkg(new_participants.clone())));
+    }
+    gossip_until(&mut peers[..4], |peer| {
+        *peer.our_pub_id() == dave_id || dkg_results(peer) == 2
+    });
+
+    // The payload is still revealed once Eric catches up.
+    let _ = peers.remove(3);
+    gossip_until(&mut peers, |peer| {
+        has_encrypted(peer, 0) && has(peer, "a") && dkg_results(peer) == 2
+    });
+
+    // Once two more DKGs have completed, the keys of the second one are dropped and a payload
+    // encrypted to it fails explicitly.
+    unwrap!(peers[3].vote_for_encrypted(&Transaction::new("b")));
+    for participants in &[&ids[..2], &ids[1..3]] {
+        let participants: BTreeSet<_> = participants.iter().cloned().collect();
+        for peer in &mut peers[..3] {
+            unwrap!(peer.vote_for(Observation::StartDkg(participants.clone())));
+        }
+    }
+    gossip_until(&mut peers[..3], |peer| dkg_results(peer) == 4);
+
+    gossip_until(&mut peers, |peer| has_undecryptable(peer, 1));
+    for peer in &peers {
+        assert!(has_encrypted(peer, 1));
+        assert!(!has(peer, "b"));
+    }
+}
+
+#[test]
+fn parsec_handle() {
+    let genesis_ids = mock::create_ids(4).into_iter().collect::<BTreeSet<_>>();
+    let handles: Vec<_> = genesis_ids
+        .iter()
+        .map(|id| {
+            let id = id.clone();
+            let genesis_ids = genesis_ids.clone();
+            ParsecHandle::spawn(move || {
+                Parsec::<Transaction, _>::from_genesis(
+                    id,
+                    &genesis_ids,
+                    vec![],
+                    ConsensusMode::Supermajority,
+                    new_rng(&mut new_common_rng(SEED)),
+                )
+            })
+        })
+        .collect();
+    let ids: Vec<_> = genesis_ids.into_iter().collect();
+
+    for (handle, id) in handles.iter().zip(&ids) {
+        let recipients = unwrap!(handle.gossip_recipients().wait());
+        assert_eq!(recipients.len(), ids.len() - 1);
+        assert!(!recipients.contains(id));
+        assert!(unwrap!(handle.dkg_status().wait()).is_empty());
+        assert_eq!(
+            handle.export_dkg_result().wait(),
+            Err(Error::MissingSectionKey)
+        );
+    }
+
+    // The votes are queued up without waiting for them to be cast.
+    let observations: Vec<_> = ["a", "b"]
+        .iter()
+        .map(|value| Observation::OpaquePayload(Transaction::new(*value)))
+        .collect();
+    let votes: Vec<_> = handles
+        .iter()
+        .map(|handle| handle.vote_for_batch(observations.clone()))
+        .collect();
+    for vote in votes {
+        unwrap!(vote.wait());
+    }
+
+    let payloads = |blocks: &Vec<Block<_, _>>| {
+        blocks
+            .iter()
+            .map(|block| block.payload().clone())
+            .collect::<Vec<_>>()
+    };
+    let has_all = |blocks: &Vec<Block<_, _>>| {
+        let payloads = payloads(blocks);
+        observations
+            .iter()
+            .all(|observation| payloads.contains(observation))
+    };
+
+    let mut blocks = vec![vec![]; handles.len()];
+    for _ in 0..100 {
+        if blocks.iter().all(has_all) {
+            break;
+        }
+
+        for src in 0..handles.len() {
+            for dst in 0..handles.len() {
+                if src == dst {
+                    continue;
+                }
+
+                let request = unwrap!(handles[src].create_gossip(ids[dst].clone()).wait());
+                let response = unwrap!(handles[dst]
+                    .handle_request(ids[src].clone(), request)
+                    .wait());
+                unwrap!(handles[src]
+                    .handle_response(ids[dst].clone(), response)
+                    .wait());
+            }
+        }
+
+        for (handle, blocks) in handles.iter().zip(&mut blocks) {
+            blocks.extend(iter::from_fn(|| handle.try_recv_block()));
+        }
+    }
+
+    // All the peers get the same blocks, in the same order.
+    assert!(blocks.iter().all(has_all));
+    let expected = payloads(&blocks[0]);
+    for blocks in &blocks[1..] {
+        assert_eq!(payloads(blocks), expected);
+    }
+
+    // A `Reply` can also be awaited.
+    #[cfg(feature = "async")]
+    assert_eq!(
+        futures::executor::block_on(handles[0].vote_for(observations[0].clone())),
+        Err(Error::DuplicateVote)
+    );
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn async_parsec() {
+    use crate::async_parsec::AsyncParsec;
+    use futures::{executor::LocalPool, future::FutureExt, stream::StreamExt, task::LocalSpawnExt};
+    use std::panic::{self, AssertUnwindSafe};
+
+    let mut common_rng = new_common_rng(SEED);
+    let genesis_ids = mock::create_ids(4).into_iter().collect::<BTreeSet<_>>();
+    let peers: Vec<_> = genesis_ids
+        .iter()
+        .map(|id| {
+            AsyncParsec::new(Parsec::<Transaction, _>::from_genesis(
+                id.clone(),
+                &genesis_ids,
+                vec![],
+                ConsensusMode::Supermajority,
+                new_rng(&mut common_rng),
+            ))
+        })
+        .collect();
+    let ids: Vec<_> = genesis_ids.into_iter().collect();
+
+    // One task per peer collects the payloads of its blocks, as they get consensused.
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let payloads: Vec<_> = peers
+        .iter()
+        .map(|peer| {
+            let payloads = Rc::new(RefCell::new(Vec::new()));
+            let task_payloads = Rc::clone(&payloads);
+            let mut blocks = peer.blocks();
+            unwrap!(spawner.spawn_local(async move {
+                while let Some(block) = blocks.next().await {
+                    task_payloads.borrow_mut().push(block.payload().clone());
+                }
+            }));
+            payloads
+        })
+        .collect();
+
+    let observation = Observation::OpaquePayload(Transaction::new("a"));
+    for peer in &peers {
+        unwrap!(pool.run_until(peer.vote_for(observation.clone())));
+    }
+
+    let has = |payloads: &Rc<RefCell<Vec<_>>>| payloads.borrow().contains(&observation);
+    for _ in 0..100 {
+        if payloads.iter().all(has) {
+            break;
+        }
+
+        pool.run_until(async {
+            for src in 0..peers.len() {
+                for dst in 0..peers.len() {
+                    if src == dst {
+                        continue;
+                    }
+
+                    let request = unwrap!(peers[src].create_gossip(&ids[dst]).await);
+                    let response = unwrap!(peers[dst].handle_request(&ids[src], request).await);
+                    unwrap!(peers[src].handle_response(&ids[dst], response).await);
+                }
+            }
+        });
+        pool.run_until_stalled();
+    }
+
+    // All the peers get the same blocks, in the same order.
+    assert!(payloads.iter().all(has));
+    let expected = payloads[0].borrow().clone();
+    for payloads in &payloads[1..] {
+        assert_eq!(*payloads.borrow(), expected);
+    }
+
+    // The stream can be polled from within `with_parsec`.
+    let mut blocks = peers[0].blocks();
+    assert!(peers[0]
+        .with_parsec(|_| blocks.next().now_or_never())
+        .is_none());
+
+    // A panic in `with_parsec` doesn't take the instance away from the wrapper.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        peers[0].with_parsec(|_| panic!("closure panicked"))
+    }));
+    assert!(result.is_err());
+    assert_eq!(
+        peers[0].with_parsec(|parsec| parsec.our_pub_id().clone()),
+        ids[0]
+    );
+}
+
+#[test]
+fn hash_algorithm_mismatch() {
+    use crate::hash::{HashAlgorithmId, HASH_ALGORITHM};
+
+    let mut common_rng = new_common_rng(SEED);
+    let mut peers = create_genesis_peers(2, ConsensusMode::Supermajority, &mut common_rng);
+    let ids: Vec<_> = peers.iter().map(|peer| peer.our_pub_id().clone()).collect();
+    let other_algorithm = if HASH_ALGORITHM == HashAlgorithmId::Sha3_256 {
+        HashAlgorithmId::Blake2b256
+    } else {
+        HashAlgorithmId::Sha3_256
+    };
+
+    let mut request = unwrap!(peers[0].create_gossip(&ids[1]));
+    let mut response = unwrap!(peers[1].handle_request(&ids[0], request.clone()));
+
+    request.hash_algorithm = other_algorithm;
+    assert_eq!(
+        peers[1].handle_request(&ids[0], request),
+        Err(Error::HashAlgorithmMismatch)
+    );
+    response.hash_algorithm = other_algorithm;
+    assert_eq!(
+        peers[0].handle_response(&ids[1], response),
+        Err(Error::HashAlgorithmMismatch)
+    );
+}
+
 #[test]
 fn our_unpolled_observations_with_consensus_mode_single() {
     let mut alice = Record::from(parse_test_dot_file("alice.dot")).play();
@@ -852,7 +2419,7 @@ mod handle_malice {
         assert!(receiver
             .handle_request(sender.our_pub_id(), request)
             .is_ok());
-        assert_peer_has_accused(&receiver, vec![(sender.our_pub_id(), expected_malice)]);
+        assert_peer_has_accused(receiver, vec![(sender.our_pub_id(), expected_malice)]);
     }
 
     fn packed_req_event(
@@ -887,7 +2454,7 @@ mod handle_malice {
         let invalid_req_hash = invalid_req.compute_hash();
         let mut packed_events = take_packed_events(&bob, bob.graph().len());
         packed_events.push(invalid_req.clone());
-        let invalid_response_msg = Response { packed_events };
+        let invalid_response_msg = Response::new(packed_events);
 
         let expected_malice = Malice::InvalidRequest(Box::new(invalid_req));
 
@@ -935,7 +2502,7 @@ mod handle_malice {
 
         let mut packed_events = take_packed_events(&bob, 2);
         packed_events.push(invalid_req.clone());
-        let invalid_response_msg = Response { packed_events };
+        let invalid_response_msg = Response::new(packed_events);
 
         assert_handling_invalid_response(
             &mut bob,
@@ -948,7 +2515,7 @@ mod handle_malice {
         packed_events = take_packed_events(&bob, 5);
         packed_events.push(invalid_req);
         // Knowledge of Alice and Bob, and the invalid_req.
-        let invalid_response_msg = Response { packed_events };
+        let invalid_response_msg = Response::new(packed_events);
 
         assert_handling_invalid_response(
             &mut bob,
@@ -987,7 +2554,7 @@ mod handle_malice {
 
         let mut packed_events = take_packed_events(&bob, bob.graph().len());
         packed_events.push(invalid_req);
-        let invalid_response_msg = Response { packed_events };
+        let invalid_response_msg = Response::new(packed_events);
         assert_handling_invalid_response(
             &mut bob,
             &mut carol,
@@ -1017,7 +2584,7 @@ mod handle_malice {
         // If the response be sent to Carol, a response event shall not be created.
         let packed_events = take_packed_events(&bob, bob.graph().len());
         assert_eq!(
-            carol.handle_response(bob.our_pub_id(), Response { packed_events }),
+            carol.handle_response(bob.our_pub_id(), Response::new(packed_events)),
             Err(Error::InvalidMessage)
         );
         assert!(!carol.graph().iter().any(|event| event.is_response()));
@@ -1035,7 +2602,7 @@ mod handle_malice {
         let mut packed_events = take_packed_events(&carol, 2);
         packed_events.push(bob_request);
         packed_events.push(invalid_resp.clone());
-        let invalid_response_msg = Response { packed_events };
+        let invalid_response_msg = Response::new(packed_events);
         assert_handling_invalid_response(
             &mut carol,
             &mut bob,
@@ -1047,7 +2614,7 @@ mod handle_malice {
         packed_events = take_packed_events(&bob, 8);
         packed_events.push(invalid_resp);
         // Knowledge of Alice, Bob and Carol, and the invalid_resp.
-        let invalid_response_msg = Response { packed_events };
+        let invalid_response_msg = Response::new(packed_events);
         assert_handling_invalid_response(
             &mut carol,
             &mut dave,
@@ -1079,9 +2646,7 @@ mod handle_malice {
         let invalid_resp_hash = invalid_resp.compute_hash();
         let expected_malice = Malice::InvalidResponse(Box::new(invalid_resp.clone()));
 
-        let invalid_response_msg = Response {
-            packed_events: vec![invalid_resp.clone()],
-        };
+        let invalid_response_msg = Response::new(vec![invalid_resp.clone()]);
         assert_handling_invalid_response(
             &mut alice,
             &mut bob,
@@ -1093,7 +2658,7 @@ mod handle_malice {
         let mut packed_events = take_packed_events(&bob, 6);
         packed_events.push(invalid_resp);
         // Knowledge of Alice and Bob, and the invalid_resp.
-        let invalid_response_msg = Response { packed_events };
+        let invalid_response_msg = Response::new(packed_events);
         assert_handling_invalid_response(
             &mut alice,
             &mut carol,
@@ -1126,9 +2691,7 @@ mod handle_malice {
         let invalid_resp_hash = invalid_resp.compute_hash();
         let expected_malice = Malice::InvalidResponse(Box::new(invalid_resp.clone()));
 
-        let invalid_response_msg = Response {
-            packed_events: vec![valid_resp, invalid_resp.clone()],
-        };
+        let invalid_response_msg = Response::new(vec![valid_resp, invalid_resp.clone()]);
         assert_handling_invalid_response(
             &mut alice,
             &mut bob,
@@ -1139,7 +2702,7 @@ mod handle_malice {
 
         let mut packed_events = take_packed_events(&alice, alice.graph().len());
         packed_events.push(invalid_resp);
-        let invalid_response_msg = Response { packed_events };
+        let invalid_response_msg = Response::new(packed_events);
         assert_handling_invalid_response(
             &mut alice,
             &mut carol,
@@ -1418,9 +2981,7 @@ mod handle_malice {
         let a_1 = unwrap!(nth_event(alice.graph(), 1).pack(alice.event_context()));
         let a_2_0 =
             PackedEvent::new_requesting(alice_id.clone(), bob_id.clone(), a_1.compute_hash());
-        let mut request = Request {
-            packed_events: vec![a_0.clone(), a_1.clone(), a_2_0.clone()],
-        };
+        let mut request = Request::new(vec![a_0.clone(), a_1.clone(), a_2_0.clone()]);
         unwrap!(bob.handle_request(&alice_id, request.clone()));
 
         // [A_0, A_1, A_2,1, A_3,0] will be sent to Carol.
@@ -1515,9 +3076,8 @@ mod handle_malice {
             PackedEvent::new_requesting(alice_id.clone(), bob_id.clone(), a_2_0.compute_hash());
         let a_3_1 =
             PackedEvent::new_requesting(alice_id.clone(), bob_id.clone(), a_2_1.compute_hash());
-        let mut request = Request {
-            packed_events: vec![a_0.clone(), a_1.clone(), a_2_0.clone(), a_3_0.clone()],
-        };
+        let mut request =
+            Request::new(vec![a_0.clone(), a_1.clone(), a_2_0.clone(), a_3_0.clone()]);
         unwrap!(bob.handle_request(&alice_id, request.clone()));
 
         // [A_0, A_1, A_2,1, A_3,1] will be sent to Bob second.  Bob should accuse A_1.
@@ -1580,9 +3140,7 @@ mod handle_malice {
         let a_1 = unwrap!(nth_event(alice.graph(), 1).pack(alice.event_context()));
         let a_2_0 =
             PackedEvent::new_requesting(alice_id.clone(), bob_id.clone(), a_1.compute_hash());
-        let mut request = Request {
-            packed_events: vec![a_0.clone(), a_1.clone(), a_2_0.clone()],
-        };
+        let mut request = Request::new(vec![a_0.clone(), a_1.clone(), a_2_0.clone()]);
         unwrap!(bob.handle_request(&alice_id, request.clone()));
 
         // [A_0, A_1, A_2,1] will be sent to Carol.
@@ -1652,13 +3210,11 @@ mod handle_malice {
         }
 
         // Send Bob's message to Alice.  B_2 should be rejected as invalid.
-        let message = Request {
-            packed_events: vec![
-                unwrap!(b_0.pack(bob.event_context())),
-                unwrap!(b_1.pack(bob.event_context())),
-                b_2_packed.clone(),
-            ],
-        };
+        let message = Request::new(vec![
+            unwrap!(b_0.pack(bob.event_context())),
+            unwrap!(b_1.pack(bob.event_context())),
+            b_2_packed.clone(),
+        ]);
         assert_eq!(
             alice.handle_request(bob.our_pub_id(), message),
             Err(Error::InvalidEvent)
@@ -1777,7 +3333,7 @@ mod handle_malice {
             .collect_vec();
         assert_eq!(2, hashes.len());
         // Assert we did actually remove the self-parent from the message.
-        assert!(!hashes.contains(&unwrap!(request.packed_events[1].self_parent())));
+        assert!(!hashes.contains(unwrap!(request.packed_events[1].self_parent())));
 
         assert_eq!(
             bob.handle_request(alice.our_pub_id(), request),
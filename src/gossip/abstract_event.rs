@@ -10,8 +10,11 @@ use crate::{observation::ObservationKey, peer_list::PeerIndex};
 
 /// Provide a small interface to Event not dependent on PublicId. Serves as a test seam.
 pub(crate) trait AbstractEventRef<'a>: Copy {
-    /// The vote payload_key for an Observation event
-    fn payload_key(self) -> Option<&'a ObservationKey>
+    /// Iterator over the vote payload_keys of an event
+    type PayloadKeys: Iterator<Item = &'a ObservationKey>;
+
+    /// The vote payload_keys for an Observation or Batch event, in the order they were cast
+    fn payload_keys(self) -> Self::PayloadKeys
     where
         Self: Sized;
 
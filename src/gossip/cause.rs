@@ -17,7 +17,7 @@ use crate::{
     error::Error,
     id::{PublicId, SecretId},
     network_event::NetworkEvent,
-    observation::{ObservationForStore, ObservationInfo},
+    observation::{ObservationInfo, ObservationsForStore},
     peer_list::{PeerIndex, PeerList},
     vote::{Vote, VoteKey},
 };
@@ -27,12 +27,13 @@ use crate::{
     observation::{ConsensusMode, ObservationStore},
 };
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
 #[serde(bound(
     serialize = "V: Serialize, E: Serialize, P: Serialize",
     deserialize = "V: Deserialize<'de>, E: Deserialize<'de>, P: Deserialize<'de>"
 ))]
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
 pub(crate) enum Cause<V, E, P> {
     // Identifier of the latest `Event` of the peer which sent the request and the `PublicId` of the
     // intended recipient.
@@ -43,8 +44,34 @@ pub(crate) enum Cause<V, E, P> {
     Response { self_parent: E, other_parent: E },
     // Identifier of our latest `Event`. Vote for a single network event.
     Observation { self_parent: E, vote: V },
+    // Identifier of our latest `Event`. Votes for several network events at once.
+    Batch { self_parent: E, votes: Vec<V> },
     // Initial empty `Event` of this peer.
     Initial,
+    // Initial empty `Event` of this peer after it rejoined the section it was removed from.
+    // `incarnation` is the number of its memberships before this one. Its first membership starts
+    // with `Initial`.
+    Rejoined { incarnation: usize },
+}
+
+impl<V, E, P> Cause<V, E, P> {
+    // The initial event of the given membership of its creator.
+    pub(crate) fn initial(incarnation: usize) -> Self {
+        if incarnation == 0 {
+            Cause::Initial
+        } else {
+            Cause::Rejoined { incarnation }
+        }
+    }
+
+    // The membership of its creator an initial event starts, or `None` for any other event.
+    pub(crate) fn incarnation(&self) -> Option<usize> {
+        match *self {
+            Cause::Initial => Some(0),
+            Cause::Rejoined { incarnation } => Some(incarnation),
+            _ => None,
+        }
+    }
 }
 
 impl<P: PublicId> Cause<VoteKey<P>, EventIndex, PeerIndex> {
@@ -52,18 +79,32 @@ impl<P: PublicId> Cause<VoteKey<P>, EventIndex, PeerIndex> {
         packed_cause: Cause<Vote<T, P>, EventHash, P>,
         creator: PeerIndex,
         ctx: EventContextRef<T, S>,
-    ) -> Result<(Self, ObservationForStore<T, P>), Error> {
+    ) -> Result<(Self, ObservationsForStore<T, P>), Error> {
         let cause = match packed_cause {
             Cause::Requesting {
                 ref self_parent,
                 ref recipient,
-            } => (
-                Cause::Requesting {
-                    self_parent: self_parent_index(ctx.graph, self_parent)?,
-                    recipient: recipient_index(ctx.peer_list, recipient)?,
-                },
-                None,
-            ),
+            } => {
+                let self_parent = self_parent_index(ctx.graph, self_parent)?;
+                let recipient_index = recipient_index(ctx.peer_list, recipient)?;
+                if recipient_id(ctx.graph, ctx.peer_list, self_parent, recipient_index)?
+                    != recipient
+                {
+                    debug!(
+                        "recipient {:?} named by an outdated or premature id",
+                        recipient
+                    );
+                    return Err(Error::InvalidEvent);
+                }
+
+                (
+                    Cause::Requesting {
+                        self_parent,
+                        recipient: recipient_index,
+                    },
+                    vec![],
+                )
+            }
             Cause::Request {
                 ref self_parent,
                 ref other_parent,
@@ -72,7 +113,7 @@ impl<P: PublicId> Cause<VoteKey<P>, EventIndex, PeerIndex> {
                     self_parent: self_parent_index(ctx.graph, self_parent)?,
                     other_parent: other_parent_index(ctx.graph, other_parent)?,
                 },
-                None,
+                vec![],
             ),
             Cause::Response {
                 ref self_parent,
@@ -82,7 +123,7 @@ impl<P: PublicId> Cause<VoteKey<P>, EventIndex, PeerIndex> {
                     self_parent: self_parent_index(ctx.graph, self_parent)?,
                     other_parent: other_parent_index(ctx.graph, other_parent)?,
                 },
-                None,
+                vec![],
             ),
             Cause::Observation { self_parent, vote } => {
                 let self_parent = self_parent_index(ctx.graph, &self_parent)?;
@@ -95,10 +136,50 @@ impl<P: PublicId> Cause<VoteKey<P>, EventIndex, PeerIndex> {
                         self_parent,
                         vote: vote_key,
                     },
-                    Some((payload_key, ObservationInfo::new(observation))),
+                    vec![(payload_key, ObservationInfo::new(observation))],
                 )
             }
-            Cause::Initial => (Cause::Initial, None),
+            Cause::Batch { self_parent, votes } => {
+                let self_parent = self_parent_index(ctx.graph, &self_parent)?;
+                if votes.is_empty() {
+                    return Err(Error::InvalidEvent);
+                }
+
+                let (vote_keys, observations): (Vec<_>, Vec<_>) = votes
+                    .into_iter()
+                    .map(|vote| {
+                        let (vote_key, observation) =
+                            VoteKey::new(vote, creator, ctx.consensus_mode);
+                        let payload_key = *vote_key.payload_key();
+                        (vote_key, (payload_key, ObservationInfo::new(observation)))
+                    })
+                    .unzip();
+
+                // A batch can't vote twice for the same observation, nor carry observations which
+                // need an event of their own.
+                let num_unique_keys = observations
+                    .iter()
+                    .map(|(payload_key, _)| payload_key)
+                    .collect::<BTreeSet<_>>()
+                    .len();
+                if num_unique_keys != observations.len()
+                    || observations
+                        .iter()
+                        .any(|(_, info)| !info.observation.can_be_batched())
+                {
+                    return Err(Error::InvalidEvent);
+                }
+
+                (
+                    Cause::Batch {
+                        self_parent,
+                        votes: vote_keys,
+                    },
+                    observations,
+                )
+            }
+            Cause::Initial => (Cause::Initial, vec![]),
+            Cause::Rejoined { incarnation } => (Cause::Rejoined { incarnation }, vec![]),
         };
 
         Ok(cause)
@@ -114,7 +195,7 @@ impl<P: PublicId> Cause<VoteKey<P>, EventIndex, PeerIndex> {
                 recipient,
             } => Cause::Requesting {
                 self_parent: self_parent_hash(ctx.graph, self_parent)?,
-                recipient: ctx.peer_list.get_known(recipient)?.id().clone(),
+                recipient: recipient_id(ctx.graph, ctx.peer_list, self_parent, recipient)?.clone(),
             },
             Cause::Request {
                 self_parent,
@@ -137,7 +218,18 @@ impl<P: PublicId> Cause<VoteKey<P>, EventIndex, PeerIndex> {
                 self_parent: self_parent_hash(ctx.graph, self_parent)?,
                 vote: vote.resolve(ctx.observations)?,
             },
+            Cause::Batch {
+                self_parent,
+                ref votes,
+            } => Cause::Batch {
+                self_parent: self_parent_hash(ctx.graph, self_parent)?,
+                votes: votes
+                    .iter()
+                    .map(|vote| vote.resolve(ctx.observations))
+                    .collect::<Result<_, _>>()?,
+            },
             Cause::Initial => Cause::Initial,
+            Cause::Rejoined { incarnation } => Cause::Rejoined { incarnation },
         };
         Ok(cause)
     }
@@ -158,6 +250,7 @@ impl Cause<Vote<Transaction, PeerId>, EventHash, PeerId> {
 
         match input {
             CauseInput::Initial => Cause::Initial,
+            CauseInput::Rejoined(incarnation) => Cause::Rejoined { incarnation },
             CauseInput::Requesting(recipient) => Cause::Requesting {
                 self_parent,
                 recipient,
@@ -174,6 +267,13 @@ impl Cause<Vote<Transaction, PeerId>, EventHash, PeerId> {
                 self_parent,
                 vote: Vote::new(creator_id, observation),
             },
+            CauseInput::Batch(observations) => Cause::Batch {
+                self_parent,
+                votes: observations
+                    .into_iter()
+                    .map(|observation| Vote::new(creator_id, observation))
+                    .collect(),
+            },
             CauseInput::Malice(_, _) => {
                 panic!("CauseInput Malice shall be replaced already");
             }
@@ -219,7 +319,22 @@ impl Cause<VoteKey<PeerId>, EventIndex, PeerIndex> {
                     self_parent,
                 }
             }
+            Cause::Batch { votes, .. } => {
+                let votes = votes
+                    .into_iter()
+                    .map(|vote| {
+                        let (vote_key, observation) = VoteKey::new(vote, creator, consensus_mode);
+                        let _ = observations
+                            .entry(*vote_key.payload_key())
+                            .or_insert_with(|| ObservationInfo::new(observation));
+                        vote_key
+                    })
+                    .collect();
+
+                Cause::Batch { votes, self_parent }
+            }
             Cause::Initial => Cause::Initial,
+            Cause::Rejoined { incarnation } => Cause::Rejoined { incarnation },
         }
     }
 }
@@ -261,6 +376,24 @@ fn other_parent_index<P: PublicId>(
     })
 }
 
+// Returns the id a `Requesting` event with the given self-parent names `recipient` by: the one the
+// recipient signed the latest of its events seen by the self-parent with. This way, the id doesn't
+// depend on when the creator learned of a key rotation of the recipient.
+pub(super) fn recipient_id<'a, S: SecretId>(
+    graph: &Graph<S::PublicId>,
+    peer_list: &'a PeerList<S>,
+    self_parent: EventIndex,
+    recipient: PeerIndex,
+) -> Result<&'a S::PublicId, Error> {
+    let index_by_creator = graph
+        .get(self_parent)
+        .and_then(|event| event.last_ancestor_by(recipient))
+        .unwrap_or(0);
+    peer_list
+        .id_at(recipient, index_by_creator)
+        .ok_or(Error::UnknownPeer)
+}
+
 pub(super) fn recipient_index<S: SecretId>(
     peer_list: &PeerList<S>,
     recipient: &S::PublicId,
@@ -13,17 +13,17 @@ use crate::{
     error::Error,
     id::{PublicId, SecretId},
     network_event::NetworkEvent,
-    observation::ObservationForStore,
+    observation::ObservationsForStore,
     peer_list::PeerIndex,
     vote::{Vote, VoteKey},
 };
 use serde::{Deserialize, Serialize};
 
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug)]
 #[serde(bound(
     serialize = "V: Serialize, E: Serialize, P: Serialize",
     deserialize = "V: Deserialize<'de>, E: Deserialize<'de>, P: Deserialize<'de>"
 ))]
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug)]
 pub(super) struct Content<V, E, P> {
     // Identifier of the peer which created this `Event`.
     pub creator: P,
@@ -42,7 +42,11 @@ impl<V, E, P> Content<V, E, P> {
             | Cause::Response {
                 ref other_parent, ..
             } => Some(other_parent),
-            Cause::Requesting { .. } | Cause::Observation { .. } | Cause::Initial => None,
+            Cause::Requesting { .. }
+            | Cause::Observation { .. }
+            | Cause::Batch { .. }
+            | Cause::Initial
+            | Cause::Rejoined { .. } => None,
         }
     }
 
@@ -61,8 +65,11 @@ impl<V, E, P> Content<V, E, P> {
             }
             | Cause::Observation {
                 ref self_parent, ..
+            }
+            | Cause::Batch {
+                ref self_parent, ..
             } => Some(self_parent),
-            Cause::Initial => None,
+            Cause::Initial | Cause::Rejoined { .. } => None,
         }
     }
 }
@@ -71,25 +78,45 @@ impl<P: PublicId> Content<VoteKey<P>, EventIndex, PeerIndex> {
     pub(crate) fn unpack<T: NetworkEvent, S: SecretId<PublicId = P>>(
         packed_content: Content<Vote<T, P>, EventHash, P>,
         ctx: EventContextRef<T, S>,
-    ) -> Result<(Self, ObservationForStore<T, P>), Error> {
-        let creator = ctx
-            .peer_list
-            .get_index(&packed_content.creator)
-            .ok_or(Error::UnknownPeer)?;
-        let (cause, observation_for_store) = Cause::unpack(packed_content.cause, creator, ctx)?;
+    ) -> Result<(Self, ObservationsForStore<T, P>), Error> {
+        let creator = if let Some(incarnation) = packed_content.cause.incarnation() {
+            // An initial event starts the chain of the membership of its creator it was created
+            // for, even if it reaches us after the creator was removed and rejoined.
+            ctx.peer_list
+                .membership_index(&packed_content.creator, incarnation)
+        } else {
+            // Follow the self-parent, so the events a peer created in each of its memberships
+            // stay in their own chain.
+            packed_content
+                .self_parent()
+                .and_then(|hash| ctx.graph.get_by_hash(hash))
+                .map(|self_parent| self_parent.creator())
+                .filter(|index| {
+                    ctx.peer_list
+                        .get(*index)
+                        .map(|peer| peer.has_id(&packed_content.creator))
+                        .unwrap_or(false)
+                })
+                .or_else(|| ctx.peer_list.get_index(&packed_content.creator))
+        }
+        .ok_or(Error::UnknownPeer)?;
+        let (cause, observations_for_store) = Cause::unpack(packed_content.cause, creator, ctx)?;
 
-        Ok((Self { creator, cause }, observation_for_store))
+        Ok((Self { creator, cause }, observations_for_store))
     }
 
+    // Packs this content of the event with the given index-by-creator, using the id the creator
+    // signed it with.
     pub(crate) fn pack<T: NetworkEvent, S: SecretId<PublicId = P>>(
         &self,
+        index_by_creator: usize,
         ctx: EventContextRef<T, S>,
     ) -> Result<Content<Vote<T, P>, EventHash, P>, Error> {
         Ok(Content {
             creator: ctx
                 .peer_list
-                .get(self.creator)
-                .map(|peer| peer.id().clone())
+                .id_at(self.creator, index_by_creator)
+                .cloned()
                 .ok_or(Error::UnknownPeer)?,
             cause: self.cause.pack(ctx)?,
         })
@@ -22,8 +22,10 @@ use crate::{
     hash::Hash,
     id::{PublicId, SecretId},
     network_event::NetworkEvent,
-    observation::{Observation, ObservationForStore, ObservationKey, ObservationStore},
-    peer_list::{PeerIndex, PeerIndexMap, PeerList},
+    observation::{
+        Observation, ObservationForStore, ObservationKey, ObservationStore, ObservationsForStore,
+    },
+    peer_list::{PeerIndex, PeerList},
     serialise,
     vote::{Vote, VoteKey},
 };
@@ -33,8 +35,18 @@ use crate::{
     observation::ConsensusMode,
 };
 use itertools::Itertools;
-use std::fmt::{self, Debug, Display, Formatter};
+use std::{
+    fmt::{self, Debug, Formatter},
+    iter, slice,
+};
+
+// Iterator over the payload keys of the votes carried by an event.
+pub(crate) type PayloadKeys<'a, P> =
+    iter::Map<slice::Iter<'a, VoteKey<P>>, fn(&VoteKey<P>) -> &ObservationKey>;
 
+// Serialised to be held by an `EventStore`.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
 pub(crate) struct Event<P: PublicId> {
     content: Content<VoteKey<P>, EventIndex, PeerIndex>,
     // Creator's signature of `content`.
@@ -49,11 +61,13 @@ impl<P: PublicId> Event<P> {
         recipient: &P,
         ctx: EventContextRef<T, S>,
     ) -> Result<Self, Error> {
+        let recipient = cause::recipient_index(ctx.peer_list, recipient)?;
         let content: Content<Vote<T, _>, _, _> = Content {
             creator: ctx.peer_list.our_pub_id().clone(),
             cause: Cause::Requesting {
                 self_parent: cause::self_parent_hash(ctx.graph, self_parent)?,
-                recipient: recipient.clone(),
+                recipient: cause::recipient_id(ctx.graph, ctx.peer_list, self_parent, recipient)?
+                    .clone(),
             },
         };
         let (hash, signature) = compute_event_hash_and_signature(&content, ctx.peer_list.our_id());
@@ -61,7 +75,7 @@ impl<P: PublicId> Event<P> {
             creator: PeerIndex::OUR,
             cause: Cause::Requesting {
                 self_parent,
-                recipient: cause::recipient_index(ctx.peer_list, recipient)?,
+                recipient,
             },
         };
 
@@ -156,27 +170,58 @@ impl<P: PublicId> Event<P> {
         let (hash, signature) = compute_event_hash_and_signature(&content, ctx.peer_list.our_id());
         let graph = ctx.graph;
         let peer_list = ctx.peer_list;
-        let (content, observation_for_store) = Content::unpack(content, ctx)?;
+        let (content, mut observations_for_store) = Content::unpack(content, ctx)?;
 
         Ok((
             Self::new(hash, signature, content, graph, peer_list),
-            observation_for_store,
+            observations_for_store.pop(),
         ))
     }
 
-    // Creates an initial event.  This is the first event by its creator in the graph.
+    // Creates a new event as the result of observing several network events at once.
+    pub fn new_from_batch<T: NetworkEvent, S: SecretId<PublicId = P>>(
+        self_parent: EventIndex,
+        observations: Vec<Observation<T, P>>,
+        ctx: EventContextRef<T, S>,
+    ) -> Result<(Self, ObservationsForStore<T, P>), Error> {
+        // Compute event hash + signature.
+        let votes = observations
+            .into_iter()
+            .map(|observation| Vote::new(ctx.peer_list.our_id(), observation))
+            .collect();
+        let content = Content {
+            creator: ctx.peer_list.our_pub_id().clone(),
+            cause: Cause::Batch {
+                self_parent: cause::self_parent_hash(ctx.graph, self_parent)?,
+                votes,
+            },
+        };
+        let (hash, signature) = compute_event_hash_and_signature(&content, ctx.peer_list.our_id());
+        let graph = ctx.graph;
+        let peer_list = ctx.peer_list;
+        let (content, observations_for_store) = Content::unpack(content, ctx)?;
+
+        Ok((
+            Self::new(hash, signature, content, graph, peer_list),
+            observations_for_store,
+        ))
+    }
+
+    // Creates an initial event.  This is the first event by its creator in the graph, in its
+    // current membership.
     pub fn new_initial<T: NetworkEvent, S: SecretId<PublicId = P>>(
         ctx: EventContextRef<T, S>,
     ) -> Self {
+        let incarnation = ctx.peer_list.our_incarnation();
         let content: Content<Vote<T, _>, _, _> = Content {
             creator: ctx.peer_list.our_pub_id().clone(),
-            cause: Cause::Initial,
+            cause: Cause::initial(incarnation),
         };
         let (hash, signature) = compute_event_hash_and_signature(&content, ctx.peer_list.our_id());
 
         let content = Content {
             creator: PeerIndex::OUR,
-            cause: Cause::Initial,
+            cause: Cause::initial(incarnation),
         };
 
         Self::new(hash, signature, content, ctx.graph, ctx.peer_list)
@@ -219,7 +264,7 @@ impl<P: PublicId> Event<P> {
 
         let graph = ctx.graph;
         let peer_list = ctx.peer_list;
-        let (content, observation_for_store) = Content::unpack(packed_event.content, ctx)?;
+        let (content, observations_for_store) = Content::unpack(packed_event.content, ctx)?;
         let cache = Cache::new(hash, &content, graph, peer_list);
 
         Ok(Some(UnpackedEvent {
@@ -228,7 +273,7 @@ impl<P: PublicId> Event<P> {
                 signature: packed_event.signature,
                 cache,
             },
-            observation_for_store,
+            observations_for_store,
         }))
     }
 
@@ -238,7 +283,7 @@ impl<P: PublicId> Event<P> {
         ctx: EventContextRef<T, S>,
     ) -> Result<PackedEvent<T, P>, Error> {
         Ok(PackedEvent {
-            content: self.content.pack(ctx)?,
+            content: self.content.pack(self.index_by_creator(), ctx)?,
             signature: self.signature.clone(),
         })
     }
@@ -247,17 +292,16 @@ impl<P: PublicId> Event<P> {
     pub fn is_descendant_of<E: AsRef<Event<P>>>(&self, other: E) -> bool {
         let other = other.as_ref();
 
-        let self_info = if let Some(info) = self.cache.ancestor_info.get(other.creator()) {
-            info
-        } else {
-            return false;
-        };
-
-        if self_info.last < other.index_by_creator() {
-            return false;
+        let info = &self.cache.ancestor_info;
+        match info.last_ancestor_by(other.creator()) {
+            Some(last) if last >= other.index_by_creator() => (),
+            _ => return false,
         }
 
-        if let Some(self_forks) = self_info.forks.get(&other.index_by_creator()) {
+        if let Some(self_forks) = info
+            .forks_by(other.creator())
+            .and_then(|forks| forks.get(&other.index_by_creator()))
+        {
             if let Some(other_forks) = other.fork_set() {
                 !self_forks.is_disjoint(other_forks)
             } else {
@@ -286,8 +330,8 @@ impl<P: PublicId> Event<P> {
     pub fn descends_from_fork(&self, creator: PeerIndex) -> bool {
         self.cache
             .ancestor_info
-            .get(creator)
-            .map(|info| info.forks.values().any(|fork_set| fork_set.len() > 1))
+            .forks_by(creator)
+            .map(|forks| forks.values().any(|fork_set| fork_set.len() > 1))
             .unwrap_or(false)
     }
 
@@ -301,10 +345,11 @@ impl<P: PublicId> Event<P> {
     pub fn fork_set(&self) -> Option<&IndexSet> {
         self.cache
             .ancestor_info
-            .get(self.creator())
-            .and_then(|info| info.forks.get(&self.index_by_creator()))
+            .forks_by(self.creator())
+            .and_then(|forks| forks.get(&self.index_by_creator()))
     }
 
+    // Payload key of the vote carried by this event, if it carries a single one.
     pub fn payload_key(&self) -> Option<&ObservationKey> {
         match self.content.cause {
             Cause::Observation { ref vote, .. } => Some(vote.payload_key()),
@@ -312,21 +357,48 @@ impl<P: PublicId> Event<P> {
         }
     }
 
-    pub fn vote_and_payload_key<T: NetworkEvent>(
-        &self,
-        observations: &ObservationStore<T, P>,
-    ) -> Option<(Vote<T, P>, ObservationKey)> {
+    // Payload keys of all the votes carried by this event, in the order they were cast.
+    pub fn payload_keys(&self) -> PayloadKeys<'_, P> {
+        let payload_key: fn(&VoteKey<P>) -> &ObservationKey = VoteKey::payload_key;
+        self.votes().iter().map(payload_key)
+    }
+
+    fn votes(&self) -> &[VoteKey<P>] {
         match self.content.cause {
-            Cause::Observation { ref vote, .. } => {
-                let key = *vote.payload_key();
-                let vote = vote.resolve(observations).ok()?;
+            Cause::Observation { ref vote, .. } => slice::from_ref(vote),
+            Cause::Batch { ref votes, .. } => votes,
+            _ => &[],
+        }
+    }
 
-                Some((vote, key))
+    // Changes the key of the votes carried by this event with the given key, after the consensus
+    // mode of their observation changed. The keys aren't part of the hash of the event.
+    pub(crate) fn rekey_vote(&mut self, old_key: &ObservationKey, new_key: ObservationKey) {
+        let votes = match self.content.cause {
+            Cause::Observation { ref mut vote, .. } => slice::from_mut(vote),
+            Cause::Batch { ref mut votes, .. } => votes,
+            _ => return,
+        };
+        for vote in votes {
+            if vote.payload_key() == old_key {
+                vote.set_payload_key(new_key);
             }
-            _ => None,
         }
     }
 
+    // The vote carried by this event for the given payload, if any.
+    pub fn vote<T: NetworkEvent>(
+        &self,
+        payload_key: &ObservationKey,
+        observations: &ObservationStore<T, P>,
+    ) -> Option<Vote<T, P>> {
+        self.votes()
+            .iter()
+            .find(|vote| vote.payload_key() == payload_key)?
+            .resolve(observations)
+            .ok()
+    }
+
     pub fn creator(&self) -> PeerIndex {
         self.content.creator
     }
@@ -349,14 +421,11 @@ impl<P: PublicId> Event<P> {
     }
 
     pub fn last_ancestors<'a>(&'a self) -> impl Iterator<Item = (PeerIndex, usize)> + 'a {
-        self.cache
-            .ancestor_info
-            .iter()
-            .map(|(peer_index, info)| (peer_index, info.last))
+        self.cache.ancestor_info.last_ancestors()
     }
 
     pub fn last_ancestor_by(&self, creator: PeerIndex) -> Option<usize> {
-        self.cache.ancestor_info.get(creator).map(|info| info.last)
+        self.cache.ancestor_info.last_ancestor_by(creator)
     }
 
     pub fn non_fork_last_ancestor_by(&self, creator: PeerIndex) -> Option<usize> {
@@ -368,17 +437,25 @@ impl<P: PublicId> Event<P> {
     }
 
     #[cfg(any(test, feature = "testing"))]
-    pub fn ancestor_info(&self) -> &PeerIndexMap<AncestorInfo> {
+    pub fn ancestor_info(&self) -> &AncestorInfo {
         &self.cache.ancestor_info
     }
 
     pub fn is_sync_event(&self) -> bool {
         match self.content.cause {
             Cause::Requesting { .. } | Cause::Request { .. } | Cause::Response { .. } => true,
-            Cause::Initial | Cause::Observation { .. } => false,
+            Cause::Initial
+            | Cause::Rejoined { .. }
+            | Cause::Observation { .. }
+            | Cause::Batch { .. } => false,
         }
     }
 
+    #[cfg(any(
+        all(test, feature = "mock"),
+        feature = "testing",
+        feature = "malice-detection"
+    ))]
     pub fn is_requesting(&self) -> bool {
         if let Cause::Requesting { .. } = self.content.cause {
             true
@@ -396,6 +473,11 @@ impl<P: PublicId> Event<P> {
         }
     }
 
+    #[cfg(any(
+        all(test, feature = "mock"),
+        feature = "testing",
+        feature = "malice-detection"
+    ))]
     pub fn is_request(&self) -> bool {
         if let Cause::Request { .. } = self.content.cause {
             true
@@ -404,6 +486,7 @@ impl<P: PublicId> Event<P> {
         }
     }
 
+    #[cfg(any(test, feature = "testing", feature = "malice-detection"))]
     pub fn is_response(&self) -> bool {
         if let Cause::Response { .. } = self.content.cause {
             true
@@ -413,11 +496,7 @@ impl<P: PublicId> Event<P> {
     }
 
     pub fn is_initial(&self) -> bool {
-        if let Cause::Initial = self.content.cause {
-            true
-        } else {
-            false
-        }
+        self.content.cause.incarnation().is_some()
     }
 
     /// Returns the first char of the creator's ID, followed by an underscore and the event's index.
@@ -470,8 +549,8 @@ impl<P: PublicId> Debug for Event<P> {
             ", last_ancestors: {:?}",
             self.cache
                 .ancestor_info
-                .iter()
-                .map(|(peer_id, info)| EntryDebug(peer_id, info.last))
+                .last_ancestors()
+                .map(|(peer_id, last)| EntryDebug(peer_id, last))
                 .format(", ")
         )?;
         write!(formatter, " }}")
@@ -499,8 +578,8 @@ impl Event<PeerId> {
     pub(crate) fn new_from_dot_input(
         creator: &PeerId,
         cause: CauseInput,
-        self_parent: Option<(EventIndex, EventHash, PeerIndexMap<AncestorInfo>)>,
-        other_parent: Option<(EventIndex, EventHash, PeerIndexMap<AncestorInfo>)>,
+        self_parent: Option<(EventIndex, EventHash, AncestorInfo)>,
+        other_parent: Option<(EventIndex, EventHash, AncestorInfo)>,
         index_by_creator: usize,
         consensus_mode: ConsensusMode,
         peer_list: &mut PeerList<PeerId>,
@@ -569,28 +648,31 @@ impl Event<PeerId> {
 #[derive(Debug)]
 pub(crate) struct UnpackedEvent<T: NetworkEvent, P: PublicId> {
     pub event: Event<P>,
-    pub observation_for_store: ObservationForStore<T, P>,
+    pub observations_for_store: ObservationsForStore<T, P>,
 }
 
 #[cfg(any(test, feature = "testing"))]
 #[derive(Debug)]
 pub(crate) enum CauseInput {
     Initial,
+    Rejoined(usize),
     Requesting(PeerId),
     Request,
     Response,
     Observation(Observation<Transaction, PeerId>),
+    Batch(Vec<Observation<Transaction, PeerId>>),
     Malice(PeerId, MaliceInput),
 }
 
 // Properties of `Event` that can be computed from its `Content`.
+#[derive(Serialize, Deserialize)]
 struct Cache {
     // Hash of `Event`s `Content`.
     hash: EventHash,
     // Index of this event relative to other events by the same creator.
     index_by_creator: usize,
     // Info about events that are ancestors of this event keyed by their creators.
-    ancestor_info: PeerIndexMap<AncestorInfo>,
+    ancestor_info: AncestorInfo,
     // First letter of the creator name.
     #[cfg(any(test, feature = "testing"))]
     creator_initial: char,
@@ -680,18 +762,21 @@ fn get_creator_initial<S: SecretId>(peer_list: &PeerList<S>, creator: PeerIndex)
         .unwrap_or('?')
 }
 
+#[cfg(any(test, feature = "testing"))]
 #[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub(crate) struct ShortName {
     creator_initial: char,
     index_by_creator: usize,
 }
 
-impl Display for ShortName {
+#[cfg(any(test, feature = "testing"))]
+impl fmt::Display for ShortName {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "{}_{}", self.creator_initial, self.index_by_creator)
     }
 }
 
+#[cfg(any(test, feature = "testing"))]
 impl Debug for ShortName {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "\"{}\"", self)
@@ -729,7 +814,7 @@ mod tests {
     ) -> (EventIndex, EventHash) {
         let hash = *initial_event.hash();
         assert!(!graph.contains(&hash));
-        (graph.insert(initial_event).event_index(), hash)
+        (unwrap!(graph.insert(initial_event)).event_index(), hash)
     }
 
     fn create_two_events(
@@ -834,8 +919,8 @@ mod tests {
     fn event_construction_from_request() {
         let (mut alice, a_0, bob, b_0) = create_two_events("Alice", "Bob");
         let b_0 = convert_event(&b_0, bob.as_ref(), alice.as_ref());
-        let a_0_index = alice.graph.insert(a_0).event_index();
-        let b_0_index = alice.graph.insert(b_0).event_index();
+        let a_0_index = unwrap!(alice.graph.insert(a_0)).event_index();
+        let b_0_index = unwrap!(alice.graph.insert(b_0)).event_index();
 
         // Alice receives request from Bob
         let event_from_request = unwrap!(Event::new_from_request(
@@ -862,7 +947,7 @@ mod tests {
     fn event_construction_from_request_without_self_parent_event_in_graph() {
         let (mut alice, _, bob, b_0) = create_two_events("Alice", "Bob");
         let b_0 = convert_event(&b_0, bob.as_ref(), alice.as_ref());
-        let b_0_index = alice.graph.insert(b_0).event_index();
+        let b_0_index = unwrap!(alice.graph.insert(b_0)).event_index();
 
         match Event::new_from_request(EventIndex::PHONY, b_0_index, alice.as_ref()) {
             Err(Error::UnknownSelfParent) => (),
@@ -874,7 +959,7 @@ mod tests {
     #[cfg(feature = "testing")]
     fn event_construction_from_request_without_other_parent_event_in_graph() {
         let (mut alice, a_0, _, _) = create_two_events("Alice", "Bob");
-        let a_0_index = alice.graph.insert(a_0).event_index();
+        let a_0_index = unwrap!(alice.graph.insert(a_0)).event_index();
 
         match Event::new_from_request(a_0_index, EventIndex::PHONY, alice.as_ref()) {
             Err(Error::UnknownOtherParent) => (),
@@ -886,8 +971,8 @@ mod tests {
     fn event_construction_from_response() {
         let (mut alice, a_0, bob, b_0) = create_two_events("Alice", "Bob");
         let b_0 = convert_event(&b_0, bob.as_ref(), alice.as_ref());
-        let a_0_index = alice.graph.insert(a_0).event_index();
-        let b_0_index = alice.graph.insert(b_0).event_index();
+        let a_0_index = unwrap!(alice.graph.insert(a_0)).event_index();
+        let b_0_index = unwrap!(alice.graph.insert(b_0)).event_index();
 
         let event_from_response = unwrap!(Event::new_from_response(
             a_0_index,
@@ -910,7 +995,7 @@ mod tests {
     #[test]
     fn event_construction_unpack() {
         let (mut alice, a_0) = create_event_with_single_peer("Alice");
-        let a_0_index = alice.graph.insert(a_0).event_index();
+        let a_0_index = unwrap!(alice.graph.insert(a_0)).event_index();
 
         // Our observation
         let net_event = Observation::OpaquePayload(Transaction::new("event_observed_by_alice"));
@@ -931,7 +1016,7 @@ mod tests {
         assert_eq!(event_from_observation, unpacked_event);
         assert!(!alice.graph.contains(unpacked_event.hash()));
 
-        let _ = alice.graph.insert(unpacked_event);
+        let _ = unwrap!(alice.graph.insert(unpacked_event));
 
         assert!(unwrap!(Event::unpack(packed_event, alice.as_ref())).is_none());
     }
@@ -939,7 +1024,7 @@ mod tests {
     #[test]
     fn event_construction_unpack_fail_with_wrong_signature() {
         let (mut alice, a_0) = create_event_with_single_peer("Alice");
-        let a_0_index = alice.graph.insert(a_0).event_index();
+        let a_0_index = unwrap!(alice.graph.insert(a_0)).event_index();
 
         // Our observation
         let net_event = Observation::OpaquePayload(Transaction::new("event_observed_by_alice"));
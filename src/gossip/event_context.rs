@@ -26,12 +26,7 @@ pub(crate) struct EventContextRef<'a, T: NetworkEvent, S: SecretId> {
 // `#[derive(Clone)]` doesn't work here for some reason...
 impl<'a, T: NetworkEvent, S: SecretId> Clone for EventContextRef<'a, T, S> {
     fn clone(&self) -> Self {
-        Self {
-            graph: self.graph,
-            peer_list: self.peer_list,
-            observations: self.observations,
-            consensus_mode: self.consensus_mode,
-        }
+        *self
     }
 }
 
@@ -62,7 +57,7 @@ mod tests {
             }
         }
 
-        pub fn as_ref(&self) -> EventContextRef<Transaction, PeerId> {
+        pub fn as_ref(&self) -> EventContextRef<'_, Transaction, PeerId> {
             EventContextRef {
                 graph: &self.graph,
                 peer_list: &self.peer_list,
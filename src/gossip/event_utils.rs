@@ -10,13 +10,12 @@ use crate::{
     id::SecretId,
     peer_list::{PeerIndex, PeerIndexMap, PeerList},
 };
-use fnv::FnvHashSet;
 use itertools::{EitherOrBoth, Itertools};
 use std::{
     cmp,
-    collections::{BTreeMap, BTreeSet},
+    collections::BTreeMap,
     fmt::{self, Debug, Formatter},
-    iter,
+    rc::Rc,
 };
 
 // Map of forks created by single peer in the ancestry of the current event.
@@ -33,136 +32,264 @@ use std::{
 // be a fork, but we cannot prove it yet using just the ancestors of the current event.
 pub(super) type ForkMap = BTreeMap<usize, IndexSet>;
 
-// Immutable set of integer indices
-#[derive(Clone, Eq, PartialEq)]
-pub(crate) struct IndexSet(FnvHashSet<usize>);
+// Immutable set of integer indices, stored as a dense bitset. The indices are fork indices, which
+// are small, so the set almost always fits in a single word.
+//
+// The last word is never zero, so that equal sets have equal representations.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) struct IndexSet(Vec<u64>);
+
+const BITS_PER_WORD: usize = 64;
 
 impl IndexSet {
     pub fn new(index: usize) -> Self {
-        IndexSet(iter::once(index).collect())
+        IndexSet(Vec::new()).insert(index)
     }
 
     pub fn union(&self, other: &Self) -> Self {
-        IndexSet(self.0.union(&other.0).cloned().collect())
+        let (longer, shorter) = if self.0.len() >= other.0.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+
+        let mut words = longer.0.clone();
+        for (word, other_word) in words.iter_mut().zip(&shorter.0) {
+            *word |= *other_word;
+        }
+        IndexSet(words)
     }
 
     pub fn insert(&self, index: usize) -> Self {
-        let mut set = self.0.clone();
-        let _ = set.insert(index);
-        IndexSet(set)
+        let (word_index, bit) = split(index);
+        let mut words = self.0.clone();
+        if words.len() <= word_index {
+            words.resize(word_index + 1, 0);
+        }
+        words[word_index] |= bit;
+        IndexSet(words)
     }
 
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
     }
 
     pub fn contains(&self, index: usize) -> bool {
-        self.0.contains(&index)
+        let (word_index, bit) = split(index);
+        self.0
+            .get(word_index)
+            .map(|word| word & bit != 0)
+            .unwrap_or(false)
     }
 
     pub fn is_disjoint(&self, other: &Self) -> bool {
-        self.0.is_disjoint(&other.0)
+        self.0
+            .iter()
+            .zip(&other.0)
+            .all(|(word, other_word)| word & other_word == 0)
     }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = usize> + 'a {
+        self.0.iter().enumerate().flat_map(|(word_index, word)| {
+            (0..BITS_PER_WORD)
+                .filter(move |bit_index| *word & (1u64 << bit_index) != 0)
+                .map(move |bit_index| word_index * BITS_PER_WORD + bit_index)
+        })
+    }
+}
+
+// Splits `index` into the index of the word holding it and the mask of its bit in that word.
+fn split(index: usize) -> (usize, u64) {
+    (index / BITS_PER_WORD, 1 << (index % BITS_PER_WORD))
 }
 
 impl Debug for IndexSet {
     fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
-        let values = self.0.iter().collect::<BTreeSet<_>>();
-        write!(formatter, "{}", values.iter().format(", "))
+        write!(formatter, "{}", self.iter().format(", "))
     }
 }
 
-// Information about ancestor events.
-#[derive(Clone, Debug, Default)]
+// Information about the ancestors of an event, by their creators.
+//
+// The last ancestors and the fork maps are kept in separate maps. The former only holds plain
+// indices, so deriving the info of a new event from its parents' is a copy and an elementwise
+// max. The latter is empty unless there are forks in the ancestry, so it usually costs nothing.
+//
+// Both maps are shared with the parents whenever they'd be equal, and only copied on write. The
+// entry of the event's own creator in `last` is overridden by the event itself, so an event gets
+// the very map of its self-parent unless its other parent brings newer ancestors. That's the case
+// of every requesting sync event and every vote.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub(crate) struct AncestorInfo {
-    // index-by-creator of the last event by the current peer that is ancestor of the current
-    // event.
-    pub last: usize,
-    // Info about forks in the ancestry of the current event.
-    pub forks: ForkMap,
+    // Creator and index-by-creator of the current event, which override the creator's entry in
+    // `last`.
+    creator: PeerIndex,
+    index_by_creator: usize,
+    // index-by-creator of the last event by each peer that is ancestor of the current event. The
+    // entry of `creator` is always present, but may be outdated.
+    last: Rc<PeerIndexMap<usize>>,
+    // Info about forks by each peer in the ancestry of the current event. A peer has an entry
+    // here only if it has a last ancestor too.
+    forks: Rc<PeerIndexMap<ForkMap>>,
 }
 
 impl AncestorInfo {
-    pub fn new() -> Self {
-        Self {
-            last: 0,
-            forks: ForkMap::new(),
+    pub fn last_ancestor_by(&self, creator: PeerIndex) -> Option<usize> {
+        if creator == self.creator {
+            Some(self.index_by_creator)
+        } else {
+            self.last.get(creator).cloned()
         }
     }
+
+    pub fn last_ancestors<'a>(&'a self) -> impl Iterator<Item = (PeerIndex, usize)> + 'a {
+        self.last.iter().map(move |(peer_index, last)| {
+            if peer_index == self.creator {
+                (peer_index, self.index_by_creator)
+            } else {
+                (peer_index, *last)
+            }
+        })
+    }
+
+    pub fn forks_by(&self, creator: PeerIndex) -> Option<&ForkMap> {
+        self.forks.get(creator)
+    }
 }
 
 pub(super) fn compute_ancestor_info<S: SecretId>(
     creator: PeerIndex,
     index_by_creator: usize,
-    self_parent_info: Option<&PeerIndexMap<AncestorInfo>>,
-    other_parent_info: Option<&PeerIndexMap<AncestorInfo>>,
+    self_parent_info: Option<&AncestorInfo>,
+    other_parent_info: Option<&AncestorInfo>,
     peer_list: &PeerList<S>,
-) -> PeerIndexMap<AncestorInfo> {
-    let mut result = match (self_parent_info, other_parent_info) {
-        (Some(self_parent_info), Some(other_parent_info)) => {
-            merge_ancestor_info_maps(self_parent_info, other_parent_info)
+) -> AncestorInfo {
+    let (mut last, mut forks) = match (self_parent_info, other_parent_info) {
+        (Some(self_parent_info), Some(other_parent_info)) => (
+            merge_last_ancestors(creator, self_parent_info, other_parent_info),
+            merge_forks(self_parent_info, other_parent_info),
+        ),
+        (Some(info), None) | (None, Some(info)) => {
+            (last_ancestors_for(creator, info), Rc::clone(&info.forks))
         }
-        (Some(self_parent_info), None) => self_parent_info.clone(),
-        (None, Some(other_parent_info)) => other_parent_info.clone(),
-        (None, None) => PeerIndexMap::new(),
+        (None, None) => (Rc::default(), Rc::default()),
     };
 
-    let info = result.entry(creator).or_insert_with(AncestorInfo::new);
-    info.last = index_by_creator;
+    if !last.contains_key(creator) {
+        let _ = Rc::make_mut(&mut last).insert(creator, index_by_creator);
+    }
 
     let fork_index = peer_list.events_by_index(creator, index_by_creator).count();
     if fork_index > 0 {
-        let _ = info
-            .forks
+        let _ = Rc::make_mut(&mut forks)
+            .entry(creator)
+            .or_insert_with(ForkMap::new)
             .insert(index_by_creator, IndexSet::new(fork_index));
     }
 
-    result
+    AncestorInfo {
+        creator,
+        index_by_creator,
+        last,
+        forks,
+    }
 }
 
-fn merge_ancestor_info_maps(
-    map0: &PeerIndexMap<AncestorInfo>,
-    map1: &PeerIndexMap<AncestorInfo>,
-) -> PeerIndexMap<AncestorInfo> {
-    // Merge the two maps: if an entry exists in only one of the map, copy it over,
-    // if it exists in both, merge them by calling `AncestorInfo::merge`.
-    // We can use `merge_join_by` from `itertools` because `PeerIndexMap::iter` yields the entries
-    // in ascending order.
-    map0.iter()
-        .merge_join_by(map1.iter(), |(peer_index0, _), (peer_index1, _)| {
-            peer_index0.cmp(peer_index1)
-        })
-        .map(|either| match either {
-            EitherOrBoth::Left((peer_index, info)) | EitherOrBoth::Right((peer_index, info)) => {
-                (peer_index, info.clone())
-            }
-            EitherOrBoth::Both((peer_index, info0), (_, info1)) => {
-                (peer_index, merge_ancestor_infos(info0, info1))
-            }
-        })
-        .collect()
+// Last ancestors of an event by `creator` whose only parent has the given info. They are shared
+// with the parent if it's by the same creator.
+fn last_ancestors_for(creator: PeerIndex, info: &AncestorInfo) -> Rc<PeerIndexMap<usize>> {
+    if info.creator == creator {
+        Rc::clone(&info.last)
+    } else {
+        Rc::new(info.last_ancestors().collect())
+    }
 }
 
-fn merge_ancestor_infos(lhs_info: &AncestorInfo, rhs_info: &AncestorInfo) -> AncestorInfo {
-    AncestorInfo {
-        last: cmp::max(lhs_info.last, rhs_info.last),
-        forks: merge_fork_maps(lhs_info, rhs_info),
+fn merge_last_ancestors(
+    creator: PeerIndex,
+    lhs_info: &AncestorInfo,
+    rhs_info: &AncestorInfo,
+) -> Rc<PeerIndexMap<usize>> {
+    // The entry of `creator` is overridden by the new event, so it doesn't matter here.
+    let is_newer = |(peer_index, rhs_last)| {
+        peer_index != creator
+            && lhs_info
+                .last_ancestor_by(peer_index)
+                .is_none_or(|lhs_last| lhs_last < rhs_last)
+    };
+    if !rhs_info.last_ancestors().any(is_newer) {
+        return last_ancestors_for(creator, lhs_info);
     }
+
+    // `PeerIndexMap` is a dense vector, so the last ancestors of the first side are copied in one
+    // go and updated in place with those of the second side.
+    let mut last = (*lhs_info.last).clone();
+    let _ = last.insert(lhs_info.creator, lhs_info.index_by_creator);
+    for (peer_index, rhs_last) in rhs_info.last_ancestors() {
+        let lhs_last = last.entry(peer_index).or_insert(rhs_last);
+        *lhs_last = cmp::max(*lhs_last, rhs_last);
+    }
+    Rc::new(last)
 }
 
-fn merge_fork_maps(lhs_info: &AncestorInfo, rhs_info: &AncestorInfo) -> ForkMap {
-    lhs_info
+fn merge_forks(lhs_info: &AncestorInfo, rhs_info: &AncestorInfo) -> Rc<PeerIndexMap<ForkMap>> {
+    // In the common case, there are no forks in the ancestry of either side.
+    if rhs_info.forks.is_empty() && lhs_info.forks.is_empty() {
+        return Rc::clone(&lhs_info.forks);
+    }
+
+    let no_forks = ForkMap::new();
+    let forks: PeerIndexMap<_> = lhs_info
         .forks
+        .keys()
+        .merge(rhs_info.forks.keys())
+        .dedup()
+        .map(|peer_index| {
+            let lhs_forks = lhs_info.forks_by(peer_index).unwrap_or(&no_forks);
+            let rhs_forks = rhs_info.forks_by(peer_index).unwrap_or(&no_forks);
+            let forks = match (
+                lhs_info.last_ancestor_by(peer_index),
+                rhs_info.last_ancestor_by(peer_index),
+            ) {
+                (Some(lhs_last), Some(rhs_last)) => {
+                    merge_fork_maps(lhs_forks, lhs_last, rhs_forks, rhs_last)
+                }
+                (Some(_), None) => lhs_forks.clone(),
+                (None, _) => rhs_forks.clone(),
+            };
+            (peer_index, forks)
+        })
+        .collect();
+
+    // Once a fork is known on both sides, their fork maps are usually the same.
+    if forks == *lhs_info.forks {
+        Rc::clone(&lhs_info.forks)
+    } else if forks == *rhs_info.forks {
+        Rc::clone(&rhs_info.forks)
+    } else {
+        Rc::new(forks)
+    }
+}
+
+// Merges the fork maps of a single peer, given the index-by-creator of the last event by that peer
+// in the ancestry of either side.
+fn merge_fork_maps(
+    lhs_forks: &ForkMap,
+    lhs_last: usize,
+    rhs_forks: &ForkMap,
+    rhs_last: usize,
+) -> ForkMap {
+    lhs_forks
         .iter()
-        .merge_join_by(rhs_info.forks.iter(), |(key0, _), (key1, _)| key0.cmp(key1))
+        .merge_join_by(rhs_forks.iter(), |(key0, _), (key1, _)| key0.cmp(key1))
         .map(|either| match either {
             EitherOrBoth::Left((&index_by_creator, lhs_fork_set)) => (
                 index_by_creator,
-                merge_with_implicit_fork_set(lhs_fork_set, index_by_creator, rhs_info.last),
+                merge_with_implicit_fork_set(lhs_fork_set, index_by_creator, rhs_last),
             ),
             EitherOrBoth::Right((&index_by_creator, rhs_fork_set)) => (
                 index_by_creator,
-                merge_with_implicit_fork_set(rhs_fork_set, index_by_creator, lhs_info.last),
+                merge_with_implicit_fork_set(rhs_fork_set, index_by_creator, lhs_last),
             ),
             EitherOrBoth::Both((&index_by_creator, lhs_fork_set), (_, rhs_fork_set)) => {
                 (index_by_creator, lhs_fork_set.union(rhs_fork_set))
@@ -191,6 +318,24 @@ fn merge_with_implicit_fork_set(
 mod tests {
     use super::*;
 
+    #[test]
+    fn index_set_spanning_several_words() {
+        let lhs = IndexSet::new(1).insert(70);
+        let rhs = IndexSet::new(200);
+
+        assert_eq!(lhs.len(), 2);
+        assert!(lhs.contains(70));
+        assert!(!lhs.contains(6));
+        assert!(lhs.is_disjoint(&rhs));
+
+        let union = lhs.union(&rhs);
+        assert_eq!(union.len(), 3);
+        assert!(!union.is_disjoint(&rhs));
+        assert_eq!(union, rhs.union(&lhs));
+        assert_eq!(union, IndexSet::new(200).insert(70).insert(1));
+        assert_eq!(format!("{:?}", union), "1, 70, 200");
+    }
+
     #[test]
     fn merge_fork_maps_of_events_that_are_not_descendants_of_any_fork() {
         // Merge C's fork maps of A1 and B1:
@@ -201,17 +346,16 @@ mod tests {
         // |     |     |
         // A0    B0    C0
 
-        let a_info = AncestorInfo {
-            last: 0,
-            forks: btree_map![],
-        };
+        let a_last = 0;
+        let a_forks = btree_map![];
 
-        let b_info = AncestorInfo {
-            last: 0,
-            forks: btree_map![],
-        };
+        let b_last = 0;
+        let b_forks = btree_map![];
 
-        assert_eq!(merge_fork_maps(&a_info, &b_info), btree_map![])
+        assert_eq!(
+            merge_fork_maps(&a_forks, a_last, &b_forks, b_last),
+            btree_map![]
+        )
     }
 
     #[test]
@@ -227,18 +371,14 @@ mod tests {
         // |     |       |
         // A0    B0      C0
 
-        let a_info = AncestorInfo {
-            last: 1,
-            forks: btree_map![],
-        };
+        let a_last = 1;
+        let a_forks = btree_map![];
 
-        let b_info = AncestorInfo {
-            last: 1,
-            forks: btree_map![1 => IndexSet::new(1)],
-        };
+        let b_last = 1;
+        let b_forks = btree_map![1 => IndexSet::new(1)];
 
         assert_eq!(
-            merge_fork_maps(&a_info, &b_info),
+            merge_fork_maps(&a_forks, a_last, &b_forks, b_last),
             btree_map![1 => IndexSet::new(0).insert(1)]
         )
     }
@@ -256,18 +396,14 @@ mod tests {
         // |     |       |
         // A0    B0      C0
 
-        let a_info = AncestorInfo {
-            last: 1,
-            forks: btree_map![1 => IndexSet::new(1)],
-        };
+        let a_last = 1;
+        let a_forks = btree_map![1 => IndexSet::new(1)];
 
-        let b_info = AncestorInfo {
-            last: 1,
-            forks: btree_map![1 => IndexSet::new(1)],
-        };
+        let b_last = 1;
+        let b_forks = btree_map![1 => IndexSet::new(1)];
 
         assert_eq!(
-            merge_fork_maps(&a_info, &b_info),
+            merge_fork_maps(&a_forks, a_last, &b_forks, b_last),
             btree_map![1 => IndexSet::new(1)]
         )
     }
@@ -287,19 +423,107 @@ mod tests {
         // |     |       |
         // A0    B0      C0
 
-        let a_info = AncestorInfo {
-            last: 1,
-            forks: btree_map![],
-        };
+        let a_last = 1;
+        let a_forks = btree_map![];
 
-        let b_info = AncestorInfo {
-            last: 2,
-            forks: btree_map![2 => IndexSet::new(1)],
-        };
+        let b_last = 2;
+        let b_forks = btree_map![2 => IndexSet::new(1)];
 
         assert_eq!(
-            merge_fork_maps(&a_info, &b_info),
+            merge_fork_maps(&a_forks, a_last, &b_forks, b_last),
             btree_map![2 => IndexSet::new(1)]
         )
     }
+
+    #[test]
+    fn merge_ancestor_infos_of_events_that_are_descendants_of_forks_by_different_peers() {
+        let p0 = PeerIndex::new_test_peer_index(0);
+        let p1 = PeerIndex::new_test_peer_index(1);
+        let p2 = PeerIndex::new_test_peer_index(2);
+
+        // The outdated entry of each creator is overridden.
+        let lhs_info = AncestorInfo {
+            creator: p0,
+            index_by_creator: 3,
+            last: Rc::new(vec![(p0, 2), (p1, 1)].into_iter().collect()),
+            forks: Rc::new(
+                vec![
+                    (p0, btree_map![3 => IndexSet::new(1)]),
+                    (p1, btree_map![1 => IndexSet::new(1)]),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+        };
+
+        let rhs_info = AncestorInfo {
+            creator: p2,
+            index_by_creator: 4,
+            last: Rc::new(vec![(p0, 3), (p1, 0), (p2, 3)].into_iter().collect()),
+            forks: Rc::new(
+                vec![(p2, btree_map![4 => IndexSet::new(2)])]
+                    .into_iter()
+                    .collect(),
+            ),
+        };
+
+        let info = AncestorInfo {
+            creator: p0,
+            index_by_creator: 4,
+            last: merge_last_ancestors(p0, &lhs_info, &rhs_info),
+            forks: merge_forks(&lhs_info, &rhs_info),
+        };
+        assert_eq!(
+            info.last_ancestors().collect::<Vec<_>>(),
+            vec![(p0, 4), (p1, 1), (p2, 4)]
+        );
+        assert_eq!(
+            info.forks_by(p0),
+            Some(&btree_map![3 => IndexSet::new(0).insert(1)])
+        );
+        assert_eq!(info.forks_by(p1), Some(&btree_map![1 => IndexSet::new(1)]));
+        assert_eq!(info.forks_by(p2), Some(&btree_map![4 => IndexSet::new(2)]));
+    }
+
+    #[test]
+    fn ancestor_infos_are_shared_unless_the_other_parent_brings_newer_ancestors() {
+        let p0 = PeerIndex::new_test_peer_index(0);
+        let p1 = PeerIndex::new_test_peer_index(1);
+        let p2 = PeerIndex::new_test_peer_index(2);
+
+        let self_parent_info = AncestorInfo {
+            creator: p0,
+            index_by_creator: 3,
+            last: Rc::new(vec![(p0, 2), (p1, 1), (p2, 2)].into_iter().collect()),
+            forks: Rc::default(),
+        };
+        let older_info = AncestorInfo {
+            creator: p1,
+            index_by_creator: 1,
+            last: Rc::new(vec![(p0, 3), (p1, 0), (p2, 2)].into_iter().collect()),
+            forks: Rc::default(),
+        };
+        let newer_info = AncestorInfo {
+            creator: p2,
+            index_by_creator: 3,
+            last: Rc::new(vec![(p0, 3), (p1, 1), (p2, 2)].into_iter().collect()),
+            forks: Rc::default(),
+        };
+
+        let last = last_ancestors_for(p0, &self_parent_info);
+        assert!(Rc::ptr_eq(&last, &self_parent_info.last));
+
+        let last = merge_last_ancestors(p0, &self_parent_info, &older_info);
+        assert!(Rc::ptr_eq(&last, &self_parent_info.last));
+        let forks = merge_forks(&self_parent_info, &older_info);
+        assert!(Rc::ptr_eq(&forks, &self_parent_info.forks));
+
+        let last = merge_last_ancestors(p0, &self_parent_info, &newer_info);
+        assert!(!Rc::ptr_eq(&last, &self_parent_info.last));
+        assert_eq!(last.get(p2), Some(&3));
+
+        // The only parent being by another creator, its own entry has to be written.
+        let last = last_ancestors_for(p0, &older_info);
+        assert_eq!(last.get(p1), Some(&1));
+    }
 }
@@ -27,7 +27,7 @@ impl<'a, P: PublicId> Iterator for Ancestors<'a, P> {
         // a single event more than once.
 
         loop {
-            let event = *self.queue.iter().rev().next()?;
+            let event = *self.queue.iter().next_back()?;
             let _ = self.queue.remove(&event);
 
             if self.visited[event.topological_index()] {
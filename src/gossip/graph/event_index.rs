@@ -6,9 +6,14 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use std::usize;
+use std::{
+    cmp,
+    collections::VecDeque,
+    fmt::{self, Debug, Formatter},
+    iter::FromIterator,
+};
 
-#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize, Debug)]
 pub(crate) struct EventIndex(pub(super) usize);
 
 impl EventIndex {
@@ -20,3 +25,118 @@ impl EventIndex {
         self.0
     }
 }
+
+/// Map keyed by `EventIndex`. Event indices are dense, so the entries are held in a vector, offset
+/// by the index of the first one so that entries can be released from the front.
+#[derive(Clone, Eq, PartialEq)]
+pub(crate) struct EventIndexMap<T> {
+    // Topological index of the first entry.
+    offset: usize,
+    // Neither the first nor the last entry is ever `None`, so that equal maps have equal
+    // representations.
+    entries: VecDeque<Option<T>>,
+}
+
+impl<T> EventIndexMap<T> {
+    pub fn new() -> Self {
+        EventIndexMap {
+            offset: 0,
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn get(&self, key: EventIndex) -> Option<&T> {
+        let position = key.0.checked_sub(self.offset)?;
+        self.entries.get(position).and_then(Option::as_ref)
+    }
+
+    pub fn insert(&mut self, key: EventIndex, value: T) -> Option<T> {
+        if self.entries.is_empty() {
+            self.offset = key.0;
+        }
+        while key.0 < self.offset {
+            self.entries.push_front(None);
+            self.offset -= 1;
+        }
+        let position = key.0 - self.offset;
+        while self.entries.len() <= position {
+            self.entries.push_back(None);
+        }
+        self.entries[position].replace(value)
+    }
+
+    pub fn remove(&mut self, key: EventIndex) -> Option<T> {
+        let position = key.0.checked_sub(self.offset)?;
+        let value = self.entries.get_mut(position)?.take();
+        self.trim();
+        value
+    }
+
+    /// Releases all the entries with a topological index below `start_index`.
+    pub fn remove_before(&mut self, start_index: usize) {
+        let count = cmp::min(start_index.saturating_sub(self.offset), self.entries.len());
+        let _ = self.entries.drain(..count);
+        self.offset += count;
+        self.trim();
+    }
+
+    pub fn clear(&mut self) {
+        self.offset = 0;
+        self.entries.clear();
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (EventIndex, &'a T)> + 'a {
+        let offset = self.offset;
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(move |(position, value)| {
+                value
+                    .as_ref()
+                    .map(|value| (EventIndex(offset + position), value))
+            })
+    }
+
+    pub fn values_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut T> + 'a {
+        self.entries.iter_mut().filter_map(Option::as_mut)
+    }
+
+    // Drops the empty entries from both ends.
+    fn trim(&mut self) {
+        while let Some(None) = self.entries.front() {
+            let _ = self.entries.pop_front();
+            self.offset += 1;
+        }
+        while let Some(None) = self.entries.back() {
+            let _ = self.entries.pop_back();
+        }
+        if self.entries.is_empty() {
+            self.offset = 0;
+        }
+    }
+}
+
+impl<T> Default for EventIndexMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FromIterator<(EventIndex, T)> for EventIndexMap<T> {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (EventIndex, T)>,
+    {
+        let mut map = Self::new();
+        for (index, value) in iter {
+            let _ = map.insert(index, value);
+        }
+        map
+    }
+}
+
+impl<T: Debug> Debug for EventIndexMap<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
@@ -7,10 +7,13 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use super::{
-    super::{abstract_event::AbstractEventRef, event::Event},
+    super::{
+        abstract_event::AbstractEventRef,
+        event::{Event, PayloadKeys},
+    },
     event_index::EventIndex,
 };
-use crate::{id::PublicId, observation::ObservationKey, peer_list::PeerIndex};
+use crate::{id::PublicId, peer_list::PeerIndex};
 use std::{
     cmp::{Ord, Ordering, PartialOrd},
     ops::Deref,
@@ -66,7 +69,7 @@ impl<'a, P: PublicId> Eq for IndexedEventRef<'a, P> {}
 
 impl<'a, P: PublicId> PartialOrd for IndexedEventRef<'a, P> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.index.partial_cmp(&other.index)
+        Some(self.cmp(other))
     }
 }
 
@@ -77,8 +80,10 @@ impl<'a, P: PublicId> Ord for IndexedEventRef<'a, P> {
 }
 
 impl<'a, P: PublicId> AbstractEventRef<'a> for IndexedEventRef<'a, P> {
-    fn payload_key(self) -> Option<&'a ObservationKey> {
-        self.event.payload_key()
+    type PayloadKeys = PayloadKeys<'a, P>;
+
+    fn payload_keys(self) -> Self::PayloadKeys {
+        self.event.payload_keys()
     }
 
     fn creator(self) -> PeerIndex {
@@ -0,0 +1,316 @@
+// Copyright 2019 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    super::{event::Event, event_hash::EventHash},
+    event_index::EventIndex,
+};
+use crate::{
+    event_store::{EventStore, StoredEvent},
+    id::PublicId,
+};
+use elsa::FrozenMap;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::{self, Debug, Formatter},
+    mem, vec,
+};
+
+/// Events of the gossip graph, in insertion order, which is a topological order. They are found
+/// by index or by hash.
+///
+/// They are held in memory unless the graph was given an `EventStore`. Dispatching with an enum
+/// rather than a trait object keeps the in-memory path as fast as a plain `Vec`.
+#[derive(Debug)]
+pub(crate) enum Events<P: PublicId> {
+    Memory(MemoryEvents<P>),
+    Stored(StoredEvents<P>),
+}
+
+impl<P: PublicId> Default for Events<P> {
+    fn default() -> Self {
+        Events::Memory(MemoryEvents::default())
+    }
+}
+
+impl<P: PublicId> Events<P> {
+    /// Appends an event not already stored, and returns its index.
+    pub fn push(&mut self, event: Event<P>) -> EventIndex {
+        match self {
+            Events::Memory(events) => events.push(event),
+            Events::Stored(events) => events.push(event),
+        }
+    }
+
+    /// Removes the last event, if any. Events held by an `EventStore` can't be removed.
+    #[cfg(all(test, feature = "mock"))]
+    pub fn pop(&mut self) -> Option<Event<P>> {
+        match self {
+            Events::Memory(events) => events.pop(),
+            Events::Stored(_) => panic!("Can't remove an event from an EventStore"),
+        }
+    }
+
+    /// Gets the event with the given index, if it exists.
+    pub fn get(&self, index: EventIndex) -> Option<&Event<P>> {
+        match self {
+            Events::Memory(events) => events.get(index),
+            Events::Stored(events) => events.get(index),
+        }
+    }
+
+    /// Gets a mutable reference to the event with the given index, if it exists.
+    pub fn get_mut(&mut self, index: EventIndex) -> Option<&mut Event<P>> {
+        match self {
+            Events::Memory(events) => events.get_mut(index),
+            Events::Stored(events) => events.get_mut(index),
+        }
+    }
+
+    /// Gets the index of the event with the given hash, if it exists.
+    pub fn get_index(&self, hash: &EventHash) -> Option<EventIndex> {
+        match self {
+            Events::Memory(events) => events.indices.get(hash).cloned(),
+            Events::Stored(events) => events.store.index_of(hash).map(EventIndex),
+        }
+    }
+
+    /// Number of events.
+    pub fn len(&self) -> usize {
+        match self {
+            Events::Memory(events) => events.events.len(),
+            Events::Stored(events) => events.store.len(),
+        }
+    }
+
+    /// Evicts cached events until at most the capacity of the cache are left, if the events are
+    /// held by an `EventStore`.
+    pub fn shrink_cache(&mut self) {
+        if let Events::Stored(events) = self {
+            events.evict();
+        }
+    }
+
+    /// Number of events held in memory.
+    #[cfg(all(test, feature = "mock"))]
+    pub fn num_cached(&self) -> usize {
+        match self {
+            Events::Memory(events) => events.events.len(),
+            Events::Stored(events) => events.cache.len(),
+        }
+    }
+
+    /// Removes all events, and returns them in insertion order.
+    pub fn drain(&mut self) -> Drain<P> {
+        match mem::take(self) {
+            Events::Memory(events) => Drain::Memory(events.events.into_iter()),
+            Events::Stored(events) => Drain::Stored(events, 0),
+        }
+    }
+}
+
+pub(crate) enum Drain<P: PublicId> {
+    Memory(vec::IntoIter<Event<P>>),
+    Stored(StoredEvents<P>, usize),
+}
+
+impl<P: PublicId> Iterator for Drain<P> {
+    type Item = Event<P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Drain::Memory(events) => events.next(),
+            Drain::Stored(events, index) => {
+                let event = events.take(EventIndex(*index))?;
+                *index += 1;
+                Some(event)
+            }
+        }
+    }
+}
+
+/// Events held in memory.
+#[derive(Debug)]
+pub(crate) struct MemoryEvents<P: PublicId> {
+    events: Vec<Event<P>>,
+    indices: BTreeMap<EventHash, EventIndex>,
+}
+
+impl<P: PublicId> Default for MemoryEvents<P> {
+    fn default() -> Self {
+        Self {
+            events: Vec::new(),
+            indices: BTreeMap::new(),
+        }
+    }
+}
+
+impl<P: PublicId> MemoryEvents<P> {
+    fn push(&mut self, event: Event<P>) -> EventIndex {
+        let index = EventIndex(self.events.len());
+        let _ = self.indices.insert(*event.hash(), index);
+        self.events.push(event);
+        index
+    }
+
+    #[cfg(all(test, feature = "mock"))]
+    fn pop(&mut self) -> Option<Event<P>> {
+        let event = self.events.pop()?;
+        let _ = self.indices.remove(event.hash());
+        Some(event)
+    }
+
+    fn get(&self, index: EventIndex) -> Option<&Event<P>> {
+        self.events.get(index.0)
+    }
+
+    fn get_mut(&mut self, index: EventIndex) -> Option<&mut Event<P>> {
+        self.events.get_mut(index.0)
+    }
+}
+
+/// Events held by an `EventStore`, with the most recently used ones cached in memory.
+///
+/// The consensus code reads the graph through shared references, so the cache is filled from
+/// `&self`. It is an `elsa::FrozenMap`, which boxes the events so the references to them stay
+/// valid as it grows: this can't be done without unsafe code, which this crate denies.
+///
+/// Cached events are borrowed from `&self`, so they can only be evicted from `&mut self` methods,
+/// once no reference to them can be alive. `Parsec` calls `shrink_cache` between the steps of the
+/// consensus and after packing gossip, so the cache only exceeds its capacity by the events read
+/// within a single step.
+pub(crate) struct StoredEvents<P: PublicId> {
+    store: Box<dyn EventStore<P>>,
+    cache: FrozenMap<EventIndex, Box<Event<P>>>,
+    capacity: usize,
+    // Time each cached event was last used at, and the reverse, to evict the least recently used
+    // ones first.
+    last_used: RefCell<(HashMap<EventIndex, u64>, BTreeMap<u64, EventIndex>)>,
+    clock: Cell<u64>,
+    // Cached events changed through `get_mut` which have to be written back to the store when
+    // evicted.
+    dirty: BTreeSet<EventIndex>,
+}
+
+impl<P: PublicId> StoredEvents<P> {
+    /// Creates an empty set of events held by `store`, caching up to `capacity` of them.
+    pub fn new(store: Box<dyn EventStore<P>>, capacity: usize) -> Self {
+        Self {
+            store,
+            cache: FrozenMap::new(),
+            capacity,
+            last_used: RefCell::new((HashMap::new(), BTreeMap::new())),
+            clock: Cell::new(0),
+            dirty: BTreeSet::new(),
+        }
+    }
+
+    fn push(&mut self, event: Event<P>) -> EventIndex {
+        let index = EventIndex(self.store.len());
+        let event = StoredEvent(event);
+        if let Err(error) = self.store.insert(&event) {
+            log_or_panic!("Failed to store event at {:?}: {:?}", index, error);
+        }
+        let _ = self.cache.as_mut().insert(index, Box::new(event.0));
+        self.touch(index);
+        index
+    }
+
+    fn get(&self, index: EventIndex) -> Option<&Event<P>> {
+        if index.0 >= self.store.len() {
+            return None;
+        }
+        self.touch(index);
+        if let Some(event) = self.cache.get(&index) {
+            return Some(event);
+        }
+        let event = self.read(index)?;
+        Some(self.cache.insert(index, Box::new(event)))
+    }
+
+    fn get_mut(&mut self, index: EventIndex) -> Option<&mut Event<P>> {
+        let _ = self.get(index)?;
+        let _ = self.dirty.insert(index);
+        self.cache
+            .as_mut()
+            .get_mut(&index)
+            .map(|event| &mut **event)
+    }
+
+    // Removes the event at `index` from the cache if it's there, or else reads it from the store.
+    fn take(&mut self, index: EventIndex) -> Option<Event<P>> {
+        let (times, indices) = self.last_used.get_mut();
+        if let Some(time) = times.remove(&index) {
+            let _ = indices.remove(&time);
+        }
+        let _ = self.dirty.remove(&index);
+        match self.cache.as_mut().remove(&index) {
+            Some(event) => Some(*event),
+            None => self.read(index),
+        }
+    }
+
+    fn read(&self, index: EventIndex) -> Option<Event<P>> {
+        match self.store.get(index.0) {
+            Ok(Some(event)) => Some(event.0),
+            Ok(None) => {
+                log_or_panic!("Missing stored event at {:?}", index);
+                None
+            }
+            Err(error) => {
+                log_or_panic!("Failed to read stored event at {:?}: {:?}", index, error);
+                None
+            }
+        }
+    }
+
+    fn touch(&self, index: EventIndex) {
+        let now = self.clock.get();
+        self.clock.set(now + 1);
+        let (times, indices) = &mut *self.last_used.borrow_mut();
+        if let Some(time) = times.insert(index, now) {
+            let _ = indices.remove(&time);
+        }
+        let _ = indices.insert(now, index);
+    }
+
+    // Evicts the least recently used events until the cache is within its capacity.
+    fn evict(&mut self) {
+        let cache = self.cache.as_mut();
+        let (times, indices) = self.last_used.get_mut();
+        while cache.len() > self.capacity {
+            let (time, index) = match indices.iter().next() {
+                Some((time, index)) => (*time, *index),
+                None => break,
+            };
+            let _ = indices.remove(&time);
+            let _ = times.remove(&index);
+            let event = match cache.remove(&index) {
+                Some(event) => StoredEvent(*event),
+                None => continue,
+            };
+            if self.dirty.remove(&index) {
+                if let Err(error) = self.store.replace(index.0, &event) {
+                    log_or_panic!("Failed to store event at {:?}: {:?}", index, error);
+                }
+            }
+        }
+    }
+}
+
+impl<P: PublicId> Debug for StoredEvents<P> {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "StoredEvents {{ len: {}, cached: {} }}",
+            self.store.len(),
+            self.cache.len()
+        )
+    }
+}
@@ -9,23 +9,25 @@
 mod ancestors;
 mod event_index;
 mod event_ref;
+mod events;
 
-pub(crate) use self::{ancestors::Ancestors, event_index::EventIndex, event_ref::IndexedEventRef};
+pub(crate) use self::{
+    ancestors::Ancestors,
+    event_index::{EventIndex, EventIndexMap},
+    event_ref::IndexedEventRef,
+};
 
+use self::events::{Events, StoredEvents};
 use super::{event::Event, event_hash::EventHash};
-use crate::id::PublicId;
+use crate::{error::Error, event_store::EventStore, id::PublicId};
 #[cfg(feature = "malice-detection")]
 use fnv::FnvHashSet;
-use std::collections::{
-    btree_map::{BTreeMap, Entry},
-    BTreeSet,
-};
+use std::collections::BTreeSet;
 
 /// The gossip graph.
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Debug)]
 pub(crate) struct Graph<P: PublicId> {
-    events: Vec<Event<P>>,
-    indices: BTreeMap<EventHash, EventIndex>,
+    events: Events<P>,
     /// Indices of `Requesting` events with no associated descendant `Request`, and `Request`s with
     /// no associated descendant `Response`.
     #[cfg(feature = "malice-detection")]
@@ -35,8 +37,7 @@ pub(crate) struct Graph<P: PublicId> {
 impl<P: PublicId> Default for Graph<P> {
     fn default() -> Self {
         Self {
-            events: Vec::new(),
-            indices: BTreeMap::new(),
+            events: Events::default(),
             #[cfg(feature = "malice-detection")]
             awaiting_associated_events: FnvHashSet::default(),
         }
@@ -48,14 +49,47 @@ impl<P: PublicId> Graph<P> {
         Self::default()
     }
 
+    /// Moves the events of this graph to `store`, which must be empty, keeping only the
+    /// `cache_capacity` most recently used ones in memory from then on. The indices of the events
+    /// don't change.
+    pub fn set_store(
+        &mut self,
+        store: Box<dyn EventStore<P>>,
+        cache_capacity: usize,
+    ) -> Result<(), Error> {
+        if !store.is_empty() {
+            return Err(Error::EventStore);
+        }
+
+        let old_events = self.events.drain();
+        self.events = Events::Stored(StoredEvents::new(store, cache_capacity));
+        for event in old_events {
+            let _ = self.events.push(event);
+            self.events.shrink_cache();
+        }
+        Ok(())
+    }
+
+    /// Evicts the least recently used events from memory until at most the cache capacity given
+    /// to `set_store` are left. Does nothing if the events are held in memory.
+    pub fn shrink_cache(&mut self) {
+        self.events.shrink_cache();
+    }
+
+    /// Number of events held in memory.
+    #[cfg(all(test, feature = "mock"))]
+    pub fn num_cached_events(&self) -> usize {
+        self.events.num_cached()
+    }
+
     /// Get index of an event with the given hash.
     pub fn get_index(&self, hash: &EventHash) -> Option<EventIndex> {
-        self.indices.get(hash).cloned()
+        self.events.get_index(hash)
     }
 
     /// Checks whether this graph contains an event with the given hash.
     pub fn contains(&self, hash: &EventHash) -> bool {
-        self.indices.contains_key(hash)
+        self.events.get_index(hash).is_some()
     }
 
     /// Insert new event into the graph.
@@ -69,17 +103,16 @@ impl<P: PublicId> Graph<P> {
     ///
     /// If the event is a `Request` or `Response`, the other_parent is removed from
     /// `awaiting_associated_events`.
-    pub fn insert(&mut self, event: Event<P>) -> IndexedEventRef<P> {
-        let index = match self.indices.entry(*event.hash()) {
-            Entry::Occupied(entry) => *entry.get(),
-            Entry::Vacant(entry) => {
-                let index = EventIndex(self.events.len());
-
+    ///
+    /// Returns `Error::Logic` if the event can't be read back from the graph.
+    pub fn insert(&mut self, event: Event<P>) -> Result<IndexedEventRef<'_, P>, Error> {
+        let index = match self.events.get_index(event.hash()) {
+            Some(index) => index,
+            None => {
                 #[cfg(any(test, feature = "testing"))]
-                assert_ne!(index, EventIndex::PHONY);
+                assert_ne!(EventIndex(self.events.len()), EventIndex::PHONY);
 
-                self.events.push(event);
-                let _ = entry.insert(index);
+                let index = self.events.push(event);
 
                 #[cfg(feature = "malice-detection")]
                 self.update_awaiting(index);
@@ -88,19 +121,23 @@ impl<P: PublicId> Graph<P> {
             }
         };
 
-        IndexedEventRef {
-            index,
-            event: &self.events[index.0],
-        }
+        let event = self.events.get(index).ok_or(Error::Logic)?;
+        Ok(IndexedEventRef { index, event })
     }
 
     /// Gets `Event` with the given `index`, if it exists.
-    pub fn get(&self, index: EventIndex) -> Option<IndexedEventRef<P>> {
+    pub fn get(&self, index: EventIndex) -> Option<IndexedEventRef<'_, P>> {
         self.events
-            .get(index.0)
+            .get(index)
             .map(|event| IndexedEventRef { index, event })
     }
 
+    /// Gets a mutable reference to the `Event` with the given `index`, if it exists. The event must
+    /// not be changed in any way affecting its hash or its ancestry.
+    pub fn get_mut(&mut self, index: EventIndex) -> Option<&mut Event<P>> {
+        self.events.get_mut(index)
+    }
+
     /// Gets `Event` by the given `hash`, if it exists.
     pub fn get_by_hash<'a>(&'a self, hash: &EventHash) -> Option<IndexedEventRef<'a, P>> {
         self.get_index(hash).and_then(|index| self.get(index))
@@ -112,12 +149,12 @@ impl<P: PublicId> Graph<P> {
     }
 
     /// Iterator over all events in this graph. Yields `IndexedEventRef`s.
-    pub fn iter(&self) -> Iter<P> {
+    pub fn iter(&self) -> Iter<'_, P> {
         self.iter_from(0)
     }
 
     /// Iterator over events in this graph starting at the given topological index.
-    pub fn iter_from(&self, start_index: usize) -> Iter<P> {
+    pub fn iter_from(&self, start_index: usize) -> Iter<'_, P> {
         Iter {
             events: &self.events,
             index: start_index,
@@ -130,7 +167,8 @@ impl<P: PublicId> Graph<P> {
     }
 
     /// Returns self-parent of the given event, if any.
-    pub fn self_parent<E: AsRef<Event<P>>>(&self, event: E) -> Option<IndexedEventRef<P>> {
+    #[cfg(feature = "malice-detection")]
+    pub fn self_parent<E: AsRef<Event<P>>>(&self, event: E) -> Option<IndexedEventRef<'_, P>> {
         event
             .as_ref()
             .self_parent()
@@ -138,7 +176,8 @@ impl<P: PublicId> Graph<P> {
     }
 
     /// Returns other-parent of the given event, if any.
-    pub fn other_parent<E: AsRef<Event<P>>>(&self, event: E) -> Option<IndexedEventRef<P>> {
+    #[cfg(feature = "malice-detection")]
+    pub fn other_parent<E: AsRef<Event<P>>>(&self, event: E) -> Option<IndexedEventRef<'_, P>> {
         event
             .as_ref()
             .other_parent()
@@ -146,7 +185,7 @@ impl<P: PublicId> Graph<P> {
     }
 
     /// Returns the first self-parent of the given event, that is a sync_event.
-    pub fn self_sync_parent<E: AsRef<Event<P>>>(&self, event: E) -> Option<IndexedEventRef<P>> {
+    pub fn self_sync_parent<E: AsRef<Event<P>>>(&self, event: E) -> Option<IndexedEventRef<'_, P>> {
         let mut event = event.as_ref();
         while let Some(parent) = event.self_parent().and_then(|index| self.get(index)) {
             if parent.is_sync_event() {
@@ -158,6 +197,7 @@ impl<P: PublicId> Graph<P> {
     }
 
     /// Returns `event` if it's a sync event, or else `self_sync_parent()` of it otherwise.
+    #[cfg(feature = "malice-detection")]
     pub fn self_sync_ancestor<'a>(
         &'a self,
         event: IndexedEventRef<'a, P>,
@@ -228,7 +268,10 @@ impl<P: PublicId> Graph<P> {
         &self,
         index: EventIndex,
     ) -> (Option<EventIndex>, Option<EventIndex>) {
-        let event = &self.events[index.0];
+        let event = match self.events.get(index) {
+            Some(event) => event,
+            None => return (None, None),
+        };
         if event.is_requesting() {
             (Some(index), None)
         } else if event.is_request() {
@@ -256,7 +299,7 @@ impl<P: PublicId> Graph<P> {
     }
 }
 
-#[cfg(any(all(test, feature = "mock"), feature = "testing"))]
+#[cfg(all(test, feature = "mock"))]
 impl<P: PublicId> Graph<P> {
     /// Remove the topologically last event.
     pub fn remove_last(&mut self) -> Option<(EventIndex, Event<P>)> {
@@ -268,7 +311,6 @@ impl<P: PublicId> Graph<P> {
             let _ = awaited.map(|awaited| self.awaiting_associated_events.insert(awaited));
         }
         let event = self.events.pop()?;
-        let _ = self.indices.remove(event.hash());
         Some((index, event))
     }
 }
@@ -301,8 +343,8 @@ impl<P: PublicId> IntoIterator for Graph<P> {
     type IntoIter = IntoIter<P>;
     type Item = <Self::IntoIter as Iterator>::Item;
 
-    fn into_iter(self) -> Self::IntoIter {
-        let mut events = self.events;
+    fn into_iter(mut self) -> Self::IntoIter {
+        let mut events: Vec<_> = self.events.drain().collect();
         events.reverse();
 
         IntoIter { events, index: 0 }
@@ -333,15 +375,12 @@ impl<'a, P: PublicId> IntoIterator for &'a Graph<P> {
     type Item = <Self::IntoIter as Iterator>::Item;
 
     fn into_iter(self) -> Self::IntoIter {
-        Iter {
-            events: &self.events,
-            index: 0,
-        }
+        self.iter()
     }
 }
 
 pub(crate) struct Iter<'a, P: PublicId + 'a> {
-    events: &'a [Event<P>],
+    events: &'a Events<P>,
     index: usize,
 }
 
@@ -349,13 +388,10 @@ impl<'a, P: PublicId> Iterator for Iter<'a, P> {
     type Item = IndexedEventRef<'a, P>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let event = self.events.get(self.index)?;
-        let item = IndexedEventRef {
-            index: EventIndex(self.index),
-            event,
-        };
+        let index = EventIndex(self.index);
+        let event = self.events.get(index)?;
         self.index += 1;
-        Some(item)
+        Some(IndexedEventRef { index, event })
     }
 }
 
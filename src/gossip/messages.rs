@@ -6,30 +6,43 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{gossip::packed_event::PackedEvent, id::PublicId, network_event::NetworkEvent};
+use crate::{
+    gossip::packed_event::PackedEvent,
+    hash::{HashAlgorithmId, HASH_ALGORITHM},
+    id::PublicId,
+    network_event::NetworkEvent,
+};
 
 /// A gossip request message.
-#[serde(bound = "")]
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
 pub struct Request<T: NetworkEvent, P: PublicId> {
+    pub(crate) hash_algorithm: HashAlgorithmId,
     pub(crate) packed_events: Vec<PackedEvent<T, P>>,
 }
 
 impl<T: NetworkEvent, P: PublicId> Request<T, P> {
     pub(crate) fn new(packed_events: Vec<PackedEvent<T, P>>) -> Self {
-        Self { packed_events }
+        Self {
+            hash_algorithm: HASH_ALGORITHM,
+            packed_events,
+        }
     }
 }
 
 /// A gossip response message.
-#[serde(bound = "")]
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
 pub struct Response<T: NetworkEvent, P: PublicId> {
+    pub(crate) hash_algorithm: HashAlgorithmId,
     pub(crate) packed_events: Vec<PackedEvent<T, P>>,
 }
 
 impl<T: NetworkEvent, P: PublicId> Response<T, P> {
     pub(crate) fn new(packed_events: Vec<PackedEvent<T, P>>) -> Self {
-        Self { packed_events }
+        Self {
+            hash_algorithm: HASH_ALGORITHM,
+            packed_events,
+        }
     }
 }
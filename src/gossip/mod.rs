@@ -27,7 +27,7 @@ pub(super) use self::{
     abstract_event::AbstractEventRef,
     event::Event,
     event_context::EventContextRef,
-    graph::{EventIndex, Graph, IndexedEventRef},
+    graph::{EventIndex, EventIndexMap, Graph, IndexedEventRef},
 };
 pub use self::{
     event_hash::EventHash,
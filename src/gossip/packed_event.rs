@@ -18,8 +18,8 @@ use crate::{hash::Hash, serialise, NetworkEvent, PublicId, Vote};
 use std::fmt::{self, Debug, Formatter};
 
 /// Packed event contains only content and signature.
-#[serde(bound = "")]
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct PackedEvent<T: NetworkEvent, P: PublicId> {
     pub(super) content: Content<Vote<T, P>, EventHash, P>,
     pub(super) signature: P::Signature,
@@ -42,6 +42,21 @@ impl<T: NetworkEvent, P: PublicId> PackedEvent<T, P> {
     pub(crate) fn compute_hash(&self) -> EventHash {
         EventHash(Hash::from(serialise(&self.content).as_slice()))
     }
+
+    /// Getter for the event's creator.
+    pub fn creator(&self) -> &P {
+        &self.content.creator
+    }
+
+    /// Getter for the event's self-parent.
+    pub fn self_parent(&self) -> Option<&EventHash> {
+        self.content.self_parent()
+    }
+
+    /// Getter for the event's self-parent.
+    pub fn other_parent(&self) -> Option<&EventHash> {
+        self.content.other_parent()
+    }
 }
 
 #[cfg(all(feature = "mock", any(feature = "testing", test)))]
@@ -96,6 +111,23 @@ impl PackedEvent<Transaction, PeerId> {
         Self::new(content)
     }
 
+    /// Construct a new `Batch` packed event.
+    pub fn new_batch(
+        creator: PeerId,
+        self_parent: EventHash,
+        observations: Vec<Observation<Transaction, PeerId>>,
+    ) -> Self {
+        let votes = observations
+            .into_iter()
+            .map(|observation| Vote::new(&creator, observation))
+            .collect();
+        let content = Content {
+            creator,
+            cause: Cause::Batch { self_parent, votes },
+        };
+        Self::new(content)
+    }
+
     /// Construct a new `Initial` packed event.
     pub fn new_initial(creator: PeerId) -> Self {
         let content = Content {
@@ -110,19 +142,4 @@ impl PackedEvent<Transaction, PeerId> {
         let signature = content.creator.sign_detached(&serialised_content);
         PackedEvent { content, signature }
     }
-
-    /// Getter for the event's creator.
-    pub fn creator(&self) -> &PeerId {
-        &self.content.creator
-    }
-
-    /// Getter for the event's self-parent.
-    pub fn self_parent(&self) -> Option<&EventHash> {
-        self.content.self_parent()
-    }
-
-    /// Getter for the event's self-parent.
-    pub fn other_parent(&self) -> Option<&EventHash> {
-        self.content.other_parent()
-    }
 }
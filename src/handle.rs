@@ -0,0 +1,330 @@
+// Copyright 2019 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    block::Block,
+    error::{Error, Result},
+    gossip::{Request, Response},
+    id::{PublicId, SecretId},
+    key_gen::dkg_status::DkgStatus,
+    network_event::NetworkEvent,
+    observation::Observation,
+    parsec::Parsec,
+};
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    task::{Context, Poll, Waker},
+    thread,
+};
+
+/// Front end to a `Parsec` instance owned by a dedicated worker thread, so that unpacking gossip
+/// and computing the consensus don't block the caller.
+///
+/// Each call sends a command to the worker and returns at once. The commands are run in the order
+/// they were sent, so the usual ordering guarantees of `Parsec` still hold. The result of a
+/// command comes back through the returned `Reply`, and the blocks consensused by the instance
+/// through `try_recv_block`, in the order `Parsec::poll` returns them.
+///
+/// Dropping the handle doesn't wait for the worker: it is detached, and stops by itself once it
+/// has run the commands already sent.
+pub struct ParsecHandle<T: NetworkEvent, P: PublicId> {
+    commands: Sender<Command<T, P>>,
+    blocks: Receiver<Block<T, P>>,
+}
+
+impl<T, P> ParsecHandle<T, P>
+where
+    T: NetworkEvent + Send + 'static,
+    P: PublicId + Send + 'static,
+    P::Signature: Send,
+{
+    /// Starts a worker thread owning the `Parsec` instance returned by `new_parsec`. The instance
+    /// is created on the worker thread itself, as it isn't `Send`.
+    pub fn spawn<S, F>(new_parsec: F) -> Self
+    where
+        S: SecretId<PublicId = P> + 'static,
+        F: FnOnce() -> Parsec<T, S> + Send + 'static,
+    {
+        let (command_tx, command_rx) = mpsc::channel();
+        let (block_tx, block_rx) = mpsc::channel();
+        // The worker stops once `command_tx` is dropped along with the handle, so there is no need
+        // to keep its `JoinHandle`.
+        let _ = thread::spawn(move || run(new_parsec(), &command_rx, &block_tx));
+
+        Self {
+            commands: command_tx,
+            blocks: block_rx,
+        }
+    }
+
+    /// Sends `observation` to `Parsec::vote_for`.
+    pub fn vote_for(&self, observation: Observation<T, P>) -> Reply<()> {
+        self.send(|reply| Command::VoteFor(observation, reply))
+    }
+
+    /// Sends `observations` to `Parsec::vote_for_batch`.
+    pub fn vote_for_batch(&self, observations: Vec<Observation<T, P>>) -> Reply<()> {
+        self.send(|reply| Command::VoteForBatch(observations, reply))
+    }
+
+    /// Sends `payload` to `Parsec::vote_for_encrypted`.
+    pub fn vote_for_encrypted(&self, payload: T) -> Reply<()> {
+        self.send(|reply| Command::VoteForEncrypted(payload, reply))
+    }
+
+    /// Returns the peers `Parsec::gossip_recipients` returns once the commands already sent have
+    /// run.
+    pub fn gossip_recipients(&self) -> Reply<Vec<P>> {
+        self.send(Command::GossipRecipients)
+    }
+
+    /// Sends `peer_id` to `Parsec::create_gossip`.
+    pub fn create_gossip(&self, peer_id: P) -> Reply<Request<T, P>> {
+        self.send(|reply| Command::CreateGossip(peer_id, reply))
+    }
+
+    /// Sends `src` and `req` to `Parsec::handle_request`.
+    pub fn handle_request(&self, src: P, req: Request<T, P>) -> Reply<Response<T, P>> {
+        self.send(|reply| Command::HandleRequest(src, req, reply))
+    }
+
+    /// Sends `src` and `resp` to `Parsec::handle_response`.
+    pub fn handle_response(&self, src: P, resp: Response<T, P>) -> Reply<()> {
+        self.send(|reply| Command::HandleResponse(src, resp, reply))
+    }
+
+    /// Returns what `Parsec::dkg_status` returns once the commands already sent have run.
+    pub fn dkg_status(&self) -> Reply<BTreeMap<usize, DkgStatus<P>>> {
+        self.send(Command::DkgStatus)
+    }
+
+    /// Returns what `Parsec::export_dkg_result` returns once the commands already sent have run.
+    pub fn export_dkg_result(&self) -> Reply<Vec<u8>> {
+        self.send(Command::ExportDkgResult)
+    }
+
+    /// Sends `sealed` to `Parsec::import_dkg_result`.
+    pub fn import_dkg_result(&self, sealed: Vec<u8>) -> Reply<()> {
+        self.send(|reply| Command::ImportDkgResult(sealed, reply))
+    }
+
+    /// Returns the next consensused block, if the worker has output one since the last call.
+    pub fn try_recv_block(&self) -> Option<Block<T, P>> {
+        self.blocks.try_recv().ok()
+    }
+
+    fn send<R, F>(&self, command: F) -> Reply<R>
+    where
+        F: FnOnce(ReplySender<R>) -> Command<T, P>,
+    {
+        let shared = Arc::new(ReplyShared::default());
+        // If the worker is gone, the `ReplySender` is dropped with the command, and the `Reply`
+        // reports it.
+        let _ = self
+            .commands
+            .send(command(ReplySender(Arc::clone(&shared))));
+        Reply(shared)
+    }
+}
+
+/// Result of a command sent to a `ParsecHandle`, available once the worker has run it.
+///
+/// It can be polled with `try_recv`, waited for with `wait`, or awaited as a `Future`. Fails with
+/// `Error::Logic` if the worker stopped without running the command.
+pub struct Reply<R>(Arc<ReplyShared<R>>);
+
+impl<R> Reply<R> {
+    /// Returns the result if it's available, without blocking.
+    pub fn try_recv(&self) -> Option<Result<R>> {
+        self.0.lock().take()
+    }
+
+    /// Blocks until the result is available.
+    pub fn wait(self) -> Result<R> {
+        let mut state = self.0.lock();
+        loop {
+            if let Some(result) = state.take() {
+                return result;
+            }
+            state = match self.0.ready.wait(state) {
+                Ok(state) => state,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+        }
+    }
+}
+
+impl<R> Future for Reply<R> {
+    type Output = Result<R>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let mut state = self.0.lock();
+        if let Some(result) = state.take() {
+            Poll::Ready(result)
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+// One-shot channel carrying the result of a command from the worker to its `Reply`.
+struct ReplyShared<R> {
+    state: Mutex<ReplyState<R>>,
+    ready: Condvar,
+}
+
+impl<R> Default for ReplyShared<R> {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(ReplyState {
+                result: None,
+                closed: false,
+                waker: None,
+            }),
+            ready: Condvar::new(),
+        }
+    }
+}
+
+impl<R> ReplyShared<R> {
+    fn lock(&self) -> MutexGuard<'_, ReplyState<R>> {
+        // Neither side panics while holding the lock, so the state is sound even if poisoned.
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+struct ReplyState<R> {
+    result: Option<Result<R>>,
+    // Whether the `ReplySender` is gone, with or without sending the result.
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+impl<R> ReplyState<R> {
+    // Takes the result if it was sent, or `Error::Logic` if it never will be.
+    fn take(&mut self) -> Option<Result<R>> {
+        self.result.take().or_else(|| {
+            if self.closed {
+                Some(Err(Error::Logic))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+// Worker side of a `Reply`. Dropping it without sending the result fails the `Reply`.
+struct ReplySender<R>(Arc<ReplyShared<R>>);
+
+impl<R> ReplySender<R> {
+    fn send(self, result: Result<R>) {
+        self.0.lock().result = Some(result);
+        // The `Drop` impl wakes the `Reply`.
+    }
+}
+
+impl<R> Drop for ReplySender<R> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut state = self.0.lock();
+            state.closed = true;
+            state.waker.take()
+        };
+        self.0.ready.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+enum Command<T: NetworkEvent, P: PublicId> {
+    VoteFor(Observation<T, P>, ReplySender<()>),
+    VoteForBatch(Vec<Observation<T, P>>, ReplySender<()>),
+    VoteForEncrypted(T, ReplySender<()>),
+    CreateGossip(P, ReplySender<Request<T, P>>),
+    HandleRequest(P, Request<T, P>, ReplySender<Response<T, P>>),
+    HandleResponse(P, Response<T, P>, ReplySender<()>),
+    GossipRecipients(ReplySender<Vec<P>>),
+    DkgStatus(ReplySender<BTreeMap<usize, DkgStatus<P>>>),
+    ExportDkgResult(ReplySender<Vec<u8>>),
+    ImportDkgResult(Vec<u8>, ReplySender<()>),
+}
+
+// Runs the commands until the handle is dropped. The blocks output by a command are forwarded
+// before its result, so they can be received as soon as the result is.
+fn run<T, S>(
+    mut parsec: Parsec<T, S>,
+    commands: &Receiver<Command<T, S::PublicId>>,
+    blocks: &Sender<Block<T, S::PublicId>>,
+) where
+    T: NetworkEvent,
+    S: SecretId,
+{
+    // The caller may have dropped the `Reply`, in which case the result is just discarded.
+    for command in commands {
+        match command {
+            Command::VoteFor(observation, reply) => {
+                let result = parsec.vote_for(observation);
+                forward_blocks(&mut parsec, blocks);
+                reply.send(result);
+            }
+            Command::VoteForBatch(observations, reply) => {
+                let result = parsec.vote_for_batch(observations);
+                forward_blocks(&mut parsec, blocks);
+                reply.send(result);
+            }
+            Command::VoteForEncrypted(payload, reply) => {
+                let result = parsec.vote_for_encrypted(&payload);
+                forward_blocks(&mut parsec, blocks);
+                reply.send(result);
+            }
+            Command::CreateGossip(peer_id, reply) => {
+                let result = parsec.create_gossip(&peer_id);
+                forward_blocks(&mut parsec, blocks);
+                reply.send(result);
+            }
+            Command::HandleRequest(src, req, reply) => {
+                let result = parsec.handle_request(&src, req);
+                forward_blocks(&mut parsec, blocks);
+                reply.send(result);
+            }
+            Command::HandleResponse(src, resp, reply) => {
+                let result = parsec.handle_response(&src, resp);
+                forward_blocks(&mut parsec, blocks);
+                reply.send(result);
+            }
+            Command::GossipRecipients(reply) => {
+                reply.send(Ok(parsec.gossip_recipients().cloned().collect()));
+            }
+            Command::DkgStatus(reply) => reply.send(Ok(parsec.dkg_status())),
+            Command::ExportDkgResult(reply) => reply.send(parsec.export_dkg_result()),
+            Command::ImportDkgResult(sealed, reply) => {
+                reply.send(parsec.import_dkg_result(&sealed))
+            }
+        }
+    }
+}
+
+fn forward_blocks<T: NetworkEvent, S: SecretId>(
+    parsec: &mut Parsec<T, S>,
+    blocks: &Sender<Block<T, S::PublicId>>,
+) {
+    while let Some(block) = parsec.poll() {
+        let _ = blocks.send(block);
+    }
+}
@@ -7,14 +7,72 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use std::fmt::{self, Debug, Formatter};
-use tiny_keccak;
 
 pub const HASH_LEN: usize = 32;
 
+/// Hash function behind `Hash`, and so behind the hashes of events, observations and blocks.
+/// SHA3-256 is used by default, and BLAKE2b with the `blake2b` feature.
+///
+/// Peers using different algorithms can't understand each other, so all the peers of a section
+/// must be built with the same one. As Cargo features are unified, a single crate enabling
+/// `blake2b` switches every user of this crate in the binary, which is why gossip messages carry
+/// the `HashAlgorithmId` and are rejected on a mismatch.
+pub(crate) trait HashAlgorithm {
+    /// Identifier sent along with the gossip messages.
+    const ID: HashAlgorithmId;
+
+    /// Hashes `data`.
+    fn hash(data: &[u8]) -> [u8; HASH_LEN];
+}
+
+/// SHA3-256.
+#[cfg(any(test, not(feature = "blake2b")))]
+pub(crate) struct Sha3_256;
+
+#[cfg(any(test, not(feature = "blake2b")))]
+impl HashAlgorithm for Sha3_256 {
+    const ID: HashAlgorithmId = HashAlgorithmId::Sha3_256;
+
+    fn hash(data: &[u8]) -> [u8; HASH_LEN] {
+        tiny_keccak::sha3_256(data)
+    }
+}
+
+/// BLAKE2b, with a 256-bit output.
+#[cfg(feature = "blake2b")]
+pub(crate) struct Blake2b256;
+
+#[cfg(feature = "blake2b")]
+impl HashAlgorithm for Blake2b256 {
+    const ID: HashAlgorithmId = HashAlgorithmId::Blake2b256;
+
+    fn hash(data: &[u8]) -> [u8; HASH_LEN] {
+        let mut hash = [0; HASH_LEN];
+        hash.copy_from_slice(blake2_rfc::blake2b::blake2b(HASH_LEN, &[], data).as_bytes());
+        hash
+    }
+}
+
+#[cfg(not(feature = "blake2b"))]
+type Algorithm = Sha3_256;
+#[cfg(feature = "blake2b")]
+type Algorithm = Blake2b256;
+
+/// Identifies a `HashAlgorithm`.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub(crate) enum HashAlgorithmId {
+    Sha3_256,
+    Blake2b256,
+}
+
+/// The algorithm this build uses.
+pub(crate) const HASH_ALGORITHM: HashAlgorithmId = Algorithm::ID;
+
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Hash([u8; HASH_LEN]);
 
 impl Hash {
+    #[cfg(any(test, feature = "testing"))]
     pub const ZERO: Self = Hash([0; HASH_LEN]);
 
     #[cfg(any(test, feature = "testing"))]
@@ -30,7 +88,7 @@ impl Hash {
 
 impl<'a> From<&'a [u8]> for Hash {
     fn from(src: &'a [u8]) -> Self {
-        Hash(tiny_keccak::sha3_256(src))
+        Hash(Algorithm::hash(src))
     }
 }
 
@@ -46,7 +104,7 @@ impl Debug for Hash {
 
     #[cfg(not(any(test, feature = "testing", feature = "dump-graphs")))]
     fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
-        write!(formatter, "{}", self::full::FullDisplay(self))
+        write!(formatter, "{}", full::FullDisplay(self))
     }
 }
 
@@ -57,7 +115,7 @@ mod full {
 
     #[cfg(feature = "dump-graphs")]
     impl Hash {
-        pub fn full_display(&self) -> FullDisplay {
+        pub fn full_display(&self) -> FullDisplay<'_> {
             FullDisplay(self)
         }
     }
@@ -73,3 +131,48 @@ mod full {
         }
     }
 }
+
+// Golden vectors pinning each algorithm, so a change of hash function can't go unnoticed.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: [u8; HASH_LEN]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn sha3_256() {
+        assert_eq!(
+            hex(Sha3_256::hash(b"")),
+            "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+        );
+        assert_eq!(
+            hex(Sha3_256::hash(b"abc")),
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+        );
+    }
+
+    #[cfg(feature = "blake2b")]
+    #[test]
+    fn blake2b_256() {
+        assert_eq!(
+            hex(Blake2b256::hash(b"")),
+            "0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8"
+        );
+        assert_eq!(
+            hex(Blake2b256::hash(b"abc")),
+            "bddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319"
+        );
+    }
+
+    #[test]
+    fn hash() {
+        let expected = if cfg!(feature = "blake2b") {
+            "e3bb8c072c998f931d7c150ad5986c281452588978d68417fe59882563c3bbd0"
+        } else {
+            "7aed2312ff1747aaad373526a2de30a87be6b3bb82cd5a2705344b0869776d79"
+        };
+        assert_eq!(hex(Hash::from(&b"parsec"[..]).0), expected);
+    }
+}
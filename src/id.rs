@@ -6,6 +6,7 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use crate::serialise;
 use serde::{de::DeserializeOwned, Serialize};
 use std::{fmt::Debug, hash::Hash};
 
@@ -46,8 +47,8 @@ pub trait SecretId {
 
 /// A basic helper to carry a given [`Signature`](trait.PublicId.html#associatedtype.Signature)
 /// along with the signer's [`PublicId`](trait.PublicId.html).
-#[serde(bound = "")]
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
 pub struct Proof<P: PublicId> {
     pub(super) public_id: P,
     pub(super) signature: P::Signature,
@@ -69,3 +70,30 @@ impl<P: PublicId> Proof<P> {
         self.public_id.verify_signature(&self.signature, data)
     }
 }
+
+/// Proof that a peer holds both the secret key it currently signs its events with and the one it
+/// is rotating to: signatures of both keys over the serialised `(old, new)` pair of public ids.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
+pub struct KeyRotationProof<P: PublicId> {
+    old_signature: P::Signature,
+    new_signature: P::Signature,
+}
+
+impl<P: PublicId> KeyRotationProof<P> {
+    /// Creates the proof for rotating from `old` to `new`.
+    pub fn new<S: SecretId<PublicId = P>>(old: &S, new: &S) -> Self {
+        let data = serialise(&(old.public_id(), new.public_id()));
+        Self {
+            old_signature: old.sign_detached(&data),
+            new_signature: new.sign_detached(&data),
+        }
+    }
+
+    /// Verifies this proof for rotating from `old` to `new`.  Returns `true` if valid.
+    pub fn is_valid(&self, old: &P, new: &P) -> bool {
+        let data = serialise(&(old, new));
+        old.verify_signature(&self.old_signature, &data)
+            && new.verify_signature(&self.new_signature, &data)
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright 2019 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    gossip::EventIndex,
+    id::{PublicId, SecretId},
+    peer_list::{PeerIndex, PeerList},
+};
+use std::collections::btree_map::{BTreeMap, Entry};
+
+/// Policy for automatically voting to remove voters which have gone quiet: if no new event
+/// created by a voter has reached us within `max_sync_events` of our own sync events, we vote
+/// `Observation::Remove` for it. See `Parsec::set_inactivity_policy`.
+pub struct InactivityPolicy<P: PublicId> {
+    max_sync_events: usize,
+    grace_period: usize,
+    hook: Option<InactivityHook<P>>,
+}
+
+type InactivityHook<P> = Box<dyn FnMut(&P, usize) -> bool>;
+
+impl<P: PublicId> InactivityPolicy<P> {
+    /// Creates a policy voting to remove a voter once none of its events has reached us within
+    /// `max_sync_events` of our own sync events.
+    pub fn new(max_sync_events: usize) -> Self {
+        Self {
+            max_sync_events,
+            grace_period: 0,
+            hook: None,
+        }
+    }
+
+    /// Sets the number of our own sync events, counted from when we start tracking a voter, during
+    /// which it is never deemed unresponsive. Defaults to zero.
+    pub fn with_grace_period(mut self, grace_period: usize) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Sets a hook called with the id of an unresponsive voter and the number of our sync events
+    /// it has been silent for, just before we vote to remove it. Returning `false` vetoes the
+    /// vote, in which case the hook is called again on each of our subsequent sync events while
+    /// the voter stays silent.
+    pub fn with_hook<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(&P, usize) -> bool + 'static,
+    {
+        self.hook = Some(Box::new(hook));
+        self
+    }
+}
+
+// Applies an `InactivityPolicy`, tracking the activity of the voters across our sync events.
+pub(crate) struct InactivityTracker<P: PublicId> {
+    policy: InactivityPolicy<P>,
+    // Overrides the `max_sync_events` of the policy, as set by the consensus parameters.
+    max_sync_events: Option<usize>,
+    activities: BTreeMap<PeerIndex, Activity>,
+}
+
+impl<P: PublicId> InactivityTracker<P> {
+    pub fn new(policy: InactivityPolicy<P>, max_sync_events: Option<usize>) -> Self {
+        Self {
+            policy,
+            max_sync_events,
+            activities: BTreeMap::new(),
+        }
+    }
+
+    pub fn set_max_sync_events(&mut self, max_sync_events: Option<usize>) {
+        self.max_sync_events = max_sync_events;
+    }
+
+    // To be called after each of our sync events. Returns the voters we should now vote to remove.
+    // They are returned again on later calls until passed to `mark_reported`.
+    pub fn record_sync_event<S: SecretId<PublicId = P>>(
+        &mut self,
+        peer_list: &PeerList<S>,
+    ) -> Vec<PeerIndex> {
+        let InactivityTracker {
+            ref mut policy,
+            max_sync_events,
+            ref mut activities,
+        } = *self;
+        let max_sync_events = max_sync_events.unwrap_or(policy.max_sync_events);
+
+        activities.retain(|peer_index, _| peer_list.peer_state(*peer_index).can_vote());
+
+        let mut inactive = vec![];
+        for (peer_index, peer) in peer_list.voters() {
+            if peer_index == PeerIndex::OUR {
+                continue;
+            }
+
+            // The sync event we start tracking a voter at counts towards its age only: its latest
+            // event is known as of then.
+            let last_event = peer_list.last_event(peer_index);
+            let activity = match activities.entry(peer_index) {
+                Entry::Vacant(entry) => {
+                    let _ = entry.insert(Activity {
+                        age: 1,
+                        ..Activity::new(last_event)
+                    });
+                    continue;
+                }
+                Entry::Occupied(entry) => entry.into_mut(),
+            };
+            activity.age += 1;
+
+            if activity.last_event != last_event {
+                *activity = Activity {
+                    age: activity.age,
+                    ..Activity::new(last_event)
+                };
+                continue;
+            }
+
+            activity.silent += 1;
+            if activity.reported
+                || activity.age <= policy.grace_period
+                || activity.silent < max_sync_events
+            {
+                continue;
+            }
+
+            let approved = policy
+                .hook
+                .as_mut()
+                .is_none_or(|hook| hook(peer.id(), activity.silent));
+            if approved {
+                inactive.push(peer_index);
+            }
+        }
+
+        inactive
+    }
+
+    // To be called once we voted to remove the given voter, so it isn't reported again.
+    pub fn mark_reported(&mut self, peer_index: PeerIndex) {
+        if let Some(activity) = self.activities.get_mut(&peer_index) {
+            activity.reported = true;
+        }
+    }
+}
+
+struct Activity {
+    // Latest event of the voter known to us.
+    last_event: Option<EventIndex>,
+    // Number of our sync events since we started tracking the voter.
+    age: usize,
+    // Number of our sync events since `last_event` changed.
+    silent: usize,
+    // Whether we already voted for the removal of the voter.
+    reported: bool,
+}
+
+impl Activity {
+    fn new(last_event: Option<EventIndex>) -> Self {
+        Self {
+            last_event,
+            age: 0,
+            silent: 0,
+            reported: false,
+        }
+    }
+}
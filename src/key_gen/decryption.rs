@@ -0,0 +1,92 @@
+// Copyright 2019 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::rng_adapter::RngAdapter;
+use crate::{observation::ObservationHash, parsec::KeyGenId};
+use rand;
+use std::{collections::BTreeMap, fmt};
+use threshold_crypto::{Ciphertext, DecryptionShare, PublicKey, PublicKeySet};
+
+/// Message carrying one participant's decryption share for a consensused
+/// `Observation::EncryptedPayload`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct DecryptionShareMessage {
+    // Hash of the `EncryptedPayload` observation this share decrypts.
+    pub(crate) payload_hash: ObservationHash,
+    // Serialised `threshold_crypto::DecryptionShare`.
+    pub(crate) share: Vec<u8>,
+}
+
+impl fmt::Debug for DecryptionShareMessage {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "DecryptionShare({:?})", self.payload_hash)
+    }
+}
+
+/// Encrypts `plaintext` to `public_key` using the provided RNG.
+pub(crate) fn encrypt(
+    public_key: &PublicKey,
+    plaintext: &[u8],
+    rng: &mut dyn rand::Rng,
+) -> Ciphertext {
+    let mut rng = RngAdapter(&mut *rng);
+    public_key.encrypt_with_rng(&mut rng, plaintext)
+}
+
+/// An `EncryptedPayload` which has been consensused, along with the id of the DKG it's encrypted
+/// to and the valid decryption shares received for it so far.
+pub(crate) struct PendingDecryption {
+    key_gen: KeyGenId,
+    ciphertext: Ciphertext,
+    shares: BTreeMap<usize, DecryptionShare>,
+}
+
+impl PendingDecryption {
+    pub fn new(key_gen: KeyGenId, ciphertext: Ciphertext) -> Self {
+        Self {
+            key_gen,
+            ciphertext,
+            shares: BTreeMap::new(),
+        }
+    }
+
+    /// Id of the DKG whose key shares decrypt this payload.
+    pub fn key_gen(&self) -> KeyGenId {
+        self.key_gen
+    }
+
+    /// Adds the share of the participant at `index`. Returns `false` if the share is invalid.
+    pub fn add_share(
+        &mut self,
+        public_key_set: &PublicKeySet,
+        index: usize,
+        share: DecryptionShare,
+    ) -> bool {
+        if !public_key_set
+            .public_key_share(index)
+            .verify_decryption_share(&share, &self.ciphertext)
+        {
+            return false;
+        }
+
+        let _ = self.shares.insert(index, share);
+        true
+    }
+
+    /// Returns the plaintext once more than `threshold` valid shares have been added.
+    pub fn decrypt(&self, public_key_set: &PublicKeySet) -> Option<Vec<u8>> {
+        if self.shares.len() <= public_key_set.threshold() {
+            return None;
+        }
+
+        public_key_set
+            .decrypt(&self.shares, &self.ciphertext)
+            .map_err(|error| warn!("Failed to combine decryption shares: {:?}", error))
+            .ok()
+    }
+}
@@ -6,12 +6,18 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use crate::{
+    error::{Error, Result},
+    id::SecretId,
+};
+use maidsafe_utilities::serialisation;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     cmp::Ordering,
+    collections::BTreeSet,
     fmt::{self, Debug, Formatter},
 };
-use threshold_crypto::{PublicKeySet, SecretKeyShare};
+use threshold_crypto::{serde_impl::SerdeSecret, PublicKeySet, SecretKeyShare};
 
 #[derive(Clone)]
 /// DKG result
@@ -31,6 +37,54 @@ impl DkgResult {
             secret_key_share,
         }
     }
+
+    /// Serialises this result, the id of the DKG which produced it and its `participants`,
+    /// including the secret key share, encrypted to `our_id` so it can be persisted and restored
+    /// with `unseal` after a restart.
+    pub fn seal<S: SecretId>(
+        &self,
+        key_gen: usize,
+        participants: &BTreeSet<S::PublicId>,
+        our_id: &S,
+    ) -> Result<Vec<u8>> {
+        let secret_key_share = self.secret_key_share.clone().map(SerdeSecret);
+        let plaintext = serialisation::serialise(&(
+            key_gen,
+            participants,
+            &self.public_key_set,
+            secret_key_share,
+        ))
+        .map_err(|_| Error::SealingFailure)?;
+        our_id
+            .encrypt(our_id.public_id(), plaintext)
+            .ok_or(Error::SealingFailure)
+    }
+
+    /// Decrypts and deserialises a result, its DKG id and its participants previously sealed by
+    /// `our_id`.
+    pub fn unseal<S: SecretId>(
+        sealed: &[u8],
+        our_id: &S,
+    ) -> Result<(usize, BTreeSet<S::PublicId>, Self)> {
+        let plaintext = our_id
+            .decrypt(our_id.public_id(), sealed)
+            .ok_or(Error::SealingFailure)?;
+        let (key_gen, participants, public_key_set, secret_key_share): (
+            usize,
+            BTreeSet<S::PublicId>,
+            PublicKeySet,
+            Option<SerdeSecret<SecretKeyShare>>,
+        ) = serialisation::deserialise(&plaintext).map_err(|_| Error::SealingFailure)?;
+
+        Ok((
+            key_gen,
+            participants,
+            Self::new(
+                public_key_set,
+                secret_key_share.map(SerdeSecret::into_inner),
+            ),
+        ))
+    }
 }
 
 impl Debug for DkgResult {
@@ -66,7 +120,7 @@ impl DkgResultWrapper {
 
 impl PartialEq for DkgResultWrapper {
     fn eq(&self, rhs: &Self) -> bool {
-        self.comparison_value().eq(&rhs.comparison_value())
+        self.comparison_value().eq(rhs.comparison_value())
     }
 }
 
@@ -74,24 +128,24 @@ impl Eq for DkgResultWrapper {}
 
 impl PartialOrd for DkgResultWrapper {
     fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> {
-        self.comparison_value().partial_cmp(&rhs.comparison_value())
+        Some(self.cmp(rhs))
     }
 }
 
 impl Ord for DkgResultWrapper {
     fn cmp(&self, rhs: &Self) -> Ordering {
-        self.comparison_value().cmp(&rhs.comparison_value())
+        self.comparison_value().cmp(rhs.comparison_value())
     }
 }
 
 impl Serialize for DkgResultWrapper {
-    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+    fn serialize<S: Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
         self.0.public_key_set.serialize(s)
     }
 }
 
 impl<'a> Deserialize<'a> for DkgResultWrapper {
-    fn deserialize<D: Deserializer<'a>>(deserializer: D) -> Result<Self, D::Error> {
+    fn deserialize<D: Deserializer<'a>>(deserializer: D) -> std::result::Result<Self, D::Error> {
         PublicKeySet::deserialize(deserializer).map(|public_key_set| {
             DkgResultWrapper(DkgResult {
                 public_key_set,
@@ -0,0 +1,28 @@
+// Copyright 2019 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::id::PublicId;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Progress of a distributed key generation which has started but not yet produced its
+/// `DkgResult`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DkgStatus<P: PublicId> {
+    /// DKG Participants.
+    pub participants: BTreeSet<P>,
+    /// Maximum number of faulty participants the generated key tolerates.
+    pub threshold: usize,
+    /// Number of `Part`s acked by enough participants to be used for the key. The key is
+    /// generated once this exceeds `threshold`.
+    pub completed_parts: usize,
+    /// For each participant whose `Part` has been handled, the participants which haven't acked it
+    /// yet.
+    pub missing_acks: BTreeMap<P, BTreeSet<P>>,
+    /// Whether we are a participant, and so will hold a secret key share once complete.
+    pub has_share: bool,
+}
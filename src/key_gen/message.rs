@@ -11,8 +11,8 @@ use crate::parsec::KeyGenId;
 use std::fmt;
 
 /// Messages used for running BLS DKG.
-#[serde(bound = "")]
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub enum DkgMessage {
     Part { key_gen_id: KeyGenId, part: Part },
     Ack { key_gen_id: KeyGenId, ack: Ack },
@@ -95,7 +95,9 @@
 //! method above. The sum of the secret keys we received from each node is then used as our secret
 //! key. No single node knows the secret master key.
 
+pub mod decryption;
 pub mod dkg_result;
+pub mod dkg_status;
 pub mod message;
 pub mod parsec_rng;
 mod rng_adapter;
@@ -103,10 +105,10 @@ mod rng_adapter;
 #[cfg(test)]
 mod tests;
 
+use self::dkg_status::DkgStatus;
 use crate::{DkgResult, SecretId};
 use failure::Fail;
 use maidsafe_utilities::serialisation;
-use rand;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{self, Debug, Formatter};
@@ -270,7 +272,7 @@ impl<S: SecretId> KeyGen<S> {
         sec_key: &S,
         pub_keys: BTreeSet<S::PublicId>,
         threshold: usize,
-        rng: &mut rand::Rng,
+        rng: &mut dyn rand::Rng,
     ) -> Result<(KeyGen<S>, Option<Part>), Error> {
         let our_id = sec_key.public_id().clone();
         let our_idx = pub_keys
@@ -376,6 +378,33 @@ impl<S: SecretId> KeyGen<S> {
             .count()
     }
 
+    /// Returns the progress of the key generation so far.
+    pub fn status(&self) -> DkgStatus<S::PublicId> {
+        let missing_acks = self
+            .parts
+            .iter()
+            .filter_map(|(proposer_idx, part)| {
+                let proposer_id = self.pub_keys.iter().nth(*proposer_idx as usize)?;
+                let missing = self
+                    .pub_keys
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, _)| !part.acks.contains(&(*idx as u64)))
+                    .map(|(_, id)| id.clone())
+                    .collect();
+                Some((proposer_id.clone(), missing))
+            })
+            .collect();
+
+        DkgStatus {
+            participants: self.pub_keys.clone(),
+            threshold: self.threshold,
+            completed_parts: self.count_complete(),
+            missing_acks,
+            has_share: self.our_idx.is_some(),
+        }
+    }
+
     /// Returns `true` if enough parts are complete to safely generate the new key.
     pub fn is_ready(&self) -> bool {
         self.count_complete() > self.threshold
@@ -23,9 +23,13 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 
+use super::decryption::{self, PendingDecryption};
+use super::rng_adapter::RngAdapter;
 use super::{dkg_threshold, KeyGen, PartOutcome};
 use crate::dev_utils::{Environment, RngChoice};
 use crate::mock::PeerId;
+use crate::DkgResult;
+use threshold_crypto::SecretKeySet;
 
 // Alter the seed here to reproduce failures
 static SEED: RngChoice = RngChoice::SeededRandom;
@@ -146,3 +150,90 @@ fn test_key_gen_8() {
 fn test_key_gen_15() {
     test_key_gen(15);
 }
+
+#[test]
+fn test_threshold_decryption() {
+    let mut env = Environment::new(SEED);
+    let threshold = 2;
+    let sk_set = SecretKeySet::random(threshold, &mut RngAdapter(&mut env.rng));
+    let pk_set = sk_set.public_keys();
+
+    let msg = b"Hidden until consensused";
+    let ciphertext = decryption::encrypt(&pk_set.public_key(), msg, &mut env.rng);
+    let mut pending = PendingDecryption::new(0, ciphertext.clone());
+
+    // A share attributed to the wrong participant is rejected.
+    let share = unwrap!(sk_set.secret_key_share(1).decrypt_share(&ciphertext));
+    assert!(!pending.add_share(&pk_set, 0, share));
+
+    // `threshold + 1` valid shares are needed to reveal the plaintext.
+    for idx in 0..=threshold {
+        assert!(pending.decrypt(&pk_set).is_none());
+        let share = unwrap!(sk_set.secret_key_share(idx).decrypt_share(&ciphertext));
+        assert!(pending.add_share(&pk_set, idx, share));
+    }
+    assert_eq!(pending.decrypt(&pk_set), Some(msg.to_vec()));
+}
+
+#[test]
+fn test_key_gen_status() {
+    let mut env = Environment::new(SEED);
+    let node_num = 4;
+    let threshold = dkg_threshold(node_num);
+    let peer_ids: Vec<PeerId> = (0..node_num)
+        .map(|idx| unwrap!(PeerId::from_index(idx)))
+        .collect();
+    let pub_keys: BTreeSet<PeerId> = peer_ids.iter().cloned().collect();
+
+    let (mut key_gen, part) = unwrap!(KeyGen::new(
+        &peer_ids[0],
+        pub_keys.clone(),
+        threshold,
+        &mut env.rng
+    ));
+    let status = key_gen.status();
+    assert_eq!(status.participants, pub_keys);
+    assert_eq!(status.threshold, threshold);
+    assert_eq!(status.completed_parts, 0);
+    assert!(status.missing_acks.is_empty());
+    assert!(status.has_share);
+
+    let ack = match unwrap!(key_gen.handle_part(&peer_ids[0], &peer_ids[0], unwrap!(part))) {
+        PartOutcome::Valid(Some(ack)) => ack,
+        _ => panic!("expected an ack for our own part"),
+    };
+    let _ = unwrap!(key_gen.handle_ack(&peer_ids[0], &peer_ids[0], ack));
+
+    let status = key_gen.status();
+    let expected_missing: BTreeSet<_> = peer_ids[1..].iter().cloned().collect();
+    assert_eq!(status.missing_acks.len(), 1);
+    assert_eq!(
+        status.missing_acks.get(&peer_ids[0]),
+        Some(&expected_missing)
+    );
+    assert_eq!(status.completed_parts, 0);
+
+    // An observer won't hold a share.
+    let observer = unwrap!(PeerId::from_index(node_num));
+    let (key_gen, part) = unwrap!(KeyGen::new(&observer, pub_keys, threshold, &mut env.rng));
+    assert!(part.is_none());
+    assert!(!key_gen.status().has_share);
+}
+
+#[test]
+fn test_seal_and_unseal_dkg_result() {
+    let mut env = Environment::new(SEED);
+    let sk_set = SecretKeySet::random(1, &mut RngAdapter(&mut env.rng));
+    let participants: BTreeSet<PeerId> =
+        (0..3).map(|idx| unwrap!(PeerId::from_index(idx))).collect();
+    let our_id = unwrap!(participants.iter().next()).clone();
+    let dkg_result = DkgResult::new(sk_set.public_keys(), Some(sk_set.secret_key_share(0)));
+
+    let sealed = unwrap!(dkg_result.seal(3, &participants, &our_id));
+    let (key_gen, unsealed_participants, unsealed) = unwrap!(DkgResult::unseal(&sealed, &our_id));
+
+    assert_eq!(key_gen, 3);
+    assert_eq!(unsealed_participants, participants);
+    assert_eq!(unsealed.public_key_set, dkg_result.public_key_set);
+    assert_eq!(unsealed.secret_key_share, dkg_result.secret_key_share);
+}
@@ -115,20 +115,19 @@
     test(attr(forbid(warnings)))
 )]
 #![forbid(
-    exceeding_bitshifts,
+    arithmetic_overflow,
     mutable_transmutes,
     no_mangle_const_items,
-    unknown_crate_types,
-    warnings
+    unknown_crate_types
 )]
 #![deny(
+    warnings,
     bad_style,
     deprecated,
     improper_ctypes,
     missing_docs,
     non_shorthand_field_patterns,
     overflowing_literals,
-    plugin_as_library,
     stable_features,
     unconditional_recursion,
     unknown_lints,
@@ -150,11 +149,14 @@
     unused_results
 )]
 #![allow(
-    box_pointers,
     missing_copy_implementations,
     missing_debug_implementations,
     variant_size_differences,
-    clippy::new_ret_no_self
+    clippy::doc_lazy_continuation,
+    clippy::doc_overindented_list_items,
+    clippy::match_like_matches_macro,
+    clippy::new_ret_no_self,
+    clippy::result_large_err
 )]
 
 #[macro_use]
@@ -177,13 +179,22 @@ extern crate unwrap;
 #[macro_use]
 pub mod dev_utils;
 
+#[cfg(feature = "async")]
+mod async_parsec;
 mod block;
 mod dump_graph;
 mod error;
+mod event_log;
+mod event_store;
 mod gossip;
+mod handle;
 mod hash;
 mod id;
+mod inactivity;
+// The `Fail` derive defines its impls inside constants.
+#[allow(non_local_definitions)]
 mod key_gen;
+mod limits;
 mod meta_voting;
 mod network_event;
 mod observation;
@@ -204,16 +215,23 @@ mod functional_tests;
 #[cfg(any(test, feature = "mock"))]
 pub mod mock;
 
+#[cfg(feature = "async")]
+pub use crate::async_parsec::{AsyncParsec, Blocks};
 #[cfg(feature = "dump-graphs")]
 pub use crate::dump_graph::{DumpGraphMode, DIR, DUMP_MODE};
 pub use crate::{
-    block::Block,
+    block::{Block, BlockHash},
     error::{Error, Result},
+    event_log::EventLog,
+    event_store::{EventStore, FileEventStore, StoredAncestors, StoredEvent},
     gossip::{EventHash, PackedEvent, Request, Response},
-    id::{Proof, PublicId, SecretId},
-    key_gen::dkg_result::*,
+    handle::{ParsecHandle, Reply},
+    id::{KeyRotationProof, Proof, PublicId, SecretId},
+    inactivity::InactivityPolicy,
+    key_gen::{dkg_result::*, dkg_status::DkgStatus},
+    limits::Limits,
     network_event::NetworkEvent,
-    observation::{ConsensusMode, Malice, Observation},
+    observation::{ConsensusMode, Malice, Observation, Parameters},
     parsec::Parsec,
     vote::Vote,
 };
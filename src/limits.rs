@@ -0,0 +1,33 @@
+// Copyright 2019 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+/// Limits on the memory a `Parsec` instance is allowed to use under heavy load. See
+/// `Parsec::set_limits`.
+///
+/// Reaching a limit throttles the growth of the instance rather than stalling it. Events are never
+/// dropped from the graph, as every peer must hold the same events to agree on the consensus, and
+/// we keep pulling them by sending requests at our own pace. What we refuse is new votes of our own
+/// and requests pushing events from peers that flood the section, until consensus catches up.
+///
+/// Unlike `Parameters`, these are local to the owning peer, but the section's members should all
+/// use the same ones.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Limits {
+    /// Maximum number of votes of a single peer for observations which aren't consensused yet.
+    /// Voting fails with `Error::Backpressure` once we have that many votes not retracted either,
+    /// and requests from a peer with more than that many in our graph are refused.
+    pub max_unconsensused_votes: Option<usize>,
+    /// Maximum number of sync events waiting for the owning peer to become a voter. Past that, the
+    /// oldest ones are dropped: only the latest are needed to take part in the consensus.
+    pub max_pending_events: Option<usize>,
+    /// Maximum number of events in the part of the gossip graph the consensus still has to go
+    /// through, i.e. from the first event carrying an unconsensused vote onwards. Voting fails with
+    /// `Error::Backpressure` once there are that many. As consensus moves this part forward, the
+    /// older events don't count.
+    pub max_graph_size: Option<usize>,
+}
@@ -7,19 +7,14 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 /// A simple enum to hold a set of bools.
-#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum BoolSet {
+    #[default]
     Empty,
     Single(bool),
     Both,
 }
 
-impl Default for BoolSet {
-    fn default() -> BoolSet {
-        BoolSet::Empty
-    }
-}
-
 impl BoolSet {
     pub fn is_empty(self) -> bool {
         self == BoolSet::Empty
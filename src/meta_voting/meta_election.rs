@@ -7,17 +7,20 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use super::{
-    meta_event::{MetaEvent, MetaEventBuilder},
+    meta_event::{MetaEvent, MetaEventBuilder, ObserverInputs},
     meta_vote::MetaVote,
 };
 use crate::{
-    gossip::{EventIndex, Graph},
+    gossip::{EventIndex, EventIndexMap, Graph},
     id::PublicId,
     observation::ObservationKey,
     peer_list::{PeerIndex, PeerIndexMap, PeerIndexSet, PeerListChange},
 };
 use fnv::{FnvHashMap, FnvHashSet};
-use std::{cmp, collections::BTreeSet, usize};
+use std::{
+    cmp,
+    collections::{BTreeMap, BTreeSet},
+};
 
 lazy_static! {
     static ref EMPTY_BTREESET_EVENT_INDEX: BTreeSet<EventIndex> = BTreeSet::new();
@@ -25,16 +28,25 @@ lazy_static! {
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub(crate) struct UnconsensusedEvents {
-    // Set of all events that carry a payload that hasn't yet been consensused.
+    // Set of all events that carry a payload that hasn't yet been consensused. An event carrying a
+    // batch of votes stays in here until all of its payloads are consensused.
     pub(crate) ordered_indices: BTreeSet<EventIndex>,
     // Same events grouped by ObservationKey for lookup performance,
     pub(crate) indices_by_key: FnvHashMap<ObservationKey, BTreeSet<EventIndex>>,
 }
 
+// The voters and first interesting events of an ended meta-election, which the observers computed
+// during it depend on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct EndedElection {
+    voters: PeerIndexSet,
+    first_interesting_events: Vec<(PeerIndex, EventIndex)>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct MetaElection {
     // Set of meta-events corresponding to the events in the gossip graph.
-    pub(crate) meta_events: FnvHashMap<EventIndex, MetaEvent>,
+    pub(crate) meta_events: EventIndexMap<MetaEvent>,
     // Set of peers participating in this meta-election, i.e. all voters at the time the current
     // meta-election was started.
     pub(crate) voters: PeerIndexSet,
@@ -50,18 +62,24 @@ pub(crate) struct MetaElection {
     pub(crate) continue_consensus_start_index: usize,
     // Topological index of the first unconsensused payload-carrying event.
     pub(crate) new_consensus_start_index: usize,
+    // Index of the current meta-election, counting from the first one of this instance.
+    pub(crate) election: usize,
+    // The ended meta-elections some meta-events computed their observer during, by index.
+    pub(crate) ended_elections: BTreeMap<usize, EndedElection>,
 }
 
 impl MetaElection {
     pub fn new(voters: PeerIndexSet) -> Self {
         MetaElection {
-            meta_events: FnvHashMap::default(),
+            meta_events: EventIndexMap::new(),
             voters,
             interesting_events: PeerIndexMap::default(),
             unconsensused_events: UnconsensusedEvents::default(),
             consensus_history: Vec::new(),
             continue_consensus_start_index: 0,
             new_consensus_start_index: 0,
+            election: 0,
+            ended_elections: BTreeMap::new(),
         }
     }
 
@@ -80,11 +98,11 @@ impl MetaElection {
     }
 
     pub fn remove_meta_event(&mut self, event_index: EventIndex) -> Option<MetaEvent> {
-        self.meta_events.remove(&event_index)
+        self.meta_events.remove(event_index)
     }
 
     pub fn meta_event(&self, event_index: EventIndex) -> Option<&MetaEvent> {
-        self.meta_events.get(&event_index)
+        self.meta_events.get(event_index)
     }
 
     /// The event meta votes if available
@@ -93,7 +111,7 @@ impl MetaElection {
         event_index: EventIndex,
     ) -> Option<&PeerIndexMap<Vec<MetaVote>>> {
         self.meta_events
-            .get(&event_index)
+            .get(event_index)
             .map(|meta_event| &meta_event.meta_votes)
             .filter(|meta_votes| !meta_votes.is_empty())
     }
@@ -107,17 +125,61 @@ impl MetaElection {
         &self.consensus_history
     }
 
-    pub fn interesting_events(&self) -> impl Iterator<Item = (PeerIndex, &[EventIndex])> {
+    /// The first interesting event of each peer.
+    pub fn first_interesting_events(&self) -> impl Iterator<Item = (PeerIndex, EventIndex)> + '_ {
         self.interesting_events
             .iter()
-            .map(|(peer_index, (event_indices, _))| (peer_index, &event_indices[..]))
+            .filter_map(|(peer_index, (event_indices, _))| {
+                event_indices
+                    .first()
+                    .map(|event_index| (peer_index, *event_index))
+            })
+    }
+
+    /// The inputs of an observer status computed now.
+    pub fn observer_inputs(&self, descends_from_observer: bool) -> ObserverInputs {
+        ObserverInputs {
+            election: self.election,
+            descends_from_observer,
+        }
+    }
+
+    /// Whether the observer status of the event at `event_index`, computed from `inputs`, would be
+    /// computed from the same inputs now, given whether the event descends from an observer now.
+    ///
+    /// Events are processed in topological order during a meta-election, so the first interesting
+    /// events when the event was processed are those of its meta-election with a lower index.
+    pub fn has_same_observer_inputs(
+        &self,
+        event_index: EventIndex,
+        inputs: ObserverInputs,
+        descends_from_observer: bool,
+    ) -> bool {
+        if inputs.descends_from_observer != descends_from_observer {
+            return false;
+        }
+        if inputs.election == self.election {
+            return true;
+        }
+
+        let ended_election = match self.ended_elections.get(&inputs.election) {
+            Some(ended_election) => ended_election,
+            None => return false,
+        };
+        ended_election.voters == self.voters
+            && ended_election
+                .first_interesting_events
+                .iter()
+                .cloned()
+                .filter(|(_, first_index)| *first_index < event_index)
+                .eq(self.first_interesting_events())
     }
 
     pub fn interesting_content_by(&self, creator: PeerIndex) -> Option<&Vec<ObservationKey>> {
         self.interesting_events
             .get(creator)
             .and_then(|(indices, _)| indices.first())
-            .and_then(|event_index| self.meta_events.get(event_index))
+            .and_then(|event_index| self.meta_events.get(*event_index))
             .map(|meta_event| &meta_event.interesting_content)
     }
 
@@ -128,13 +190,41 @@ impl MetaElection {
     ) -> bool {
         self.interesting_events
             .get(creator)
-            .map_or(false, |(_, contents)| contents.contains(payload_key))
+            .is_some_and(|(_, contents)| contents.contains(payload_key))
     }
 
     pub fn continue_consensus_start_index(&self) -> usize {
         self.continue_consensus_start_index
     }
 
+    pub fn new_consensus_start_index(&self) -> usize {
+        self.new_consensus_start_index
+    }
+
+    /// Regroups the unconsensused events by their new keys after the consensus mode changed, and
+    /// restarts the current election, as the interesting content of the events changed too.
+    pub fn rekey_unconsensused_events<F>(&mut self, key_of: F)
+    where
+        F: Fn(EventIndex) -> Vec<ObservationKey>,
+    {
+        let mut indices_by_key = FnvHashMap::default();
+        for event_index in &self.unconsensused_events.ordered_indices {
+            for key in key_of(*event_index) {
+                let _ = indices_by_key
+                    .entry(key)
+                    .or_insert_with(BTreeSet::new)
+                    .insert(*event_index);
+            }
+        }
+        self.unconsensused_events.indices_by_key = indices_by_key;
+
+        self.continue_consensus_start_index = self.new_consensus_start_index;
+        self.meta_events.clear();
+        self.interesting_events.clear();
+        self.ended_elections.clear();
+        self.election += 1;
+    }
+
     /// Starts new election.
     pub fn new_election<P: PublicId>(
         &mut self,
@@ -144,6 +234,7 @@ impl MetaElection {
     ) {
         let peer_list_changed = !peer_list_changes.is_empty();
 
+        self.end_election();
         self.update_voters(peer_list_changes);
         self.update_unconsensused_events(&decided_keys);
         self.update_new_consensus_start_index(graph.len());
@@ -155,7 +246,7 @@ impl MetaElection {
     }
 
     #[cfg(feature = "dump-graphs")]
-    pub fn meta_events(&self) -> &FnvHashMap<EventIndex, MetaEvent> {
+    pub fn meta_events(&self) -> &EventIndexMap<MetaEvent> {
         &self.meta_events
     }
 
@@ -168,7 +259,7 @@ impl MetaElection {
             .unconsensused_events
             .indices_by_key
             .entry(key)
-            .or_insert_with(BTreeSet::new)
+            .or_default()
             .insert(event_index);
     }
 
@@ -188,6 +279,14 @@ impl MetaElection {
         indices.iter().cloned()
     }
 
+    /// Whether the vote for `key` carried by the given event is still unconsensused.
+    pub fn is_unconsensused(&self, event_index: EventIndex, key: &ObservationKey) -> bool {
+        self.unconsensused_events
+            .indices_by_key
+            .get(key)
+            .is_some_and(|indices| indices.contains(&event_index))
+    }
+
     fn add_interesting_event(
         &mut self,
         creator: PeerIndex,
@@ -204,16 +303,24 @@ impl MetaElection {
 
     // Updates unconsensused_events by removing those that became consensused.
     fn update_unconsensused_events(&mut self, decided_keys: &[ObservationKey]) {
+        let mut removed = BTreeSet::new();
         for key in decided_keys {
             if let Some(remove) = self.unconsensused_events.indices_by_key.remove(key) {
-                for event_index in remove {
-                    let _ = self
-                        .unconsensused_events
-                        .ordered_indices
-                        .remove(&event_index);
-                }
+                removed.extend(remove);
             }
         }
+
+        // Keep the events which still carry other unconsensused votes.
+        for indices in self.unconsensused_events.indices_by_key.values() {
+            removed.retain(|event_index| !indices.contains(event_index));
+        }
+
+        for event_index in removed {
+            let _ = self
+                .unconsensused_events
+                .ordered_indices
+                .remove(&event_index);
+        }
     }
 
     fn update_voters(&mut self, peer_list_changes: Vec<PeerListChange>) {
@@ -259,20 +366,36 @@ impl MetaElection {
         };
     }
 
+    // Keeps the inputs of the observers computed during the current meta-election, and starts the
+    // next one.
+    fn end_election(&mut self) {
+        let ended_election = EndedElection {
+            voters: self.voters.clone(),
+            first_interesting_events: self.first_interesting_events().collect(),
+        };
+        let _ = self.ended_elections.insert(self.election, ended_election);
+        self.election += 1;
+    }
+
     fn update_meta_events(&mut self, decided_keys: &[ObservationKey], peer_list_changed: bool) {
         if peer_list_changed {
             self.meta_events.clear();
+            self.ended_elections.clear();
         } else {
             let new_consensus_start_index = self.new_consensus_start_index;
-            self.meta_events.retain(|event_index, _| {
-                event_index.topological_index() >= new_consensus_start_index
-            });
+            self.meta_events.remove_before(new_consensus_start_index);
             let decided_keys_lookup: FnvHashSet<_> = decided_keys.iter().collect();
+            let mut observer_elections = BTreeSet::new();
             for meta_event in self.meta_events.values_mut() {
                 meta_event
                     .interesting_content
                     .retain(|payload_key| !decided_keys_lookup.contains(payload_key));
+                if let Some(inputs) = meta_event.observer_inputs {
+                    let _ = observer_elections.insert(inputs.election);
+                }
             }
+            self.ended_elections
+                .retain(|election, _| observer_elections.contains(election));
         }
     }
 
@@ -286,7 +409,7 @@ impl MetaElection {
         {
             let interesting = self
                 .meta_events
-                .get(&event.event_index())
+                .get(event.event_index())
                 .map(|meta_event| &meta_event.interesting_content)
                 .filter(|interesting_content| !interesting_content.is_empty())
                 .cloned();
@@ -307,8 +430,8 @@ pub(crate) mod snapshot {
     };
     use std::collections::BTreeMap;
 
-    #[serde(bound = "")]
     #[derive(Eq, PartialEq, Debug, Serialize, Deserialize)]
+    #[serde(bound = "")]
     pub(crate) struct MetaElectionSnapshot<P: PublicId> {
         meta_events: BTreeMap<EventHash, MetaEventSnapshot<P>>,
         voters: BTreeSet<P>,
@@ -329,7 +452,7 @@ pub(crate) mod snapshot {
                 .iter()
                 .filter_map(|(index, meta_event)| {
                     graph
-                        .get(*index)
+                        .get(index)
                         .map(|event| *event.hash())
                         .map(|hash| (hash, MetaEventSnapshot::new(meta_event, peer_list)))
                 })
@@ -17,6 +17,8 @@ use crate::{
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub(crate) struct MetaEvent {
     pub observer: Observer,
+    // What `observer` was computed from, if it was.
+    pub observer_inputs: Option<ObserverInputs>,
     // Hashes of payloads of all the votes deemed interesting by this event.
     pub interesting_content: Vec<ObservationKey>,
     pub meta_votes: PeerIndexMap<Vec<MetaVote>>,
@@ -65,12 +67,24 @@ impl Observer {
     }
 }
 
+// Everything the observer status of an event depends on, besides the ancestors of the event,
+// which never change. The voters and the first interesting events are those of the meta-election,
+// which keeps them: see `MetaElection::has_same_observer_inputs`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub(crate) struct ObserverInputs {
+    // The meta-election the observer status was computed during.
+    pub election: usize,
+    // Whether the self-sync-parent of the event is an observer or has an ancestor observer.
+    pub descends_from_observer: bool,
+}
+
 impl MetaEvent {
     pub fn build<P: PublicId>(event: IndexedEventRef<P>) -> MetaEventBuilder<P> {
         MetaEventBuilder {
             event,
             meta_event: MetaEvent {
                 observer: Observer::None,
+                observer_inputs: None,
                 interesting_content: Vec::new(),
                 meta_votes: PeerIndexMap::default(),
             },
@@ -120,8 +134,13 @@ impl<'a, P: PublicId + 'a> MetaEventBuilder<'a, P> {
         self.meta_event.observer.has_observee(peer_index)
     }
 
-    pub fn set_observer(&mut self, observer: Observer) {
+    pub fn observer_inputs(&self) -> Option<ObserverInputs> {
+        self.meta_event.observer_inputs
+    }
+
+    pub fn set_observer(&mut self, observer: Observer, inputs: ObserverInputs) {
         self.meta_event.observer = observer;
+        self.meta_event.observer_inputs = Some(inputs);
     }
 
     pub fn set_interesting_content(&mut self, content: Vec<ObservationKey>) {
@@ -143,8 +162,8 @@ pub(crate) mod snapshot {
     use crate::{id::SecretId, observation::snapshot::ObservationKeySnapshot, peer_list::PeerList};
     use std::collections::{BTreeMap, BTreeSet};
 
-    #[serde(bound = "")]
     #[derive(Eq, PartialEq, Debug, Serialize, Deserialize)]
+    #[serde(bound = "")]
     pub(crate) struct MetaEventSnapshot<P: PublicId> {
         observees: BTreeSet<P>,
         interesting_content: Vec<ObservationKeySnapshot<P>>,
@@ -184,5 +203,4 @@ pub(crate) mod snapshot {
             }
         }
     }
-
 }
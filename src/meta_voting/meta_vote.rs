@@ -110,7 +110,7 @@ impl MetaVote {
         for vote in prev {
             let counts = MetaVoteCounts::new(vote, others, total_peers);
             let mut updated = *vote;
-            updated.update(counts, &coin_tosses);
+            updated.update(counts, coin_tosses);
             let decided = vote.is_decided();
             next.push(updated);
             if decided {
@@ -119,7 +119,7 @@ impl MetaVote {
         }
 
         while let Some(next_meta_vote) =
-            Self::next_vote(next.last(), others, &coin_tosses, total_peers)
+            Self::next_vote(next.last(), others, coin_tosses, total_peers)
         {
             next.push(next_meta_vote);
         }
@@ -160,7 +160,7 @@ impl MetaVote {
             let coin_toss = coin_tosses.get(&parent.round);
             let mut next = parent.increase_step(&counts, coin_toss.cloned());
             let new_counts = MetaVoteCounts::new(&next, others, total_peers);
-            next.update(new_counts, &coin_tosses);
+            next.update(new_counts, coin_tosses);
             Some(next)
         } else {
             None
@@ -58,8 +58,7 @@ impl MetaVoteCounts {
             .filter_map(|other| {
                 other
                     .iter()
-                    .filter(|vote| vote.round_and_step() == parent.round_and_step())
-                    .last()
+                    .rfind(|vote| vote.round_and_step() == parent.round_and_step())
             })
             .chain(iter::once(parent))
         {
@@ -122,7 +121,7 @@ mod tests {
     use crate::meta_voting::meta_vote_values::{
         AuxValue, BinValues, Estimates, MetaVoteValues, UndecidedMetaVoteValues,
     };
-    use std::{iter, slice};
+    use std::slice;
 
     #[test]
     fn count_estimates() {
@@ -348,15 +347,17 @@ mod tests {
         total_peers: NonZeroUsize,
     ) -> MetaVoteCounts {
         let repeat_votes = |count, estimates| {
-            iter::repeat(MetaVote {
-                values: MetaVoteValues::Undecided(UndecidedMetaVoteValues::new(
-                    Estimates::new(estimates),
-                    BinValues::new(BoolSet::Empty),
-                    AuxValue::new(None),
-                )),
-                ..Default::default()
-            })
-            .take(count)
+            iter::repeat_n(
+                MetaVote {
+                    values: MetaVoteValues::Undecided(UndecidedMetaVoteValues::new(
+                        Estimates::new(estimates),
+                        BinValues::new(BoolSet::Empty),
+                        AuxValue::new(None),
+                    )),
+                    ..Default::default()
+                },
+                count,
+            )
         };
 
         let votes: Vec<_> = repeat_votes(num_empty, BoolSet::Empty)
@@ -375,15 +376,17 @@ mod tests {
         total_peers: NonZeroUsize,
     ) -> MetaVoteCounts {
         let repeat_votes = |count, aux_value| {
-            iter::repeat(MetaVote {
-                values: MetaVoteValues::Undecided(UndecidedMetaVoteValues::new(
-                    Estimates::new(BoolSet::Empty),
-                    BinValues::new(BoolSet::Empty),
-                    AuxValue::new(aux_value),
-                )),
-                ..Default::default()
-            })
-            .take(count)
+            iter::repeat_n(
+                MetaVote {
+                    values: MetaVoteValues::Undecided(UndecidedMetaVoteValues::new(
+                        Estimates::new(BoolSet::Empty),
+                        BinValues::new(BoolSet::Empty),
+                        AuxValue::new(aux_value),
+                    )),
+                    ..Default::default()
+                },
+                count,
+            )
         };
 
         let votes: Vec<_> = repeat_votes(num_empty, None)
@@ -180,9 +180,10 @@ impl Debug for MetaVoteValues {
 
 impl MetaVoteValues {
     pub fn from_initial_estimate(value: bool) -> Self {
-        let mut values = UndecidedMetaVoteValues::default();
-        values.estimates = Estimates::from_initial_value(value);
-        MetaVoteValues::Undecided(values)
+        MetaVoteValues::Undecided(UndecidedMetaVoteValues {
+            estimates: Estimates::from_initial_value(value),
+            ..UndecidedMetaVoteValues::default()
+        })
     }
 
     pub fn count(self, total_peers: NonZeroUsize) -> MetaVoteCounts {
@@ -373,19 +374,14 @@ impl Default for MetaVoteValues {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Serialize, Deserialize, Default)]
 pub(crate) enum Step {
+    #[default]
     ForcedTrue,
     ForcedFalse,
     GenuineFlip,
 }
 
-impl Default for Step {
-    fn default() -> Step {
-        Step::ForcedTrue
-    }
-}
-
 impl Debug for Step {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         let step = match self {
@@ -147,7 +147,7 @@ impl PublicId for PeerId {
 impl SecretId for PeerId {
     type PublicId = PeerId;
     fn public_id(&self) -> &Self::PublicId {
-        &self
+        self
     }
     fn sign_detached(&self, data: &[u8]) -> Signature {
         Signature(self.sec_sign.sign_detached(data))
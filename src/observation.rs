@@ -7,10 +7,11 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use crate::{
+    block::BlockHash,
     gossip::{EventHash, PackedEvent},
     hash::Hash,
-    id::{PublicId, SecretId},
-    key_gen::message::DkgMessage,
+    id::{KeyRotationProof, PublicId, SecretId},
+    key_gen::{decryption::DecryptionShareMessage, message::DkgMessage},
     network_event::NetworkEvent,
     peer_list::{Peer, PeerIndex, PeerList},
     serialise, DkgResultWrapper,
@@ -24,8 +25,8 @@ use std::{
 };
 
 /// An enum of the various network events for which a peer can vote.
-#[serde(bound = "")]
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub enum Observation<T: NetworkEvent, P: PublicId> {
     /// Genesis group
     Genesis {
@@ -78,6 +79,74 @@ pub enum Observation<T: NetworkEvent, P: PublicId> {
     /// Vote for the next message (Part or Ack) to be handled for the Distributed Key Generation
     /// algorithm used by our common coin.
     DkgMessage(DkgMessage),
+    /// Vote for an event whose payload is hidden until it has been consensused. Once consensused,
+    /// the plaintext is output in a later block as `OpaquePayload`, or the payload is output again
+    /// as `UndecryptablePayload` if it can never be revealed. See `Parsec::vote_for_encrypted`.
+    EncryptedPayload {
+        /// Id of the DKG whose public key the payload is encrypted to, as keyed in
+        /// `Parsec::dkg_status`.
+        key_gen: usize,
+        /// Serialised `threshold_crypto::Ciphertext` of the serialised `T`.
+        ciphertext: Vec<u8>,
+    },
+    /// Output only: Do not vote for it.
+    /// Will have empty proof set. Output in place of the plaintext of a consensused
+    /// `EncryptedPayload` which can never be revealed, because its ciphertext is invalid or the
+    /// keys of its DKG have been dropped.
+    UndecryptablePayload {
+        /// Id of the DKG the payload claims to be encrypted to.
+        key_gen: usize,
+        /// The ciphertext, as in the `EncryptedPayload`.
+        ciphertext: Vec<u8>,
+    },
+    /// Internal only: Do not vote for it or expect it to come in blocks.
+    /// A decryption share for a consensused `EncryptedPayload`.
+    DecryptionShare(DecryptionShareMessage),
+    /// Vote to rotate the signing key of a member. Only the member itself can vote for it: see
+    /// `Parsec::rotate_key`. Once consensused, the member keeps its voting rights and event chain,
+    /// but signs all its subsequent events with the new key.
+    RotateKey {
+        /// Public id the member currently signs its events with.
+        old: P,
+        /// Public id the member will sign its events with once this is consensused.
+        new: P,
+        /// Signatures of both keys, proving the member holds them.
+        proof: KeyRotationProof<P>,
+    },
+    /// Vote to split the section in two. `left` and `right` must partition the current voters,
+    /// otherwise the split is ignored. Once consensused, no further blocks are output and
+    /// `Parsec::split` creates the instance for the owning peer's side.
+    Split {
+        /// Voters making up one side of the split.
+        left: BTreeSet<P>,
+        /// Voters making up the other side of the split.
+        right: BTreeSet<P>,
+    },
+    /// Vote to merge with another section, which votes for the mirror `Merge` naming us. Once
+    /// consensused, no further blocks are output and `Parsec::merge` creates the joint instance.
+    Merge {
+        /// Voters of the other section. Must be disjoint from ours, otherwise the merge is
+        /// ignored.
+        other_section: BTreeSet<P>,
+        /// Hash of a block of the other section, as recent as we know of. Blocks the other section
+        /// consensuses after it, before its own `Merge` block, don't invalidate the merge.
+        other_last_block: BlockHash,
+    },
+    /// Vote to change the consensus parameters. Every member switches to them right after this
+    /// block, and a joining peer learns them when it reaches this block.
+    SetParameters(Parameters),
+    /// Internal only: Do not vote for it or expect it to come in blocks.
+    /// Retraction of the creator's own vote for the given observation. See `Parsec::retract_vote`.
+    Retract(Box<Observation<T, P>>),
+    /// Output only: Do not vote for it.
+    /// Will have empty proof set. Output in place of a consensused `OpaquePayload` bigger than the
+    /// `max_payload_size` of the `Parameters` in force when it was consensused.
+    OversizedPayload {
+        /// Hash of the block the payload would have been output in. See `BlockHash::new`.
+        hash: BlockHash,
+        /// Serialised size of the payload.
+        size: usize,
+    },
 }
 
 impl<T: NetworkEvent, P: PublicId> Observation<T, P> {
@@ -90,6 +159,14 @@ impl<T: NetworkEvent, P: PublicId> Observation<T, P> {
         }
     }
 
+    /// Is this observation's payload encrypted until consensused?
+    pub fn is_encrypted(&self) -> bool {
+        match *self {
+            Observation::EncryptedPayload { .. } => true,
+            _ => false,
+        }
+    }
+
     /// Is this observation an internal `DkgMessage`
     pub fn is_dkg_message(&self) -> bool {
         match *self {
@@ -98,14 +175,52 @@ impl<T: NetworkEvent, P: PublicId> Observation<T, P> {
         }
     }
 
+    /// Is this observation an internal `DecryptionShare`
+    pub fn is_decryption_share(&self) -> bool {
+        match *self {
+            Observation::DecryptionShare(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Is this observation an internal `Retract`
+    pub fn is_retraction(&self) -> bool {
+        match *self {
+            Observation::Retract(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Is this observation a `RotateKey`
+    pub fn is_key_rotation(&self) -> bool {
+        match *self {
+            Observation::RotateKey { .. } => true,
+            _ => false,
+        }
+    }
+
     /// Is this observation an internal and should not be published in a `Block`
     pub fn is_internal(&self) -> bool {
         match *self {
-            Observation::DkgMessage(_) | Observation::StartDkg(_) => true,
+            Observation::DkgMessage(_)
+            | Observation::StartDkg(_)
+            | Observation::DecryptionShare(_)
+            | Observation::Retract(_) => true,
             _ => false,
         }
     }
 
+    // Whether this observation can be voted for as part of a batch. Genesis, accusations, key
+    // rotations and internal observations must always be carried by events of their own.
+    pub(crate) fn can_be_batched(&self) -> bool {
+        match *self {
+            Observation::Genesis { .. }
+            | Observation::Accusation { .. }
+            | Observation::RotateKey { .. } => false,
+            _ => !self.is_internal(),
+        }
+    }
+
     /// Is this observation a result only `DkgResult`
     pub fn is_dkg_result(&self) -> bool {
         match *self {
@@ -133,13 +248,48 @@ impl<T: NetworkEvent, P: PublicId> Debug for Observation<T, P> {
             Observation::OpaquePayload(payload) => {
                 write!(formatter, "OpaquePayload({:?})", payload)
             }
+            Observation::EncryptedPayload {
+                key_gen,
+                ciphertext,
+            } => write!(
+                formatter,
+                "EncryptedPayload({}, {} bytes)",
+                key_gen,
+                ciphertext.len()
+            ),
+            Observation::UndecryptablePayload {
+                key_gen,
+                ciphertext,
+            } => write!(
+                formatter,
+                "UndecryptablePayload({}, {} bytes)",
+                key_gen,
+                ciphertext.len()
+            ),
+            Observation::DecryptionShare(msg) => write!(formatter, "{:?}", msg),
+            Observation::RotateKey { old, new, .. } => {
+                write!(formatter, "RotateKey({:?} -> {:?})", old, new)
+            }
+            Observation::Split { left, right } => {
+                write!(formatter, "Split({:?}, {:?})", left, right)
+            }
+            Observation::Merge { other_section, .. } => {
+                write!(formatter, "Merge({:?})", other_section)
+            }
+            Observation::SetParameters(parameters) => {
+                write!(formatter, "SetParameters({:?})", parameters)
+            }
+            Observation::Retract(observation) => write!(formatter, "Retract({:?})", observation),
+            Observation::OversizedPayload { hash, size } => {
+                write!(formatter, "OversizedPayload({:?}, {} bytes)", hash, size)
+            }
         }
     }
 }
 
 /// Type of malicious behaviour.
-#[serde(bound = "")]
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
 pub enum Malice<T: NetworkEvent, P: PublicId> {
     /// Event carries a vote for `Observation::Genesis`, but shouldn't.
     UnexpectedGenesis(EventHash),
@@ -241,8 +391,8 @@ impl PartialEq for UnprovableMalice {
 impl Eq for UnprovableMalice {}
 
 impl PartialOrd for UnprovableMalice {
-    fn partial_cmp(&self, _: &Self) -> Option<Ordering> {
-        Some(Ordering::Equal)
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
@@ -281,10 +431,6 @@ impl<'a> Visitor<'a> for UnprovableMaliceVisitor {
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub(crate) struct ObservationHash(pub(crate) Hash);
 
-impl ObservationHash {
-    pub const ZERO: Self = ObservationHash(Hash::ZERO);
-}
-
 impl<'a, T: NetworkEvent, P: PublicId> From<&'a Observation<T, P>> for ObservationHash {
     fn from(observation: &'a Observation<T, P>) -> Self {
         ObservationHash(Hash::from(serialise(observation).as_slice()))
@@ -303,6 +449,8 @@ pub(crate) struct ObservationInfo<T: NetworkEvent, P: PublicId> {
     pub(crate) observation: Observation<T, P>,
     pub(crate) consensused: bool,
     pub(crate) created_by_us: bool,
+    // Whether we retracted our vote for it.
+    pub(crate) retracted_by_us: bool,
 }
 
 impl<T: NetworkEvent, P: PublicId> ObservationInfo<T, P> {
@@ -311,6 +459,7 @@ impl<T: NetworkEvent, P: PublicId> ObservationInfo<T, P> {
             observation,
             consensused: false,
             created_by_us: false,
+            retracted_by_us: false,
         }
     }
 }
@@ -321,8 +470,11 @@ pub(crate) type ObservationStore<T, P> = BTreeMap<ObservationKey, ObservationInf
 // Observation with corresponding key for ObservationStore
 pub(crate) type ObservationForStore<T, P> = Option<(ObservationKey, ObservationInfo<T, P>)>;
 
+// Observations with corresponding keys for ObservationStore, in the order they're voted for.
+pub(crate) type ObservationsForStore<T, P> = Vec<(ObservationKey, ObservationInfo<T, P>)>;
+
 // Key to compare observations.
-#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize, Debug)]
 pub(crate) enum ObservationKey {
     Single(ObservationHash, PeerIndex),
     Supermajority(ObservationHash),
@@ -343,15 +495,6 @@ impl ObservationKey {
         }
     }
 
-    pub fn matches(&self, other_hash: &ObservationHash, other_creator: PeerIndex) -> bool {
-        match *self {
-            ObservationKey::Single(ref hash, creator) => {
-                other_hash == hash && other_creator == creator
-            }
-            ObservationKey::Supermajority(ref hash) => other_hash == hash,
-        }
-    }
-
     pub fn consensus_mode(&self) -> ConsensusMode {
         match *self {
             ObservationKey::Single(..) => ConsensusMode::Single,
@@ -383,7 +526,7 @@ impl ObservationKey {
 }
 
 /// Number of votes necessary to reach consensus on an `OpaquePayload`.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 pub enum ConsensusMode {
     /// One vote is enough.
     Single,
@@ -393,9 +536,13 @@ pub enum ConsensusMode {
 
 impl ConsensusMode {
     pub(crate) fn of<T: NetworkEvent, P: PublicId>(self, observation: &Observation<T, P>) -> Self {
-        if observation.is_opaque() {
+        if observation.is_opaque() || observation.is_encrypted() {
             self
-        } else if observation.is_dkg_message() {
+        } else if observation.is_dkg_message()
+            || observation.is_decryption_share()
+            || observation.is_key_rotation()
+            || observation.is_retraction()
+        {
             ConsensusMode::Single
         } else {
             ConsensusMode::Supermajority
@@ -403,6 +550,53 @@ impl ConsensusMode {
     }
 }
 
+/// Consensus parameters of a section, which its voters can change by voting for
+/// `Observation::SetParameters`. See `Parsec::parameters`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub struct Parameters {
+    /// Number of votes necessary to reach consensus on an `OpaquePayload` or `EncryptedPayload`.
+    pub consensus_mode: ConsensusMode,
+    /// Maximum serialised size of an `OpaquePayload`, if any. Bigger payloads can't be voted for,
+    /// and are output as `Observation::OversizedPayload` if consensused anyway, as votes cast
+    /// before the limit was lowered can be.
+    pub max_payload_size: Option<usize>,
+    /// If set, overrides the `max_sync_events` of the inactivity policies of the members. See
+    /// `InactivityPolicy::new`.
+    pub max_inactive_sync_events: Option<usize>,
+}
+
+impl Parameters {
+    /// Creates parameters with the given consensus mode, and no other limit.
+    pub fn new(consensus_mode: ConsensusMode) -> Self {
+        Self {
+            consensus_mode,
+            max_payload_size: None,
+            max_inactive_sync_events: None,
+        }
+    }
+
+    // Whether `observation` is an `OpaquePayload` bigger than allowed.
+    pub(crate) fn is_too_large<T: NetworkEvent, P: PublicId>(
+        &self,
+        observation: &Observation<T, P>,
+    ) -> bool {
+        self.oversize(observation).is_some()
+    }
+
+    // The serialised size of `observation`, if it's an `OpaquePayload` bigger than allowed.
+    pub(crate) fn oversize<T: NetworkEvent, P: PublicId>(
+        &self,
+        observation: &Observation<T, P>,
+    ) -> Option<usize> {
+        match (self.max_payload_size, observation) {
+            (Some(max_size), Observation::OpaquePayload(payload)) => {
+                Some(serialise(payload).len()).filter(|size| *size > max_size)
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Returns whether `small` is more than two thirds of `large`.
 pub fn is_more_than_two_thirds(small: usize, large: usize) -> bool {
     3 * small > 2 * large
@@ -413,8 +607,8 @@ pub(crate) mod snapshot {
     use super::*;
     use crate::{id::SecretId, peer_list::PeerList};
 
-    #[serde(bound = "")]
     #[derive(Eq, PartialEq, Debug, Serialize, Deserialize)]
+    #[serde(bound = "")]
     pub(crate) enum ObservationKeySnapshot<P: PublicId> {
         Supermajority(ObservationHash),
         Single(ObservationHash, P),
@@ -450,8 +644,8 @@ mod tests {
         let malice2 = Malice::Unprovable::<Transaction, PeerId>(UnprovableMalice::Unspecified);
 
         assert!(malice1 == malice2);
-        assert!(!(malice1 < malice2));
-        assert!(!(malice1 > malice2));
+        assert!(malice1 >= malice2);
+        assert!(malice1 <= malice2);
 
         assert_eq!(
             Hash::from(serialise(&malice1).as_slice()),
@@ -12,46 +12,53 @@ use crate::dev_utils::ParsedContents;
 use crate::gossip::EventHash;
 #[cfg(all(test, any(feature = "testing", feature = "mock")))]
 use crate::gossip::GraphSnapshot;
+#[cfg(any(feature = "testing", all(test, feature = "mock")))]
+use crate::mock::{PeerId, Transaction};
 #[cfg(feature = "malice-detection")]
 use crate::observation::Malice;
 use crate::{
-    block::{Block, BlockGroup},
+    block::{Block, BlockGroup, BlockHash},
     dump_graph,
     error::{Error, Result},
+    event_log::EventLog,
+    event_store::EventStore,
     gossip::{
         Event, EventContextRef, EventIndex, Graph, IndexedEventRef, PackedEvent, Request, Response,
     },
-    id::{PublicId, SecretId},
+    hash::{Hash, HashAlgorithmId, HASH_ALGORITHM},
+    id::{KeyRotationProof, PublicId, SecretId},
+    inactivity::{InactivityPolicy, InactivityTracker},
     key_gen::{
-        dkg_threshold, message::DkgMessage, parsec_rng::ParsecRng, Ack, AckOutcome, KeyGen, Part,
-        PartOutcome,
+        decryption::{self, DecryptionShareMessage, PendingDecryption},
+        dkg_status::DkgStatus,
+        dkg_threshold,
+        message::DkgMessage,
+        parsec_rng::ParsecRng,
+        Ack, AckOutcome, KeyGen, Part, PartOutcome,
     },
+    limits::Limits,
     meta_voting::{MetaElection, MetaEvent, MetaEventBuilder, MetaVote, Observer},
     network_event::NetworkEvent,
     observation::{
-        is_more_than_two_thirds, ConsensusMode, Observation, ObservationHash, ObservationKey,
-        ObservationStore,
+        is_more_than_two_thirds, ConsensusMode, Observation, ObservationHash, ObservationInfo,
+        ObservationKey, ObservationStore, Parameters,
     },
     parsec_helpers::find_interesting_content_for_event,
     peer_list::{Peer, PeerIndex, PeerIndexMap, PeerIndexSet, PeerList, PeerListChange, PeerState},
-};
-#[cfg(any(feature = "testing", all(test, feature = "mock")))]
-use crate::{
-    hash::Hash,
-    mock::{PeerId, Transaction},
+    serialise, DkgResult,
 };
 use fnv::FnvHashSet;
 use itertools::Itertools;
+use maidsafe_utilities::serialisation;
 #[cfg(any(test, feature = "testing"))]
 use std::ops::{Deref, DerefMut};
 use std::{
     collections::{BTreeMap, BTreeSet, VecDeque},
     iter,
     marker::PhantomData,
-    mem,
     num::NonZeroUsize,
-    usize,
 };
+use threshold_crypto::{Ciphertext, DecryptionShare};
 
 pub(crate) type KeyGenId = usize;
 
@@ -66,12 +73,16 @@ pub(crate) type KeyGenId = usize;
 /// types have to implement [NetworkEvent](trait.NetworkEvent.html) and
 /// [SecretId](trait.SecretId.html) traits, respectively.
 ///
-/// The `Parsec` struct exposes two constructors:
+/// The `Parsec` struct exposes the following constructors:
 ///
 /// * [from_genesis](struct.Parsec.html#method.from_genesis), if the owning peer is a part of the
 /// genesis group, i.e. the initial group of peers that participate in the network startup
 /// * [from_existing](struct.Parsec.html#method.from_existing), if the owning peer is trying to
 /// join an already functioning network
+/// * [rejoin_existing](struct.Parsec.html#method.rejoin_existing), if the owning peer is trying to
+/// join again a section it was removed from
+/// * [new_learner](struct.Parsec.html#method.new_learner), if the owning peer only follows the
+/// consensus of a section
 ///
 /// Once the peer becomes a full member of the section,
 /// [gossip_recipients](struct.Parsec.html#method.gossip_recipients) will start to return potential
@@ -108,9 +119,37 @@ pub struct Parsec<T: NetworkEvent, S: SecretId> {
     consensused_blocks: VecDeque<BlockGroup<T, S::PublicId>>,
     // The map of meta votes of the events on each consensus block.
     meta_election: MetaElection,
-    consensus_mode: ConsensusMode,
+    // The current consensus parameters.
+    parameters: Parameters,
     // Dkg messages to raise at the end of processing of current gossip message.
     pending_dkg_msgs: Vec<DkgMessage>,
+    // Participants and result of the completed DKGs whose keys are still held for
+    // `EncryptedPayload`s, by KeyGenId. The last one is the current section key.
+    section_keys: BTreeMap<KeyGenId, (BTreeSet<S::PublicId>, DkgResult)>,
+    // Consensused `EncryptedPayload`s waiting for enough decryption shares, by observation hash.
+    pending_decryptions: BTreeMap<ObservationHash, PendingDecryption>,
+    // Decryption shares to raise at the end of processing of current gossip message.
+    pending_decryption_shares: Vec<DecryptionShareMessage>,
+    // The first event retracting the vote of the given creator for the given observation.
+    retractions: BTreeMap<(PeerIndex, ObservationHash), EventIndex>,
+    // The id we are rotating our key to, until our `RotateKey` vote gets consensused.
+    pending_key: Option<S>,
+    // Applies the inactivity policy, if any.
+    inactivity: Option<InactivityTracker<S::PublicId>>,
+    // Memory limits of this instance.
+    limits: Limits,
+    // The consensused section split, if any. No further blocks are output once it is set.
+    split: Option<Split<S::PublicId>>,
+    // Hash of the last block output.
+    last_block_hash: Option<BlockHash>,
+    // The consensused section merge, if any. No further blocks are output once it is set.
+    merge: Option<Merge<S::PublicId>>,
+    // Whether we are a learner, following the consensus without ever voting.
+    learner: bool,
+    // The learners we push our gossip to, with how much of our graph each of them has.
+    learners: BTreeMap<S::PublicId, LearnerProgress>,
+    // The log our gossip events are written to, if any, with the number of events it holds.
+    event_log: Option<(EventLog, usize)>,
     // Accusations to raise at the end of the processing of current gossip message.
     #[cfg(feature = "malice-detection")]
     pending_accusations: Accusations<T, S::PublicId>,
@@ -133,7 +172,8 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
     /// * `genesis_related_info` extra arbitrary information attached to the genesis event for use
     /// by the client.
     /// * `consensus_mode` determines how many votes are needed for an observation to become a
-    /// candidate for consensus. For more details, see [ConsensusMode](enum.ConsensusMode.html)
+    /// candidate for consensus. For more details, see [ConsensusMode](enum.ConsensusMode.html).
+    /// It can be changed later by voting for `Observation::SetParameters`.
     /// * `secure_rng` cryptographically secure RNG to use for DKG key generation.
     pub fn from_genesis(
         our_id: S,
@@ -186,6 +226,9 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
 
     /// Creates a new `Parsec` for a peer that is joining an existing section.
     ///
+    /// The joiner receives the whole gossip graph since genesis and replays its consensus, as the
+    /// meta-elections need the full ancestry of the events they go through.
+    ///
     /// * `our_id` is the value that will identify the owning peer in the network.
     /// * `genesis_group` is the set of public IDs of the peers that were present at the section
     /// startup.
@@ -217,8 +260,68 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
             log_or_panic!("Section can't already contain us");
         }
 
-        let mut peer_list = PeerList::new(our_id);
+        Self::joining(
+            PeerList::new(our_id),
+            genesis_group,
+            section,
+            consensus_mode,
+            secure_rng,
+        )
+    }
+
+    /// Creates a new `Parsec` for a peer that rejoins a section it was removed from, with the same
+    /// ID.
+    ///
+    /// The rejoiner replays the whole gossip graph since genesis like any joiner. The events it
+    /// created in its former memberships are held apart from the ones it creates from now on, as
+    /// every member does.
+    ///
+    /// * `incarnation` is the number of memberships of the section the owning peer had before,
+    /// i.e. the number of times it was removed from it. It must be at least one.
+    ///
+    /// See `from_existing` for the other arguments. Unlike there, `genesis_group` contains us if
+    /// we were part of it.
+    pub fn rejoin_existing(
+        our_id: S,
+        genesis_group: &BTreeSet<S::PublicId>,
+        section: &BTreeSet<S::PublicId>,
+        incarnation: usize,
+        consensus_mode: ConsensusMode,
+        secure_rng: Box<dyn rand::Rng>,
+    ) -> Self {
+        if incarnation == 0 {
+            log_or_panic!("A rejoining peer must have been a member before");
+        }
+
+        if genesis_group.is_empty() {
+            log_or_panic!("Genesis group can't be empty");
+        }
+
+        if section.is_empty() {
+            log_or_panic!("Section can't be empty");
+        }
+
+        if section.contains(our_id.public_id()) {
+            log_or_panic!("Section can't already contain us");
+        }
+
+        Self::joining(
+            PeerList::new_rejoining(our_id, incarnation),
+            genesis_group,
+            section,
+            consensus_mode,
+            secure_rng,
+        )
+    }
 
+    // Construct `Parsec` for a peer joining a section of the given genesis group and members.
+    fn joining(
+        mut peer_list: PeerList<S>,
+        genesis_group: &BTreeSet<S::PublicId>,
+        section: &BTreeSet<S::PublicId>,
+        consensus_mode: ConsensusMode,
+        secure_rng: Box<dyn rand::Rng>,
+    ) -> Self {
         // Add ourselves
         peer_list.change_peer_state(PeerIndex::OUR, PeerState::RECV);
 
@@ -239,6 +342,25 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         Self::empty(peer_list, genesis_indices, consensus_mode, secure_rng)
     }
 
+    /// Creates a new `Parsec` for a learner: a peer which follows the consensus of an existing
+    /// section, outputting the same blocks as its voters, but never votes, never creates events
+    /// and is never counted towards the supermajority. See `from_existing` for the arguments.
+    ///
+    /// A learner only receives gossip, from the members which added it with `add_learner`, and
+    /// answers it with empty responses.
+    pub fn new_learner(
+        our_id: S,
+        genesis_group: &BTreeSet<S::PublicId>,
+        section: &BTreeSet<S::PublicId>,
+        consensus_mode: ConsensusMode,
+        secure_rng: Box<dyn rand::Rng>,
+    ) -> Self {
+        let mut parsec =
+            Self::from_existing(our_id, genesis_group, section, consensus_mode, secure_rng);
+        parsec.learner = true;
+        parsec
+    }
+
     // Construct empty `Parsec` with no peers (except us) and no gossip events.
     fn empty(
         peer_list: PeerList<S>,
@@ -256,8 +378,21 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
             consensused_blocks: VecDeque::new(),
             observations: BTreeMap::new(),
             meta_election: MetaElection::new(genesis_group),
-            consensus_mode,
+            parameters: Parameters::new(consensus_mode),
             pending_dkg_msgs: vec![],
+            section_keys: BTreeMap::new(),
+            pending_decryptions: BTreeMap::new(),
+            pending_decryption_shares: vec![],
+            retractions: BTreeMap::new(),
+            pending_key: None,
+            inactivity: None,
+            limits: Limits::default(),
+            split: None,
+            last_block_hash: None,
+            merge: None,
+            learner: false,
+            learners: BTreeMap::new(),
+            event_log: None,
             #[cfg(feature = "malice-detection")]
             pending_accusations: vec![],
             pending_events: vec![],
@@ -286,6 +421,20 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
 
         self.confirm_self_state(PeerState::VOTE)?;
 
+        if self.split.is_some() {
+            return Err(Error::SectionSplit);
+        }
+
+        if self.merge.is_some() {
+            return Err(Error::SectionMerged);
+        }
+
+        if self.parameters.is_too_large(&observation) {
+            return Err(Error::PayloadTooLarge);
+        }
+
+        self.confirm_vote_within_limits(1)?;
+
         if self.have_voted_for(&observation) {
             return Err(Error::DuplicateVote);
         }
@@ -299,13 +448,448 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         Ok(())
     }
 
+    /// Inserts the owning peer's votes for all of `observations` into the gossip graph at once,
+    /// as a single gossip event. Each vote is still signed individually and counts towards the
+    /// consensus of its own observation, so it ends up in the proofs of the corresponding block.
+    ///
+    /// Either all the votes are cast or none of them is. Returns an error under the same
+    /// conditions as `vote_for` for any of the observations, if `observations` contains the same
+    /// observation more than once, or if it contains a genesis, accusation or internal
+    /// observation. Voting for an empty batch does nothing.
+    pub fn vote_for_batch(&mut self, observations: Vec<Observation<T, S::PublicId>>) -> Result<()> {
+        debug!(
+            "{:?} voting for batch {:?}",
+            self.our_pub_id(),
+            observations
+        );
+
+        self.confirm_self_state(PeerState::VOTE)?;
+
+        if self.split.is_some() {
+            return Err(Error::SectionSplit);
+        }
+
+        if self.merge.is_some() {
+            return Err(Error::SectionMerged);
+        }
+
+        if observations.is_empty() {
+            return Ok(());
+        }
+
+        if observations
+            .iter()
+            .any(|observation| self.parameters.is_too_large(observation))
+        {
+            return Err(Error::PayloadTooLarge);
+        }
+
+        self.confirm_vote_within_limits(observations.len())?;
+
+        let keys: BTreeSet<_> = observations
+            .iter()
+            .map(|observation| self.our_key(observation))
+            .collect();
+        if keys.len() != observations.len()
+            || observations
+                .iter()
+                .any(|observation| self.have_voted_for(observation))
+        {
+            return Err(Error::DuplicateVote);
+        }
+
+        if !observations.iter().all(Observation::can_be_batched) {
+            return Err(Error::InvalidEvent);
+        }
+
+        self.flush_pending_events()?;
+
+        let self_parent = self.our_last_event_index()?;
+        let event = self.new_event_from_batch(self_parent, observations)?;
+
+        let _ = self.add_event(event)?;
+        Ok(())
+    }
+
+    /// Votes for the removal of the owning peer from the section.
+    ///
+    /// The owning peer should keep gossiping as usual until `has_left` returns `true`, so the
+    /// other members can reach consensus on the removal too. Meanwhile,
+    /// `our_abandoned_observations` returns the votes which might never be consensused, so they
+    /// can be handed over to another peer.
+    ///
+    /// Returns an error if the owning peer is not a full member of the section, or if it has
+    /// already requested to leave.
+    pub fn request_leave(&mut self) -> Result<()> {
+        let observation = Observation::Remove {
+            peer_id: self.our_pub_id().clone(),
+            related_info: vec![],
+        };
+        self.vote_for(observation)
+    }
+
+    /// Starts rotating the signing key of the owning peer to `new_id`.
+    ///
+    /// Votes for `Observation::RotateKey`, carrying the signatures of both our current and new
+    /// keys, so no other member needs to vote for it. Once it is consensused, the owning peer keeps
+    /// its voting rights and event chain, but signs all its subsequent events with `new_id`.
+    ///
+    /// Returns an error if a previous rotation hasn't been consensused yet.
+    pub fn rotate_key(&mut self, new_id: S) -> Result<()> {
+        if self.pending_key.is_some() {
+            return Err(Error::KeyRotationInProgress);
+        }
+
+        let observation = Observation::RotateKey {
+            old: self.our_pub_id().clone(),
+            new: new_id.public_id().clone(),
+            proof: KeyRotationProof::new(self.peer_list.our_id(), &new_id),
+        };
+        self.vote_for(observation)?;
+        self.pending_key = Some(new_id);
+        Ok(())
+    }
+
+    /// Sets the policy for automatically voting to remove voters from which no new event has
+    /// reached us for a while, or clears it if `None`. There is no such policy by default.
+    pub fn set_inactivity_policy(&mut self, policy: Option<InactivityPolicy<S::PublicId>>) {
+        let max_sync_events = self.parameters.max_inactive_sync_events;
+        self.inactivity = policy.map(|policy| InactivityTracker::new(policy, max_sync_events));
+    }
+
+    /// Sets the memory limits of this instance. There are no limits by default.
+    ///
+    /// Once our vote limit or the graph size limit is reached, `vote_for` and `vote_for_batch`
+    /// fail with `Error::Backpressure`, without any side effect. The caller should retry them
+    /// later, after more gossip has let the consensus progress, or retract some votes.
+    ///
+    /// `handle_request` fails with `Error::Backpressure` too, without any side effect, when the
+    /// sender is over the vote limit in our graph. We still get its events through our own
+    /// requests, and those of the other peers, so it's slowed down rather than cut off.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Moves the events of the gossip graph to `store`, so histories too large for the memory can
+    /// be handled. From then on, only the `cache_capacity` most recently used events are kept in
+    /// memory between the steps of the consensus computation, and once a gossip message is packed.
+    /// Can be called at any time.
+    ///
+    /// Returns `Error::EventStore` if `store` isn't empty.
+    /// A later failure to read or write `store` is logged as an error, and panics in debug builds.
+    pub fn set_event_store(
+        &mut self,
+        store: Box<dyn EventStore<S::PublicId>>,
+        cache_capacity: usize,
+    ) -> Result<()> {
+        self.graph.set_store(store, cache_capacity)
+    }
+
+    /// Makes the owning peer write all the events of its gossip graph to `log`, so this instance
+    /// can be rebuilt after a crash or a restart. Our own events are synced to disk before any
+    /// `Request` or `Response` carrying them is returned, so a rebuilt instance never forks them.
+    ///
+    /// To rebuild an instance, create it again with the same arguments as the original one, then
+    /// call this method with the log of the original one before anything else: the events in the
+    /// log are added to the graph again, recomputing the consensus, so `poll` returns the blocks
+    /// consensused before the restart again. A DKG in progress at the time of the restart can't
+    /// be completed by the owning peer though.
+    ///
+    /// Returns `Error::EventLog` if the log can't be read or written, or if it wasn't written by an
+    /// instance created with the same arguments.
+    pub fn set_event_log(&mut self, mut log: EventLog) -> Result<()> {
+        let logged_events = log.read()?;
+        let num_logged_events = logged_events.len();
+
+        let matches = logged_events
+            .iter()
+            .zip(self.graph.iter())
+            .all(|(logged_event, event)| logged_event.compute_hash() == *event.hash());
+        if !matches {
+            return Err(Error::EventLog);
+        }
+
+        for packed_event in logged_events {
+            // Our events created while adding the logged ones, like our initial event, are
+            // identical to the logged ones.
+            if self.graph.contains(&packed_event.compute_hash()) {
+                continue;
+            }
+            self.replay_event(packed_event)
+                .map_err(|_| Error::EventLog)?;
+        }
+
+        // The messages we raised when processing these events before the restart are already in
+        // the log. New ones would conflict with them.
+        self.pending_dkg_msgs.clear();
+        self.pending_decryption_shares.clear();
+        #[cfg(feature = "malice-detection")]
+        self.pending_accusations.clear();
+
+        self.event_log = Some((log, num_logged_events));
+        self.sync_event_log()
+    }
+
+    /// Returns the current consensus parameters: the ones given at creation, as changed by the
+    /// consensused `Observation::SetParameters` so far.
+    pub fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+
+    /// Creates the `Parsec` instance for the owning peer's side of the consensused
+    /// `Observation::Split`, with a genesis made of that side. Its related info is the serialised
+    /// `(left, right, split block hash, history hash)` tuple, the history hash covering the blocks
+    /// before the split, so each child history is seeded from the exact point its parent ended.
+    /// Our unconsensused `OpaquePayload` votes are cast again in it.
+    ///
+    /// The current instance doesn't output any block after the split, but should keep handling
+    /// gossip for a while, so the other members can reach consensus on the split too.
+    ///
+    /// Returns `Error::MissingSplit` if no split has been consensused yet, or `Error::NotInSplit`
+    /// if the owning peer is on neither side.
+    pub fn split(&self, secure_rng: Box<dyn rand::Rng>) -> Result<Self>
+    where
+        S: Clone,
+    {
+        let split = self.split.as_ref().ok_or(Error::MissingSplit)?;
+        let group = if split.left.contains(self.our_pub_id()) {
+            &split.left
+        } else if split.right.contains(self.our_pub_id()) {
+            &split.right
+        } else {
+            return Err(Error::NotInSplit);
+        };
+
+        let mut parsec = Self::from_genesis(
+            self.peer_list.our_id().clone(),
+            group,
+            serialise(split),
+            self.parameters.consensus_mode,
+            secure_rng,
+        );
+        parsec.parameters = self.parameters;
+        for observation in self.our_unconsensused_observations().filter(|observation| {
+            observation.is_opaque() && !self.parameters.is_too_large(observation)
+        }) {
+            parsec.vote_for(observation.clone())?;
+        }
+
+        Ok(parsec)
+    }
+
+    /// Returns the hash of the last block output, which the other section names in its
+    /// `Observation::Merge` when merging with ours.
+    pub fn last_block_hash(&self) -> Option<BlockHash> {
+        self.last_block_hash
+    }
+
+    /// Creates the joint `Parsec` instance once our `Observation::Merge` has been consensused.
+    /// `their_merge` is the `Merge` consensused by the other section, which must name our voters
+    /// and one of our blocks. That block may be older than our `Merge` block: the other section
+    /// doesn't need to know our latest block when voting.
+    ///
+    /// The genesis of the joint instance is made of both sections' voters, with the serialised
+    /// `(voters, Merge block hash)` pairs of both parent sections as related info, so it's tied
+    /// to the block ending each parent history. Our unconsensused `OpaquePayload` votes are cast
+    /// again in it, so every member carrying over its own votes carries over those of both
+    /// sections.
+    ///
+    /// The current instance doesn't output any block after the merge, but should keep handling
+    /// gossip for a while, so the other members can reach consensus on the merge too.
+    ///
+    /// Returns `Error::MissingMerge` if no merge has been consensused yet, or
+    /// `Error::InvalidMerge` if `their_merge` doesn't mirror ours.
+    pub fn merge(
+        &self,
+        their_merge: &Observation<T, S::PublicId>,
+        secure_rng: Box<dyn rand::Rng>,
+    ) -> Result<Self>
+    where
+        S: Clone,
+    {
+        let merge = self.merge.as_ref().ok_or(Error::MissingMerge)?;
+        match *their_merge {
+            Observation::Merge {
+                ref other_section,
+                ref other_last_block,
+            } if *other_section == merge.voters && self.has_block(other_last_block) => {}
+            _ => return Err(Error::InvalidMerge),
+        }
+
+        let genesis_group = merge.voters.union(&merge.other_section).cloned().collect();
+        let related_info: BTreeSet<_> = vec![
+            (&merge.voters, merge.block_hash),
+            (&merge.other_section, BlockHash::new(their_merge)),
+        ]
+        .into_iter()
+        .collect();
+        let mut parsec = Self::from_genesis(
+            self.peer_list.our_id().clone(),
+            &genesis_group,
+            serialise(&related_info),
+            self.parameters.consensus_mode,
+            secure_rng,
+        );
+        parsec.parameters = self.parameters;
+        for observation in self.our_unconsensused_observations().filter(|observation| {
+            observation.is_opaque() && !self.parameters.is_too_large(observation)
+        }) {
+            parsec.vote_for(observation.clone())?;
+        }
+
+        Ok(parsec)
+    }
+
+    // Hash of the payload hashes of the first `len` consensused blocks.
+    fn consensus_history_hash(&self, len: usize) -> Hash {
+        let hashes: Vec<_> = self
+            .meta_election
+            .consensus_history()
+            .iter()
+            .take(len)
+            .map(ObservationKey::hash)
+            .collect();
+        Hash::from(serialise(&hashes).as_slice())
+    }
+
+    // Whether a block with the given hash is part of our consensus history.
+    fn has_block(&self, block_hash: &BlockHash) -> bool {
+        self.meta_election
+            .consensus_history()
+            .iter()
+            .any(|key| BlockHash::from_observation_hash(key.hash()) == *block_hash)
+    }
+
+    /// Returns `true` once the owning peer has been removed from the section, either following
+    /// `request_leave` or the other members' votes. The block with payload
+    /// `Observation::Remove(our_id)` is the last one `poll` will return.
+    pub fn has_left(&self) -> bool {
+        self.peer_list.has_been_removed(PeerIndex::OUR)
+    }
+
+    /// Returns the observations voted for by the owning peer which haven't been consensused yet,
+    /// excluding its own removal, once it has requested to leave or has been removed.
+    ///
+    /// Until `has_left` returns `true` some of them might still be consensused. Afterwards none of
+    /// them will ever be returned by `poll`, so the application should ask another peer to vote
+    /// for them instead.
+    pub fn our_abandoned_observations(&self) -> impl Iterator<Item = &Observation<T, S::PublicId>> {
+        let leaving = self.has_left() || self.has_requested_leave();
+        self.our_unconsensused_observations()
+            .filter(move |observation| leaving && !self.is_our_removal(observation))
+    }
+
+    /// Encrypts `payload` to the public key of the most recently completed DKG and votes for it as
+    /// an `Observation::EncryptedPayload`. The payload is ordered while still encrypted, so it
+    /// can't be front-run. Once consensused, the members holding a secret key share gossip their
+    /// decryption shares and the plaintext is output in a later block as
+    /// `Observation::OpaquePayload`.
+    ///
+    /// The keys of a DKG are held until it's superseded by two newer ones and no consensused
+    /// payload encrypted to it is left to reveal. A payload consensused after its keys have been
+    /// dropped is output as `Observation::UndecryptablePayload` instead, and should be voted for
+    /// again.
+    ///
+    /// Returns `Error::MissingSectionKey` if no DKG has completed yet, or any error `vote_for`
+    /// would return.
+    pub fn vote_for_encrypted(&mut self, payload: &T) -> Result<()> {
+        let (key_gen, public_key) = self
+            .section_keys
+            .iter()
+            .next_back()
+            .map(|(key_gen, (_, dkg_result))| (*key_gen, dkg_result.public_key_set.public_key()))
+            .ok_or(Error::MissingSectionKey)?;
+        let ciphertext =
+            decryption::encrypt(&public_key, &serialise(payload), &mut self.secure_rng);
+
+        self.vote_for(Observation::EncryptedPayload {
+            key_gen,
+            ciphertext: serialise(&ciphertext),
+        })
+    }
+
+    /// Returns the progress of each distributed key generation which has started but not yet
+    /// produced its `DkgResult` block, keyed by the order in which their `StartDkg` observations
+    /// were consensused.
+    pub fn dkg_status(&self) -> BTreeMap<usize, DkgStatus<S::PublicId>> {
+        self.key_gen
+            .iter()
+            .map(|(key_gen_id, key_gen)| (*key_gen_id, key_gen.status()))
+            .collect()
+    }
+
+    /// Returns the result of the most recently completed DKG, including our secret key share,
+    /// sealed under our `SecretId`. It can be persisted and passed to `import_dkg_result` after a
+    /// restart, since the secret key share is never included in `DkgResult` blocks.
+    ///
+    /// Returns `Error::MissingSectionKey` if no DKG has completed yet.
+    pub fn export_dkg_result(&self) -> Result<Vec<u8>> {
+        let (key_gen, (participants, dkg_result)) = self
+            .section_keys
+            .iter()
+            .next_back()
+            .ok_or(Error::MissingSectionKey)?;
+        dkg_result.seal(*key_gen, participants, self.peer_list.our_id())
+    }
+
+    /// Restores a DKG result exported by `export_dkg_result`. It becomes our current section key
+    /// unless a later DKG has completed since.
+    ///
+    /// Returns `Error::SealingFailure` if `sealed` wasn't exported by us.
+    pub fn import_dkg_result(&mut self, sealed: &[u8]) -> Result<()> {
+        let (key_gen, participants, dkg_result) =
+            DkgResult::unseal(sealed, self.peer_list.our_id())?;
+        let _ = self
+            .section_keys
+            .insert(key_gen, (participants, dkg_result));
+        Ok(())
+    }
+
     /// Returns an iterator with the IDs of peers who the owning peer can send gossip messages to.
     /// Calling `create_gossip` with a peer ID returned by this method is guaranteed to succeed
     /// (assuming no section mutation happened in between).
     pub fn gossip_recipients(&self) -> impl Iterator<Item = &S::PublicId> {
+        let learners = if self.peer_list.our_state().can_send() {
+            Some(self.learners.keys())
+        } else {
+            None
+        };
+
         self.peer_list
             .gossip_recipients()
             .map(|(_, peer)| peer.id())
+            .chain(learners.into_iter().flatten())
+    }
+
+    /// Adds a learner to push our gossip to. Unlike voters, learners are added locally by each
+    /// member willing to serve them, without voting. See `new_learner`.
+    ///
+    /// Returns an error if the owning peer can't send gossip, or if `peer_id` is a member of the
+    /// section.
+    pub fn add_learner(&mut self, peer_id: S::PublicId) -> Result<()> {
+        self.confirm_self_state(PeerState::SEND)?;
+
+        if let Some(peer_index) = self.peer_list.get_index(&peer_id) {
+            if !self.is_removed(peer_index) {
+                return Err(Error::InvalidPeerState {
+                    required: PeerState::RECV,
+                    actual: self.peer_list.peer_state(peer_index),
+                });
+            }
+        }
+
+        let _ = self.learners.entry(peer_id).or_default();
+        Ok(())
+    }
+
+    /// Stops pushing our gossip to the given learner. Returns `false` if it wasn't a learner.
+    pub fn remove_learner(&mut self, peer_id: &S::PublicId) -> bool {
+        self.learners.remove(peer_id).is_some()
+    }
+
+    /// Returns whether the owning peer is a learner.
+    pub fn is_learner(&self) -> bool {
+        self.learner
     }
 
     /// Creates a new message to be gossiped to a peer, containing all gossip events this peer
@@ -314,6 +898,10 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
     /// * `peer_id`: the intended recipient of the gossip message
     /// * returns a `Request` to be sent to the intended recipient
     pub fn create_gossip(&mut self, peer_id: &S::PublicId) -> Result<Request<T, S::PublicId>> {
+        if self.learners.contains_key(peer_id) {
+            return self.create_learner_gossip(peer_id);
+        }
+
         let peer_index = self.get_peer_index(peer_id)?;
         self.confirm_allowed_to_gossip_to(peer_index)?;
 
@@ -330,17 +918,23 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         let sync_event = Event::new_from_requesting(self_parent, peer_id, self.event_context())?;
         let _ = self.add_event(sync_event)?;
 
+        self.sync_event_log()?;
+
         let events = if self.peer_list.last_event(peer_index).is_some() {
             self.events_to_gossip_to_peer(peer_index)?
         } else {
             self.graph.iter().map(|e| e.inner()).collect()
         };
-        self.pack_events(events).map(Request::new)
+        let packed_events = self.pack_events(events);
+        self.graph.shrink_cache();
+        packed_events.map(Request::new)
     }
 
     /// Handles a `Request` the owning peer received from the `src` peer.  Returns a `Response` to
-    /// be sent back to `src`, or `Err` if the request was not valid or if `src` has been removed
-    /// from the section already.
+    /// be sent back to `src`, or `Err` if the request was not valid.
+    ///
+    /// If `src` has been removed from the section already, its events are ignored, but it still
+    /// gets ours in the response, so it can learn it has left.
     pub fn handle_request(
         &mut self,
         src: &S::PublicId,
@@ -352,16 +946,37 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
             src
         );
 
+        confirm_hash_algorithm(req.hash_algorithm)?;
         let src_index = self.get_peer_index(src)?;
+        self.confirm_request_within_limits(src_index)?;
+        if self.learner {
+            if !req.packed_events.is_empty() {
+                let _ = self.unpack_and_add_events(src_index, req.packed_events)?;
+            }
+            self.sync_event_log()?;
+            return Ok(Response::new(vec![]));
+        }
+
+        if self.peer_list.has_been_removed(src_index) {
+            let events = self.events_to_gossip_to_peer(src_index)?;
+            let packed_events = self.pack_events(events);
+            self.graph.shrink_cache();
+            return packed_events.map(Response::new);
+        }
+
         let other_parent = self.unpack_and_add_events(src_index, req.packed_events)?;
         self.create_dkg_events()?;
+        self.create_decryption_share_events()?;
         #[cfg(feature = "malice-detection")]
         self.create_accusation_events(other_parent)?;
         self.create_sync_event(true, other_parent)?;
         self.flush_pending_events()?;
+        self.sync_event_log()?;
 
         let events = self.events_to_gossip_to_peer(src_index)?;
-        self.pack_events(events).map(Response::new)
+        let packed_events = self.pack_events(events);
+        self.graph.shrink_cache();
+        packed_events.map(Response::new)
     }
 
     /// Handles a `Response` the owning peer received from the `src` peer. Returns `Err` if the
@@ -377,9 +992,25 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
             src
         );
 
+        confirm_hash_algorithm(resp.hash_algorithm)?;
+        if let Some(progress) = self.learners.get_mut(src) {
+            // The learner acknowledges our request.
+            if !resp.packed_events.is_empty() {
+                return Err(Error::InvalidMessage);
+            }
+            progress.acked = progress.sent;
+            return Ok(());
+        }
+
         let src_index = self.get_peer_index(src)?;
         let other_parent = self.unpack_and_add_events(src_index, resp.packed_events)?;
+        if self.has_left() {
+            // `src` might have removed us already, in which case it answered with its events only.
+            return Ok(());
+        }
+
         self.create_dkg_events()?;
+        self.create_decryption_share_events()?;
         #[cfg(feature = "malice-detection")]
         self.create_accusation_events(other_parent)?;
         self.create_sync_event(false, other_parent)?;
@@ -414,6 +1045,44 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         self.consensused_blocks.pop_front()
     }
 
+    /// Retracts the owning peer's vote for `observation`. The retraction is gossiped like a vote,
+    /// and the events seeing it no longer count our vote towards consensus on `observation`. It
+    /// can still be consensused if enough voters decide on it without seeing the retraction.
+    ///
+    /// Once retracted, `observation` is no longer reported by `our_unpolled_observations`, and
+    /// can't be voted for again by the owning peer.
+    ///
+    /// Returns `Error::AlreadyConsensused` if `observation` has already been consensused,
+    /// `Error::UnknownPayload` if we haven't voted for it, `Error::DuplicateVote` if we have
+    /// already retracted it, or an error if the owning peer is not a full member of the section.
+    pub fn retract_vote(&mut self, observation: &Observation<T, S::PublicId>) -> Result<()> {
+        debug!("{:?} retracting {:?}", self.our_pub_id(), observation);
+
+        self.confirm_self_state(PeerState::VOTE)?;
+
+        let key = self.our_key(observation);
+        match self.observations.get(&key) {
+            Some(info) if info.created_by_us => {
+                if info.consensused {
+                    return Err(Error::AlreadyConsensused);
+                }
+                if info.retracted_by_us {
+                    return Err(Error::DuplicateVote);
+                }
+            }
+            _ => return Err(Error::UnknownPayload),
+        }
+
+        self.flush_pending_events()?;
+
+        let self_parent = self.our_last_event_index()?;
+        let retraction = Observation::Retract(Box::new(observation.clone()));
+        let event = self.new_event_from_observation(self_parent, retraction)?;
+
+        let _ = self.add_event(event)?;
+        Ok(())
+    }
+
     /// Check if the owning peer can vote (that is, it has reached a consensus on itself being a
     /// full member of the section).
     pub fn can_vote(&self) -> bool {
@@ -422,19 +1091,28 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
 
     /// Checks if the given `observation` has already been voted for by the owning peer.
     pub fn have_voted_for(&self, observation: &Observation<T, S::PublicId>) -> bool {
-        let hash = ObservationHash::from(observation);
-        let key = ObservationKey::new(hash, PeerIndex::OUR, self.consensus_mode.of(observation));
         self.observations
-            .get(&key)
+            .get(&self.our_key(observation))
             .map(|info| info.created_by_us)
             .unwrap_or(false)
     }
 
+    // Key of our vote for `observation`.
+    fn our_key(&self, observation: &Observation<T, S::PublicId>) -> ObservationKey {
+        ObservationKey::new(
+            ObservationHash::from(observation),
+            PeerIndex::OUR,
+            self.parameters.consensus_mode.of(observation),
+        )
+    }
+
     /// Check if there are any observations that have been voted for but not yet polled - that is,
     /// either they haven't been consensused yet or a block containing that observation hasn't yet
     /// been retrieved by calling `poll`.
     pub fn has_unpolled_observations(&self) -> bool {
-        self.observations.values().any(|info| !info.consensused)
+        self.observations
+            .values()
+            .any(|info| !info.consensused && !info.retracted_by_us)
             || !self.consensused_blocks.is_empty()
     }
 
@@ -462,9 +1140,22 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         })
     }
 
+    fn has_requested_leave(&self) -> bool {
+        self.observations
+            .values()
+            .any(|info| info.created_by_us && self.is_our_removal(&info.observation))
+    }
+
+    fn is_our_removal(&self, observation: &Observation<T, S::PublicId>) -> bool {
+        match observation {
+            Observation::Remove { peer_id, .. } => peer_id == self.our_pub_id(),
+            _ => false,
+        }
+    }
+
     fn our_unconsensused_observations(&self) -> impl Iterator<Item = &Observation<T, S::PublicId>> {
         self.observations.values().filter_map(|info| {
-            if info.created_by_us && !info.consensused {
+            if info.created_by_us && !info.consensused && !info.retracted_by_us {
                 Some(&info.observation)
             } else {
                 None
@@ -483,7 +1174,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         // signed by us, yet with payloads voted for by us.
         // In `Single` mode, on the other hand, check also that we signed it, to avoid false
         // positives when there are blocks with the same payloads but signed by someone else.
-        match self.consensus_mode.of(payload) {
+        match self.parameters.consensus_mode.of(payload) {
             ConsensusMode::Supermajority => matching_blocks.next().is_some(),
             ConsensusMode::Single => {
                 matching_blocks.any(|block| block.is_signed_by(self.our_pub_id()))
@@ -492,10 +1183,38 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
     }
 
     /// Must only be used for events which have already been added to our graph.
-    fn get_known_event(&self, event_index: EventIndex) -> Result<IndexedEventRef<S::PublicId>> {
+    fn get_known_event(&self, event_index: EventIndex) -> Result<IndexedEventRef<'_, S::PublicId>> {
         get_known_event(self.our_pub_id(), &self.graph, event_index)
     }
 
+    // Learners have all our events up to the ones they acknowledged, so we send them the rest,
+    // without creating a sync event: they aren't part of the graph.
+    fn create_learner_gossip(&mut self, peer_id: &S::PublicId) -> Result<Request<T, S::PublicId>> {
+        self.confirm_self_state(PeerState::SEND)?;
+
+        debug!(
+            "{:?} creating gossip request for learner {:?}",
+            self.our_pub_id(),
+            peer_id
+        );
+
+        let acked = self
+            .learners
+            .get(peer_id)
+            .map_or(0, |progress| progress.acked);
+        self.sync_event_log()?;
+        let events = self.graph.iter_from(acked).map(|event| event.inner());
+        let request = self.pack_events(events).map(Request::new);
+        self.graph.shrink_cache();
+        let request = request?;
+
+        let sent = self.graph.len();
+        if let Some(progress) = self.learners.get_mut(peer_id) {
+            progress.sent = sent;
+        }
+        Ok(request)
+    }
+
     fn confirm_allowed_to_gossip_to(&self, peer_index: PeerIndex) -> Result<()> {
         self.confirm_self_state(PeerState::SEND)?;
         // We require `PeerState::DKG` in addition to `PeerState::RECV` here, because if the
@@ -505,6 +1224,73 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         self.confirm_peer_state(peer_index, PeerState::DKG | PeerState::RECV)
     }
 
+    // Checks that we can cast `num_votes` new votes without going over our limits.
+    fn confirm_vote_within_limits(&self, num_votes: usize) -> Result<()> {
+        // Only the votes cast through `vote_for` and `vote_for_batch` count.
+        let num_unconsensused_votes = self
+            .our_unconsensused_observations()
+            .filter(|observation| match observation {
+                Observation::Genesis { .. } | Observation::Accusation { .. } => false,
+                _ => !observation.is_internal(),
+            })
+            .count();
+        if self
+            .limits
+            .max_unconsensused_votes
+            .is_some_and(|max_votes| num_unconsensused_votes + num_votes > max_votes)
+            || self.is_graph_full()
+        {
+            return Err(Error::Backpressure);
+        }
+        Ok(())
+    }
+
+    // Checks that `src_index` hasn't flooded the section with more votes than our limit. A peer
+    // sitting exactly at the limit is still within it.
+    fn confirm_request_within_limits(&self, src_index: PeerIndex) -> Result<()> {
+        if src_index == PeerIndex::OUR {
+            return Ok(());
+        }
+        if self
+            .limits
+            .max_unconsensused_votes
+            .is_some_and(|max_votes| self.num_unconsensused_votes_by(src_index) > max_votes)
+        {
+            return Err(Error::Backpressure);
+        }
+        Ok(())
+    }
+
+    fn is_graph_full(&self) -> bool {
+        // The events up to the first one carrying an unconsensused vote are done with.
+        let start_index = self
+            .meta_election
+            .unconsensused_events(None)
+            .next()
+            .map_or(self.graph.len(), |event_index| {
+                event_index.topological_index()
+            });
+        self.limits
+            .max_graph_size
+            .is_some_and(|max_graph_size| self.graph.len() - start_index >= max_graph_size)
+    }
+
+    // Number of votes by `creator` in our graph for observations that aren't consensused yet.
+    fn num_unconsensused_votes_by(&self, creator: PeerIndex) -> usize {
+        self.unconsensused_events(None)
+            .filter(|event| event.creator() == creator)
+            .map(|event| {
+                event
+                    .payload_keys()
+                    .filter(|payload_key| {
+                        self.meta_election
+                            .is_unconsensused(event.event_index(), payload_key)
+                    })
+                    .count()
+            })
+            .sum()
+    }
+
     fn confirm_peer_state(&self, peer_index: PeerIndex, required: PeerState) -> Result<()> {
         let actual = self.peer_list.peer_state(peer_index);
         if actual.contains(required) {
@@ -579,6 +1365,31 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         })
     }
 
+    // Writes the events added to the graph since the last call to the event log, if any, and syncs
+    // it to disk.
+    fn sync_event_log(&mut self) -> Result<()> {
+        let num_logged_events = match self.event_log {
+            Some((_, num_logged_events)) => num_logged_events,
+            None => return Ok(()),
+        };
+
+        let events = self.pack_events(
+            self.graph
+                .iter_from(num_logged_events)
+                .map(|event| event.inner()),
+        );
+        self.graph.shrink_cache();
+        let events = events?;
+        if let Some((ref mut log, ref mut num_logged_events)) = self.event_log {
+            for event in &events {
+                log.append(event)?;
+            }
+            log.sync()?;
+            *num_logged_events += events.len();
+        }
+        Ok(())
+    }
+
     fn pack_events<'a, I>(&self, events: I) -> Result<Vec<PackedEvent<T, S::PublicId>>>
     where
         I: IntoIterator<Item = &'a Event<S::PublicId>>,
@@ -603,7 +1414,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         let hash_of_last_event = packed_events
             .last()
             .map(PackedEvent::compute_hash)
-            .ok_or_else(|| Error::InvalidMessage)?;
+            .ok_or(Error::InvalidMessage)?;
         for packed_event in packed_events {
             if let Some(event) = self.unpack(packed_event)? {
                 let event_creator = event.creator();
@@ -621,22 +1432,79 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
             }
         }
 
+        // Learners are never voted in, so they never get `PeerState::DKG`, and a peer which just
+        // learnt of its own removal from these events has lost it.
         #[cfg(feature = "malice-detection")]
-        self.detect_premature_gossip()?;
+        {
+            if !self.learner && !self.has_left() {
+                self.detect_premature_gossip()?;
+            }
+        }
 
         let last_event_index = self
             .graph
             .get_index(&hash_of_last_event)
-            .ok_or_else(|| Error::InvalidMessage)?;
+            .ok_or(Error::InvalidMessage)?;
         Ok(last_event_index)
     }
 
+    // Adds an event read from our event log to the graph, as when it was first added.
+    fn replay_event(&mut self, packed_event: PackedEvent<T, S::PublicId>) -> Result<()> {
+        if let Some(event) = self.unpack(packed_event)? {
+            let event_creator = event.creator();
+            let _ = self.add_event(event)?;
+            if event_creator != PeerIndex::OUR {
+                self.peer_list
+                    .change_peer_state(event_creator, PeerState::RECV);
+            }
+        }
+        Ok(())
+    }
+
     fn unpack(
         &mut self,
         packed_event: PackedEvent<T, S::PublicId>,
     ) -> Result<Option<Event<S::PublicId>>> {
+        let creator_id = packed_event.creator().clone();
         if let Some(unpacked_event) = Event::unpack(packed_event, self.event_context())? {
-            if let Some((payload_key, observation_info)) = unpacked_event.observation_for_store {
+            let creator = unpacked_event.event.creator();
+            for (_, info) in &unpacked_event.observations_for_store {
+                if let Observation::RotateKey {
+                    ref old,
+                    ref new,
+                    ref proof,
+                } = info.observation
+                {
+                    // Only the member itself can announce its key rotation. The new key isn't
+                    // accepted until the rotation is consensused.
+                    let valid = self
+                        .peer_list
+                        .get(creator)
+                        .is_some_and(|peer| peer.id() == old)
+                        && proof.is_valid(old, new)
+                        && self
+                            .peer_list
+                            .get_index(new)
+                            .is_none_or(|index| index == creator);
+                    if !valid {
+                        debug!(
+                            "{:?} received invalid key rotation {:?} -> {:?} from {:?}",
+                            self.our_pub_id(),
+                            old,
+                            new,
+                            creator_id
+                        );
+                        return Err(Error::InvalidEvent);
+                    }
+                }
+            }
+            self.peer_list.record_signing_id(
+                creator,
+                &creator_id,
+                unpacked_event.event.index_by_creator(),
+            );
+
+            for (payload_key, observation_info) in unpacked_event.observations_for_store {
                 let _ = self
                     .observations
                     .entry(payload_key)
@@ -648,6 +1516,8 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         }
     }
 
+    // Whether the self-parent of the given event is one of our events, which is only the case for
+    // the events read back from our event log.
     fn new_event_from_observation(
         &mut self,
         self_parent: EventIndex,
@@ -656,7 +1526,25 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         let (event, observation_for_store) =
             Event::new_from_observation(self_parent, observation, self.event_context())?;
 
-        if let Some((payload_key, observation_info)) = observation_for_store {
+        if let Some((payload_key, observation_info)) = observation_for_store {
+            let _ = self
+                .observations
+                .entry(payload_key)
+                .or_insert_with(|| observation_info);
+        }
+
+        Ok(event)
+    }
+
+    fn new_event_from_batch(
+        &mut self,
+        self_parent: EventIndex,
+        observations: Vec<Observation<T, S::PublicId>>,
+    ) -> Result<Event<S::PublicId>> {
+        let (event, observations_for_store) =
+            Event::new_from_batch(self_parent, observations, self.event_context())?;
+
+        for (payload_key, observation_info) in observations_for_store {
             let _ = self
                 .observations
                 .entry(payload_key)
@@ -683,26 +1571,25 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
             return Err(Error::InvalidEvent);
         }
 
-        let unconsensused_payload_key = event
-            .payload_key()
-            .and_then(|key| self.observations.get_mut(key).map(|info| (key, info)))
-            .and_then(|(key, info)| {
+        let mut unconsensused_payload_keys = Vec::new();
+        for key in event.payload_keys() {
+            if let Some(info) = self.observations.get_mut(key) {
                 if our {
                     info.created_by_us = true;
                 }
-                if info.consensused {
-                    None
-                } else {
-                    Some(*key)
+                if !info.consensused {
+                    unconsensused_payload_keys.push(*key);
                 }
-            });
+            }
+        }
 
-        let event_index = self.insert_event(event);
+        let event_index = self.insert_event(event)?;
+        self.record_retraction(event_index);
 
-        let _ = unconsensused_payload_key.map(|payload_key| {
+        for payload_key in unconsensused_payload_keys {
             self.meta_election
                 .add_unconsensused_event(event_index, payload_key);
-        });
+        }
 
         #[cfg(any(test, feature = "testing"))]
         let ignore_process_events = self.ignore_process_events;
@@ -720,18 +1607,71 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
     // this node becomes voter.
     fn add_initial_event(&mut self) {
         let event = Event::new_initial(self.event_context());
-        let _ = self.insert_event(event);
+        if let Err(error) = self.insert_event(event) {
+            log_or_panic!(
+                "{:?} failed to add its initial event: {:?}",
+                self.our_pub_id(),
+                error
+            );
+        }
+    }
+
+    // Records the retraction carried by the given event, if any.
+    fn record_retraction(&mut self, event_index: EventIndex) {
+        let (creator, retracted) = match self.graph.get(event_index).and_then(|event| {
+            let key = event.payload_key()?;
+            match self.observations.get(key)?.observation {
+                Observation::Retract(ref retracted) => Some((event.creator(), retracted.clone())),
+                _ => None,
+            }
+        }) {
+            Some(retraction) => retraction,
+            None => return,
+        };
+
+        let hash = ObservationHash::from(&*retracted);
+        let _ = self
+            .retractions
+            .entry((creator, hash))
+            .or_insert(event_index);
+
+        if creator == PeerIndex::OUR {
+            let key = self.our_key(&retracted);
+            if let Some(info) = self.observations.get_mut(&key) {
+                info.retracted_by_us = true;
+            }
+        }
+    }
+
+    // Whether the creator of `vote_event` retracted its vote for the given payload, from the point
+    // of view of `event`.
+    fn is_retracted(
+        &self,
+        event: IndexedEventRef<S::PublicId>,
+        vote_event: IndexedEventRef<S::PublicId>,
+        payload_key: &ObservationKey,
+    ) -> bool {
+        if self.retractions.is_empty() {
+            return false;
+        }
+
+        self.retractions
+            .get(&(vote_event.creator(), *payload_key.hash()))
+            .and_then(|event_index| self.graph.get(*event_index))
+            .is_some_and(|retraction| event.is_descendant_of(retraction))
     }
 
-    fn insert_event(&mut self, event: Event<S::PublicId>) -> EventIndex {
-        let event = self.graph.insert(event);
+    fn insert_event(&mut self, event: Event<S::PublicId>) -> Result<EventIndex> {
+        let event = self.graph.insert(event)?;
         self.peer_list.add_event(event);
-        event.event_index()
+        Ok(event.event_index())
     }
 
     fn process_events(&mut self, mut start_index: usize) -> Result<()> {
         'outer: loop {
             for event_index in self.graph.indices_from(start_index) {
+                // The events read by the previous step are no longer borrowed.
+                self.graph.shrink_cache();
                 match self.process_event(event_index)? {
                     PostProcessAction::Restart(new_start_index)
                         if new_start_index <= event_index.topological_index() =>
@@ -746,11 +1686,15 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
             break;
         }
 
+        self.graph.shrink_cache();
         Ok(())
     }
 
     fn process_event(&mut self, event_index: EventIndex) -> Result<PostProcessAction> {
-        if self.peer_list.our_state() == PeerState::inactive() {
+        if self.peer_list.our_state() == PeerState::inactive()
+            || self.split.is_some()
+            || self.merge.is_some()
+        {
             return Ok(PostProcessAction::Continue);
         }
 
@@ -764,12 +1708,16 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         self.output_consensus_info(&payload_keys);
 
         let blocks = self.create_blocks(&payload_keys)?;
+        if let Some(block) = blocks.0.back() {
+            self.last_block_hash = Some(block.hash());
+        }
         if !blocks.is_empty() {
             self.consensused_blocks.push_back(blocks);
         }
 
         self.mark_observations_as_consensused(&payload_keys);
 
+        let consensus_mode = self.parameters.consensus_mode;
         let peer_list_changes = payload_keys
             .iter()
             .filter_map(|payload_key| self.handle_consensus(event_index, payload_key))
@@ -777,6 +1725,9 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
 
         self.meta_election
             .new_election(&self.graph, payload_keys, peer_list_changes);
+        if self.parameters.consensus_mode != consensus_mode {
+            self.rekey_votes();
+        }
 
         // Trigger reprocess.
         let start_index = self.meta_election.continue_consensus_start_index();
@@ -786,7 +1737,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
     fn output_consensus_info(&self, payload_keys: &[ObservationKey]) {
         dump_graph::to_file(dump_graph::ToFileInfo {
             owner_id: self.our_pub_id(),
-            consensus_mode: self.consensus_mode,
+            consensus_mode: self.parameters.consensus_mode,
             gossip_graph: &self.graph,
             meta_election: &self.meta_election,
             peer_list: &self.peer_list,
@@ -872,7 +1823,65 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
                 }
                 None
             }
-            Some(Observation::Genesis { .. }) | Some(Observation::OpaquePayload(_)) => None,
+            Some(Observation::EncryptedPayload {
+                key_gen,
+                ciphertext,
+            }) => {
+                if self
+                    .handle_encrypted_payload_consensus(payload_key, key_gen, &ciphertext)
+                    .is_none()
+                {
+                    warn!(
+                        "{:?} can't decrypt EncryptedPayload with hash {:?} encrypted to DKG {}",
+                        self.our_pub_id(),
+                        payload_key.hash(),
+                        key_gen
+                    );
+                    self.consensused_blocks.push_back(BlockGroup(
+                        iter::once(Block::new_undecryptable_block(key_gen, ciphertext)).collect(),
+                    ));
+                }
+                None
+            }
+            Some(Observation::UndecryptablePayload { .. }) => {
+                log_or_panic!("Unexpected UndecryptablePayload consensus.");
+                None
+            }
+            Some(Observation::OversizedPayload { .. }) => {
+                log_or_panic!("Unexpected OversizedPayload consensus.");
+                None
+            }
+            Some(Observation::DecryptionShare(msg)) => {
+                if self.handle_decryption_share(payload_key, &msg).is_none() {
+                    warn!(
+                        "Ignoring DecryptionShare with Error: key: {:?}, msg: {:?}",
+                        payload_key, msg
+                    );
+                }
+                None
+            }
+            Some(Observation::RotateKey { old, new, .. }) => {
+                self.handle_rotate_key_consensus(&old, new);
+                None
+            }
+            Some(Observation::Split { left, right }) => {
+                self.handle_split_consensus(left, right);
+                None
+            }
+            Some(Observation::Merge {
+                other_section,
+                other_last_block,
+            }) => {
+                self.handle_merge_consensus(other_section, other_last_block);
+                None
+            }
+            Some(Observation::SetParameters(parameters)) => {
+                self.handle_set_parameters_consensus(parameters);
+                None
+            }
+            Some(Observation::Genesis { .. })
+            | Some(Observation::OpaquePayload(_))
+            | Some(Observation::Retract(_)) => None,
             None => {
                 log_or_panic!("Failed to get observation from hash.");
                 None
@@ -931,7 +1940,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
     ) -> Option<()> {
         if let Some(key_gen) = &mut self.key_gen.get_mut(&key_gen_id) {
             let ack_result = key_gen
-                .handle_ack(&self.peer_list.our_id(), creator_id, ack.clone())
+                .handle_ack(self.peer_list.our_id(), creator_id, ack.clone())
                 .map_err(|err| warn!("handle_dkg_message_ack error: {:?}", err))
                 .ok()?;
 
@@ -944,11 +1953,13 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
                             key_gen_id
                         );
                         let dkg_result = key_gen.generate().ok()?;
+                        let _ = self.section_keys.insert(key_gen_id, dkg_result.clone());
 
                         self.consensused_blocks.push_back(BlockGroup(
                             iter::once(Block::new_dkg_block(dkg_result)).collect(),
                         ));
                         let _ = self.key_gen.remove(&key_gen_id);
+                        self.prune_section_keys();
                     }
                 }
                 AckOutcome::Invalid(fault) => {
@@ -962,7 +1973,100 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         Some(())
     }
 
+    // This function must be called on consensus on an `EncryptedPayload` observation.
+    // Returns `None` if the payload can never be revealed, either because its ciphertext is invalid
+    // or because we no longer hold the keys of `key_gen`. Keys are dropped deterministically, in
+    // consensus order, so all the peers agree on it.
+    fn handle_encrypted_payload_consensus(
+        &mut self,
+        payload_key: &ObservationKey,
+        key_gen: KeyGenId,
+        ciphertext: &[u8],
+    ) -> Option<()> {
+        let (_, dkg_result) = self.section_keys.get(&key_gen)?;
+        let ciphertext: Ciphertext = serialisation::deserialise(ciphertext).ok()?;
+        if !ciphertext.verify() {
+            return None;
+        }
+
+        if let Some(secret_key_share) = &dkg_result.secret_key_share {
+            let share = secret_key_share.decrypt_share(&ciphertext)?;
+            self.pending_decryption_shares.push(DecryptionShareMessage {
+                payload_hash: *payload_key.hash(),
+                share: serialise(&share),
+            });
+        }
+
+        let _ = self
+            .pending_decryptions
+            .entry(*payload_key.hash())
+            .or_insert_with(|| PendingDecryption::new(key_gen, ciphertext));
+        Some(())
+    }
+
+    fn handle_decryption_share(
+        &mut self,
+        payload_key: &ObservationKey,
+        msg: &DecryptionShareMessage,
+    ) -> Option<()> {
+        let creator_id = self.peer_list.get(payload_key.peer_index()?)?.id();
+        let pending = if let Some(pending) = self.pending_decryptions.get_mut(&msg.payload_hash) {
+            pending
+        } else {
+            // Already decrypted.
+            return Some(());
+        };
+        // The keys of a generation aren't dropped while a payload encrypted to it is pending.
+        let (participants, dkg_result) = self.section_keys.get(&pending.key_gen())?;
+        let index = participants.iter().position(|id| id == creator_id)?;
+        let share: DecryptionShare = serialisation::deserialise(&msg.share).ok()?;
+
+        if !pending.add_share(&dkg_result.public_key_set, index, share) {
+            return None;
+        }
+
+        if let Some(plaintext) = pending.decrypt(&dkg_result.public_key_set) {
+            let _ = self.pending_decryptions.remove(&msg.payload_hash);
+            self.prune_section_keys();
+            let payload = serialisation::deserialise(&plaintext).ok()?;
+            self.consensused_blocks.push_back(BlockGroup(
+                iter::once(Block::new_decrypted_block(payload)).collect(),
+            ));
+        }
+        Some(())
+    }
+
+    // Drops the keys of the DKGs superseded by at least two newer ones, unless a consensused
+    // `EncryptedPayload` encrypted to them is still waiting to be revealed. Keeping the previous
+    // key too gives payloads voted for just before a DKG completed time to be consensused.
+    fn prune_section_keys(&mut self) {
+        let kept: BTreeSet<_> = self
+            .section_keys
+            .keys()
+            .rev()
+            .take(2)
+            .cloned()
+            .chain(
+                self.pending_decryptions
+                    .values()
+                    .map(PendingDecryption::key_gen),
+            )
+            .collect();
+        let dropped: Vec<_> = self
+            .section_keys
+            .keys()
+            .filter(|key_gen| !kept.contains(key_gen))
+            .cloned()
+            .collect();
+        for key_gen in dropped {
+            let _ = self.section_keys.remove(&key_gen);
+        }
+    }
+
     // This function must be called on consensus on a `StartDkg` observation.
+    // If we are not one of the `peers`, we still follow the DKG as an observer: we handle all the
+    // `Part` and `Ack` messages so we get the `PublicKeySet` (without a secret key share) once the
+    // key is ready.
     fn handle_dkg_start_consensus(&mut self, peers: &BTreeSet<S::PublicId>) -> Option<()> {
         let state = if self.new_peer_can_recv(self.our_pub_id()) {
             PeerState::DKG | PeerState::SEND | PeerState::RECV
@@ -1042,11 +2146,19 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
     }
 
     fn add_gossip_peer(&mut self, peer_id: &S::PublicId, state: PeerState) -> PeerIndex {
-        let peer_index = if let Some(peer_index) = self.peer_list.get_index(peer_id) {
-            self.peer_list.change_peer_state(peer_index, state);
-            peer_index
-        } else {
-            self.peer_list.add_peer(peer_id.clone(), state)
+        if self.learner && peer_id == self.our_pub_id() {
+            // Learners stay read-only, even if voted in by mistake.
+            return PeerIndex::OUR;
+        }
+
+        let peer_index = match self.peer_list.get_index(peer_id) {
+            Some(peer_index) if !self.is_removed(peer_index) => {
+                self.peer_list.change_peer_state(peer_index, state);
+                peer_index
+            }
+            // Either a new peer, or one rejoining after being removed. A rejoining peer gets a
+            // fresh membership under a new index, while its previous events remain valid history.
+            _ => self.peer_list.add_peer(peer_id.clone(), state),
         };
 
         if peer_index == PeerIndex::OUR && self.peer_list.our_events().next().is_none() {
@@ -1056,6 +2168,193 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         peer_index
     }
 
+    fn handle_set_parameters_consensus(&mut self, parameters: Parameters) {
+        info!(
+            "{:?} switching to parameters {:?}",
+            self.our_pub_id(),
+            parameters
+        );
+
+        self.parameters = parameters;
+        if let Some(ref mut inactivity) = self.inactivity {
+            inactivity.set_max_sync_events(parameters.max_inactive_sync_events);
+        }
+    }
+
+    // Re-keys the votes for the unconsensused observations whose consensus mode changed. Members
+    // may have unpacked them before or after the change, so this makes all of them count the votes
+    // the same way from the block which changed it.
+    fn rekey_votes(&mut self) {
+        let consensus_mode = self.parameters.consensus_mode;
+        let start_index = self.meta_election.new_consensus_start_index();
+
+        let mut rekeyed = BTreeMap::new();
+        for event_index in self.graph.indices_from(start_index) {
+            let event = match self.graph.get_mut(event_index) {
+                Some(event) => event,
+                None => continue,
+            };
+            let old_keys: Vec<_> = event.payload_keys().cloned().collect();
+            for old_key in old_keys {
+                let observation = match self.observations.get(&old_key) {
+                    Some(info) if !info.consensused => &info.observation,
+                    _ => continue,
+                };
+                let new_key = ObservationKey::new(
+                    *old_key.hash(),
+                    event.creator(),
+                    consensus_mode.of(observation),
+                );
+                if new_key == old_key {
+                    continue;
+                }
+
+                event.rekey_vote(&old_key, new_key);
+                rekeyed
+                    .entry(old_key)
+                    .or_insert_with(Vec::new)
+                    .push((new_key, event.creator()));
+            }
+        }
+
+        for (old_key, new_keys) in rekeyed {
+            let info = match self.observations.remove(&old_key) {
+                Some(info) => info,
+                None => continue,
+            };
+            for (new_key, creator) in new_keys {
+                let new_info = self
+                    .observations
+                    .entry(new_key)
+                    .or_insert_with(|| ObservationInfo::new(info.observation.clone()));
+                if creator == PeerIndex::OUR {
+                    new_info.created_by_us |= info.created_by_us;
+                    new_info.retracted_by_us |= info.retracted_by_us;
+                }
+            }
+        }
+
+        let graph = &self.graph;
+        self.meta_election
+            .rekey_unconsensused_events(|event_index| {
+                graph
+                    .get(event_index)
+                    .map(|event| event.payload_keys().cloned().collect())
+                    .unwrap_or_default()
+            });
+    }
+
+    fn handle_rotate_key_consensus(&mut self, old: &S::PublicId, new: S::PublicId) {
+        if old == self.our_pub_id() {
+            match self.pending_key.take() {
+                Some(new_id) if *new_id.public_id() == new => {
+                    self.peer_list.rotate_our_key(new_id);
+                }
+                pending_key => {
+                    warn!(
+                        "{:?} can't rotate to {:?}: missing its secret key",
+                        self.our_pub_id(),
+                        new
+                    );
+                    self.pending_key = pending_key;
+                }
+            }
+        } else if let Some(peer_index) = self.peer_list.get_index(old) {
+            self.peer_list.rotate_key(peer_index, new);
+        }
+    }
+
+    fn handle_split_consensus(
+        &mut self,
+        left: BTreeSet<S::PublicId>,
+        right: BTreeSet<S::PublicId>,
+    ) {
+        let voters: BTreeSet<_> = self
+            .peer_list
+            .voters()
+            .map(|(_, peer)| peer.id().clone())
+            .collect();
+        let is_partition = !left.is_empty()
+            && !right.is_empty()
+            && left.is_disjoint(&right)
+            && left.union(&right).cloned().collect::<BTreeSet<_>>() == voters;
+        if !is_partition {
+            warn!(
+                "{:?} ignoring split {:?} | {:?} which doesn't partition the voters {:?}",
+                self.our_pub_id(),
+                left,
+                right,
+                voters
+            );
+            return;
+        }
+
+        info!(
+            "{:?} ending history on split {:?} | {:?}",
+            self.our_pub_id(),
+            left,
+            right
+        );
+        // The current batch isn't in the history yet, so this covers the blocks before the split.
+        let history_hash =
+            self.consensus_history_hash(self.meta_election.consensus_history().len());
+        let block_hash = BlockHash::new(&Observation::<T, S::PublicId>::Split {
+            left: left.clone(),
+            right: right.clone(),
+        });
+        self.split = Some(Split {
+            left,
+            right,
+            block_hash,
+            history_hash,
+        });
+    }
+
+    fn handle_merge_consensus(
+        &mut self,
+        other_section: BTreeSet<S::PublicId>,
+        other_last_block: BlockHash,
+    ) {
+        let voters: BTreeSet<_> = self
+            .peer_list
+            .voters()
+            .map(|(_, peer)| peer.id().clone())
+            .collect();
+        if other_section.is_empty() || !other_section.is_disjoint(&voters) {
+            warn!(
+                "{:?} ignoring merge with {:?} which isn't disjoint from the voters {:?}",
+                self.our_pub_id(),
+                other_section,
+                voters
+            );
+            return;
+        }
+
+        info!(
+            "{:?} ending history on merge with {:?}",
+            self.our_pub_id(),
+            other_section
+        );
+        let block_hash = BlockHash::new(&Observation::<T, S::PublicId>::Merge {
+            other_section: other_section.clone(),
+            other_last_block,
+        });
+        self.merge = Some(Merge {
+            voters,
+            other_section,
+            block_hash,
+        });
+    }
+
+    fn is_removed(&self, peer_index: PeerIndex) -> bool {
+        peer_index != PeerIndex::OUR
+            && self
+                .peer_list
+                .get(peer_index)
+                .and_then(Peer::removal_event)
+                .is_some()
+    }
+
     fn handle_remove_peer(
         &mut self,
         event_index: EventIndex,
@@ -1115,7 +2414,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         };
 
         let is_interesting_payload = |payload_key: &ObservationKey| {
-            self.is_interesting_payload(builder, &peers_that_can_vote, payload_key)
+            self.is_interesting_payload(builder, peers_that_can_vote, payload_key)
         };
 
         let payloads = find_interesting_content_for_event(
@@ -1175,7 +2474,10 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         event: IndexedEventRef<S::PublicId>,
         payload_key: &ObservationKey,
     ) -> usize {
-        let unconsensused_events = self.unconsensused_events(Some(payload_key)).collect_vec();
+        let unconsensused_events = self
+            .unconsensused_events(Some(payload_key))
+            .filter(|that_event| !self.is_retracted(event, *that_event, payload_key))
+            .collect_vec();
 
         peers_that_can_vote
             .iter()
@@ -1194,25 +2496,41 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         payload_key: &ObservationKey,
     ) -> bool {
         self.unconsensused_events(Some(payload_key))
+            .filter(|that_event| !self.is_retracted(event, *that_event, payload_key))
             .any(|that_event| event.is_descendant_of(that_event))
     }
 
     fn set_observer(&self, builder: &mut MetaEventBuilder<S::PublicId>) {
         // An event is an observer if it has a supermajority of observees and its self-parent
         // does not.
+        //
+        // This is a function of the voters, of the first interesting events, of whether the
+        // self-sync-parent is or descends from an observer, and of the ancestors of the event only:
+        // `strongly_sees` just counts the voters and walks the ancestors. The ancestors never
+        // change, so if the other inputs are the same as when the meta-event was last built, for a
+        // previous meta-election, so is the observer.
+        let descends_from_observer = self.is_descendant_of_observer(builder.event());
+        if builder.observer_inputs().is_some_and(|inputs| {
+            self.meta_election.has_same_observer_inputs(
+                builder.event().event_index(),
+                inputs,
+                descends_from_observer,
+            )
+        }) {
+            return;
+        }
 
-        if self.is_descendant_of_observer(builder.event()) {
-            builder.set_observer(Observer::Ancestor);
+        let inputs = self.meta_election.observer_inputs(descends_from_observer);
+        if descends_from_observer {
+            builder.set_observer(Observer::Ancestor, inputs);
             return;
         }
 
-        let voter_count = self.voter_count();
         let observees: PeerIndexSet = self
             .meta_election
-            .interesting_events()
-            .filter_map(|(peer_index, event_indices)| {
-                let event_index = event_indices.first()?;
-                let event = self.get_known_event(*event_index).ok()?;
+            .first_interesting_events()
+            .filter_map(|(peer_index, event_index)| {
+                let event = self.get_known_event(event_index).ok()?;
                 if self.strongly_sees(builder.event(), event) {
                     Some(peer_index)
                 } else {
@@ -1221,10 +2539,10 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
             })
             .collect();
 
-        if is_more_than_two_thirds(observees.len(), voter_count) {
-            builder.set_observer(Observer::This(observees));
+        if is_more_than_two_thirds(observees.len(), self.voters().len()) {
+            builder.set_observer(Observer::This(observees), inputs);
         } else {
-            builder.set_observer(Observer::None);
+            builder.set_observer(Observer::None, inputs);
         }
     }
 
@@ -1265,8 +2583,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
             return Ok(());
         }
 
-        let ancestors_meta_votes =
-            self.other_voting_ancestors_meta_votes(&voters, &builder.event());
+        let ancestors_meta_votes = self.other_voting_ancestors_meta_votes(voters, &builder.event());
 
         if let Some(parent_meta_votes) = parent_meta_votes {
             // Parent has meta votes: Derive this event's meta votes from them.
@@ -1289,7 +2606,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
                 .collect();
 
             for (peer_index, temp_votes) in &temp_votes {
-                let coin_tosses = self.toss_coins(&voters, peer_index, temp_votes)?;
+                let coin_tosses = self.toss_coins(voters, peer_index, temp_votes)?;
                 let final_meta_votes = MetaVote::next_final(temp_votes, &coin_tosses, voters_len);
 
                 builder.add_meta_votes(peer_index, final_meta_votes);
@@ -1341,7 +2658,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         // FIXME: This coin is common, but also predictable. It means that we can't guarentee
         // Liveness in the presence of a sophisticated adversary which controls the scheduler.
         // It is only a temporary measure until we implement an actual common coin.
-        Ok(Some(temp_vote.round % 2 != 0))
+        Ok(Some(!temp_vote.round.is_multiple_of(2)))
     }
 
     // Returns all the meta votes from the event's voting ancestors except the event's creator.
@@ -1392,7 +2709,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
     fn unconsensused_events(
         &self,
         filter_key: Option<&ObservationKey>,
-    ) -> impl Iterator<Item = IndexedEventRef<S::PublicId>> {
+    ) -> impl Iterator<Item = IndexedEventRef<'_, S::PublicId>> {
         self.meta_election
             .unconsensused_events(filter_key)
             .filter_map(move |index| self.get_known_event(index).ok())
@@ -1408,7 +2725,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         // If the creator of the current event is the only known voter and the event carries
         // a payload, decide it immediately.
         if iter::once(event.creator()).eq(self.voters()) {
-            return event.payload_key().into_iter().cloned().collect();
+            return event.payload_keys().cloned().collect();
         }
 
         // Otherwise proceed normally with evaluating the meta-election.
@@ -1509,38 +2826,53 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
                     .map(|event| event.inner())
                     .filter(|event| voters.contains(event.creator()))
                     .filter_map(|event| {
-                        let (vote, key) = event.vote_and_payload_key(&self.observations)?;
-                        let creator_id = self.peer_list.get(event.creator()).map(Peer::id)?;
-                        Some((key, vote, creator_id))
+                        let vote = event.vote(payload_key, &self.observations)?;
+                        let creator_id = self
+                            .peer_list
+                            .id_at(event.creator(), event.index_by_creator())?;
+                        Some((creator_id.clone(), vote))
                     })
-                    .map(|(_, vote, creator_id)| (creator_id.clone(), vote.clone()))
                     .collect();
 
                 Block::new(&votes)
             })
-            .filter(|block| match block {
-                Err(Error::MissingVotes) => false,
-                Err(_) => true,
-                Ok(block) => {
-                    // Do not leak internal blocks to Parsec consumer
-                    !block.payload().is_internal()
-                }
+            .filter_map(|block| match block {
+                Err(Error::MissingVotes) => None,
+                Err(error) => Some(Err(error)),
+                // Do not leak internal blocks to Parsec consumer
+                Ok(ref block) if block.payload().is_internal() => None,
+                Ok(block) => match self.parameters.oversize(block.payload()) {
+                    Some(size) => {
+                        warn!(
+                            "{:?} consensused a payload of {} bytes, over the limit: {:?}",
+                            self.our_pub_id(),
+                            size,
+                            block.hash()
+                        );
+                        Some(Ok(Block::new_oversized_block(block.payload(), size)))
+                    }
+                    None => Some(Ok(block)),
+                },
             })
             .collect();
 
         Ok(BlockGroup(blocks?))
     }
 
-    // Returns the number of peers that created events which are seen by event X (descendant) and
-    // see event Y (ancestor). This means number of peers through which there is a directed path
-    // between x and y, excluding peers contains fork.
-    fn num_peers_created_events_seen_by_x_that_can_see_y(
-        &self,
-        x: &Event<S::PublicId>,
-        y: &Event<S::PublicId>,
-    ) -> usize {
+    // Returns the peers that created events which are seen by event X (descendant) and see event Y
+    // (ancestor). These are the peers through which there is a directed path between x and y,
+    // excluding peers contains fork.
+    fn peers_created_events_seen_by_x_that_can_see_y<'a>(
+        &'a self,
+        x: &'a Event<S::PublicId>,
+        y: &'a Event<S::PublicId>,
+    ) -> impl Iterator<Item = PeerIndex> + 'a {
+        // Every such path goes through an ancestor of x, so if y isn't one there are no such peers.
+        let is_descendant = x.is_descendant_of(y);
+
         x.last_ancestors()
-            .filter(|(peer_index, event_index)| {
+            .filter(move |_| is_descendant)
+            .filter(move |(peer_index, event_index)| {
                 for event_idx in self.peer_list.events_by_index(*peer_index, *event_index) {
                     if let Ok(event) = self.get_known_event(event_idx) {
                         if x.sees(event) && event.sees(y) {
@@ -1550,7 +2882,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
                 }
                 false
             })
-            .count()
+            .map(|(peer_index, _)| peer_index)
     }
 
     // Returns whether event X can strongly see the event Y during the evaluation of the given
@@ -1560,10 +2892,14 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         A: AsRef<Event<S::PublicId>>,
         B: AsRef<Event<S::PublicId>>,
     {
-        is_more_than_two_thirds(
-            self.num_peers_created_events_seen_by_x_that_can_see_y(x.as_ref(), y.as_ref()),
-            self.voter_count(),
-        )
+        // Stop looking for peers as soon as there are enough of them.
+        let voter_count = self.voter_count();
+        let threshold = 2 * voter_count / 3 + 1;
+        let count = self
+            .peers_created_events_seen_by_x_that_can_see_y(x.as_ref(), y.as_ref())
+            .take(threshold)
+            .count();
+        is_more_than_two_thirds(count, voter_count)
     }
 
     // Constructs a sync event to prove receipt of a `Request` or `Response` (depending on the value
@@ -1594,11 +2930,71 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         }
 
         let _ = self.add_event(event)?;
+        self.vote_for_inactive_peers();
         Ok(())
     }
 
+    // Votes to remove the voters our inactivity policy, if any, deems unresponsive.
+    // This runs once our sync event is already in the graph, so failing to vote doesn't fail the
+    // gossip: the voter just stays unreported and is retried on our next sync event.
+    fn vote_for_inactive_peers(&mut self) {
+        if self.split.is_some() || self.merge.is_some() {
+            return;
+        }
+
+        let inactive = if let Some(ref mut inactivity) = self.inactivity {
+            inactivity.record_sync_event(&self.peer_list)
+        } else {
+            return;
+        };
+
+        if !self.peer_list.our_state().can_vote() {
+            return;
+        }
+
+        for peer_index in inactive {
+            let peer_id = match self.peer_list.get_known(peer_index) {
+                Ok(peer) => peer.id().clone(),
+                Err(error) => {
+                    log_or_panic!(
+                        "{:?} can't find unresponsive peer {:?}: {:?}",
+                        self.our_pub_id(),
+                        peer_index,
+                        error
+                    );
+                    continue;
+                }
+            };
+            let observation = Observation::Remove {
+                peer_id,
+                related_info: vec![],
+            };
+
+            if !self.have_voted_for(&observation) {
+                info!(
+                    "{:?} voting to remove unresponsive {:?}",
+                    self.our_pub_id(),
+                    observation
+                );
+                if let Err(error) = self.vote_for(observation) {
+                    warn!(
+                        "{:?} failed to vote to remove unresponsive peer {:?}: {:?}",
+                        self.our_pub_id(),
+                        peer_index,
+                        error
+                    );
+                    continue;
+                }
+            }
+
+            if let Some(ref mut inactivity) = self.inactivity {
+                inactivity.mark_reported(peer_index);
+            }
+        }
+    }
+
     fn create_dkg_events(&mut self) -> Result<()> {
-        for msg in mem::replace(&mut self.pending_dkg_msgs, vec![]) {
+        for msg in std::mem::take(&mut self.pending_dkg_msgs) {
             self.create_dkg_event(msg)?;
         }
         Ok(())
@@ -1619,6 +3015,22 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         Ok(())
     }
 
+    fn create_decryption_share_events(&mut self) -> Result<()> {
+        for msg in std::mem::take(&mut self.pending_decryption_shares) {
+            self.process_or_queue_pending_event(PendingEvent::DecryptionShare { msg })?;
+        }
+        Ok(())
+    }
+
+    fn add_decryption_share_event(&mut self, msg: DecryptionShareMessage) -> Result<()> {
+        let event = self.new_event_from_observation(
+            self.our_last_event_index()?,
+            Observation::DecryptionShare(msg),
+        )?;
+        let _ = self.add_event(event)?;
+        Ok(())
+    }
+
     // Returns an iterator over `self.events` which will yield all the events we think `peer_id`
     // doesn't yet know about.  We should already have checked that we know `peer_id` and that we
     // have recorded at least one event from this peer before calling this function.
@@ -1654,7 +3066,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
             return Ok(());
         }
 
-        for event in mem::replace(&mut self.pending_events, vec![]) {
+        for event in std::mem::take(&mut self.pending_events) {
             self.process_pending_event(event)?;
         }
 
@@ -1668,6 +3080,9 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         // Store as pending events if we do not have the initial event, which means we are
         // not voter yet.
         if self.peer_list.last_event(PeerIndex::OUR).is_none() {
+            if let PendingEvent::Sync { .. } = event {
+                self.drop_oldest_pending_sync_event_if_full();
+            }
             self.pending_events.push(event);
             Ok(())
         } else {
@@ -1675,6 +3090,25 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         }
     }
 
+    // Makes room for a new pending sync event, if we have as many pending events as our limit.
+    // The other pending events carry votes, so they are never dropped.
+    fn drop_oldest_pending_sync_event_if_full(&mut self) {
+        let is_full = self
+            .limits
+            .max_pending_events
+            .is_some_and(|max_pending_events| self.pending_events.len() >= max_pending_events);
+        if !is_full {
+            return;
+        }
+
+        if let Some(position) = self.pending_events.iter().position(|event| match event {
+            PendingEvent::Sync { .. } => true,
+            _ => false,
+        }) {
+            let _ = self.pending_events.remove(position);
+        }
+    }
+
     fn process_pending_event(&mut self, event: PendingEvent<T, S::PublicId>) -> Result<()> {
         match event {
             PendingEvent::Sync {
@@ -1683,6 +3117,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
                 ..
             } => self.add_sync_event(is_request, other_parent),
             PendingEvent::DkgMessage { msg } => self.add_dkg_event(msg),
+            PendingEvent::DecryptionShare { msg } => self.add_decryption_share_event(msg),
             #[cfg(feature = "malice-detection")]
             PendingEvent::Accusation {
                 offender,
@@ -1692,12 +3127,12 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         }
     }
 
-    fn event_context(&self) -> EventContextRef<T, S> {
+    fn event_context(&self) -> EventContextRef<'_, T, S> {
         EventContextRef {
             graph: &self.graph,
             peer_list: &self.peer_list,
             observations: &self.observations,
-            consensus_mode: self.consensus_mode,
+            consensus_mode: self.parameters.consensus_mode,
         }
     }
 
@@ -1731,7 +3166,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
 #[cfg(feature = "malice-detection")]
 impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
     fn create_accusation_events(&mut self, other_parent: EventIndex) -> Result<()> {
-        let pending_accusations = mem::replace(&mut self.pending_accusations, vec![]);
+        let pending_accusations = std::mem::take(&mut self.pending_accusations);
         for (offender, malice) in pending_accusations {
             self.create_accusation_event(offender, malice, other_parent)?;
         }
@@ -1788,7 +3223,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
             .and_then(|index| self.get_known_event(index))?;
         let other_parent = self.get_known_event(other_parent)?;
 
-        Ok(self.accused_events(&malice).iter().all(|accused_event| {
+        Ok(self.accused_events(malice).iter().all(|accused_event| {
             self_parent.is_descendant_of(accused_event)
                 || other_parent.is_descendant_of(accused_event)
         }))
@@ -1909,7 +3344,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
                 || self
                     .graph
                     .self_parent(event)
-                    .map_or(true, |self_parent| !self_parent.is_initial())
+                    .is_none_or(|self_parent| !self_parent.is_initial())
         };
 
         if accuse {
@@ -1934,7 +3369,12 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
                 return;
             };
 
-            self.genesis_group().contains(creator_id)
+            // A genesis member which rejoined after being removed starts a new chain without a
+            // genesis vote.
+            let first_incarnation =
+                self.peer_list.membership_index(creator_id, 0) == Some(event.creator());
+
+            first_incarnation && self.genesis_group().contains(creator_id)
         };
 
         if accuse {
@@ -1945,13 +3385,17 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
     // Detect that if the event carries a vote, there is already one or more votes with the same
     // observation by the same creator.
     fn detect_duplicate_vote(&mut self, event: &Event<S::PublicId>) {
-        let other_hash = {
-            let payload_key = if let Some(key) = event.payload_key() {
-                key
-            } else {
-                return;
-            };
+        for payload_key in event.payload_keys() {
+            self.detect_duplicate_vote_for(event, payload_key);
+        }
+    }
 
+    fn detect_duplicate_vote_for(
+        &mut self,
+        event: &Event<S::PublicId>,
+        payload_key: &ObservationKey,
+    ) {
+        let other_hash = {
             let mut duplicates = self
                 .peer_list
                 .peer_events(event.creator())
@@ -1959,8 +3403,8 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
                 .filter_map(|index| self.get_known_event(index).ok())
                 .filter(|event| {
                     event
-                        .payload_key()
-                        .map_or(false, |event_payload_key| event_payload_key == payload_key)
+                        .payload_keys()
+                        .any(|event_payload_key| event_payload_key == payload_key)
                 })
                 .map(|event| *event.hash())
                 .take(2);
@@ -2023,10 +3467,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
             self_parent_index.and_then(|event_index| self.get_known_event(event_index).ok())
         {
             match self.event_payload(&self_parent) {
-                Some(&Observation::Accusation {
-                    ref offender,
-                    ref malice,
-                }) => {
+                Some(Observation::Accusation { offender, malice }) => {
                     if malice.is_provable() && !self.we_have_accused(offender, malice) {
                         invalid_accusations.push(*self_parent.hash());
                     }
@@ -2065,10 +3506,8 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
             .rev()
             .filter_map(|event_index| self.get_known_event(event_index).ok())
             .filter_map(|event| {
-                if let Some(&Observation::Accusation {
-                    ref offender,
-                    ref malice,
-                }) = self.event_payload(event.inner())
+                if let Some(Observation::Accusation { offender, malice }) =
+                    self.event_payload(event.inner())
                 {
                     Some((offender, malice))
                 } else {
@@ -2140,8 +3579,8 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
 
     fn events_with_self_parent(
         &self,
-        parent: IndexedEventRef<S::PublicId>,
-    ) -> impl Iterator<Item = IndexedEventRef<S::PublicId>> {
+        parent: IndexedEventRef<'_, S::PublicId>,
+    ) -> impl Iterator<Item = IndexedEventRef<'_, S::PublicId>> {
         let parent_index = parent.event_index();
         self.peer_list
             .events_by_index(parent.creator(), parent.index_by_creator() + 1)
@@ -2200,7 +3639,7 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
         // purely as an optimisation
         let last_malice_event_accused_by_peer = self
             .accusations_by_peer_since(creator, starting_index)
-            .filter_map(|(_, malice)| malice.single_hash().and_then(|h| self.graph.get_index(&h)))
+            .filter_map(|(_, malice)| malice.single_hash().and_then(|h| self.graph.get_index(h)))
             .max_by_key(|event_index| event_index.topological_index());
         if let Some(index) = last_malice_event_accused_by_peer {
             self.peer_list
@@ -2227,10 +3666,10 @@ impl<T: NetworkEvent, S: SecretId> Parsec<T, S> {
             .map(|(offender, malice)| (*offender, malice))
             .chain(our_accusations)
             .filter(|(offender, _)| offender != &creator)
-            .filter(|(_, malice)| self.accused_events_are_ancestors_of(&malice, event_index))
+            .filter(|(_, malice)| self.accused_events_are_ancestors_of(malice, event_index))
             .filter(|(_, malice)| {
                 !self.accused_event_is_fork_but_this_event_is_not_a_fork_descendant(
-                    &malice,
+                    malice,
                     event_index,
                 )
             })
@@ -2265,7 +3704,7 @@ impl<T: NetworkEvent, S: SecretId> Drop for Parsec<T, S> {
     fn drop(&mut self) {
         dump_graph::to_file(dump_graph::ToFileInfo {
             owner_id: self.our_pub_id(),
-            consensus_mode: self.consensus_mode,
+            consensus_mode: self.parameters.consensus_mode,
             gossip_graph: &self.graph,
             meta_election: &self.meta_election,
             peer_list: &self.peer_list,
@@ -2288,6 +3727,14 @@ fn get_known_event<'a, P: PublicId>(
     })
 }
 
+fn confirm_hash_algorithm(hash_algorithm: HashAlgorithmId) -> Result<()> {
+    if hash_algorithm == HASH_ALGORITHM {
+        Ok(())
+    } else {
+        Err(Error::HashAlgorithmMismatch)
+    }
+}
+
 // What to do after processing the current event.
 enum PostProcessAction {
     // Continue with the next event (if any)
@@ -2299,6 +3746,35 @@ enum PostProcessAction {
 #[cfg(feature = "malice-detection")]
 type Accusations<T, P> = Vec<(PeerIndex, Malice<T, P>)>;
 
+// Sides of a section split, with the hash of the split block and of the history before it.
+#[derive(Serialize, Debug)]
+#[serde(bound = "")]
+struct Split<P: PublicId> {
+    left: BTreeSet<P>,
+    right: BTreeSet<P>,
+    block_hash: BlockHash,
+    history_hash: Hash,
+}
+
+// Our side of a consensused section merge.
+struct Merge<P: PublicId> {
+    // Our voters when the merge was consensused.
+    voters: BTreeSet<P>,
+    // Voters of the other section.
+    other_section: BTreeSet<P>,
+    // Hash of our `Merge` block.
+    block_hash: BlockHash,
+}
+
+// How much of our graph, in topological order, a learner has.
+#[derive(Default)]
+struct LearnerProgress {
+    // Number of events the learner acknowledged receiving.
+    acked: usize,
+    // Number of events sent in our latest request to it.
+    sent: usize,
+}
+
 enum PendingEvent<T: NetworkEvent, P: PublicId> {
     Sync {
         is_request: bool,
@@ -2308,6 +3784,9 @@ enum PendingEvent<T: NetworkEvent, P: PublicId> {
     DkgMessage {
         msg: DkgMessage,
     },
+    DecryptionShare {
+        msg: DecryptionShareMessage,
+    },
     #[cfg(feature = "malice-detection")]
     Accusation {
         offender: PeerIndex,
@@ -2346,7 +3825,7 @@ impl Parsec<Transaction, PeerId> {
         );
 
         for event in &parsed_contents.graph {
-            if let Some(payload_key) = event.payload_key() {
+            for payload_key in event.payload_keys() {
                 if let Some(info) = parsed_contents.observations.get_mut(payload_key) {
                     if event.creator() == PeerIndex::OUR {
                         info.created_by_us = true;
@@ -2370,9 +3849,7 @@ impl Parsec<Transaction, PeerId> {
             // Use Cursor to get an io::Read object so we can use the not over constrained
             // `deserialise_from`.
             let mut cursor = std::io::Cursor::new(serialized_key_gens_and_next_id);
-            let (key_gen, key_gen_next_id) = unwrap!(
-                maidsafe_utilities::serialisation::deserialise_from(&mut cursor)
-            );
+            let (key_gen, key_gen_next_id) = unwrap!(serialisation::deserialise_from(&mut cursor));
 
             parsec.key_gen = key_gen;
             parsec.key_gen_next_id = key_gen_next_id;
@@ -2444,7 +3921,7 @@ impl<T: NetworkEvent, S: SecretId> TestParsec<T, S> {
         unwrap!(self.0.our_last_event_index())
     }
 
-    pub fn event_context(&self) -> EventContextRef<T, S> {
+    pub fn event_context(&self) -> EventContextRef<'_, T, S> {
         self.0.event_context()
     }
 
@@ -2494,6 +3971,10 @@ impl TestParsec<Transaction, PeerId> {
         self.0.peer_list.change_peer_state(peer_index, state)
     }
 
+    pub fn add_peer(&mut self, peer_id: PeerId, state: PeerState) {
+        let _ = self.0.peer_list.add_peer(peer_id, state);
+    }
+
     pub fn pack_event(&self, event: &Event<PeerId>) -> PackedEvent<Transaction, PeerId> {
         unwrap!(event.pack(self.0.event_context()))
     }
@@ -2538,7 +4019,7 @@ impl TestParsec<Transaction, PeerId> {
             unwrap!(self.peer_list.remove_last_event(event.creator()))
         );
 
-        if let Some(payload_key) = event.payload_key() {
+        for payload_key in event.payload_keys() {
             let _ = self
                 .0
                 .meta_election
@@ -2604,10 +4085,6 @@ impl TestParsec<Transaction, PeerId> {
         &self.0.pending_accusations
     }
 
-    pub fn add_peer(&mut self, peer_id: PeerId, state: PeerState) {
-        let _ = self.0.peer_list.add_peer(peer_id, state);
-    }
-
     pub fn restart_consensus(&mut self) -> Result<()> {
         self.0.process_events(0)
     }
@@ -8,7 +8,6 @@
 
 use crate::{gossip::AbstractEventRef, observation::ObservationKey};
 use itertools::Itertools;
-use std::usize;
 
 /// Find interesting payloads for the builder_event.
 /// For payload observed from builder_event, order them by creation index.
@@ -23,16 +22,18 @@ where
     E: AbstractEventRef<'a>,
 {
     let has_builder_creator = |event: E| event.creator() == builder_event.creator();
+    let is_already_interesting_content = &is_already_interesting_content;
 
     let mut events_to_process = unconsensused_events
         .filter(|event| is_descendant(builder_event, *event))
-        .filter_map(|event| {
+        .flat_map(move |event| {
             event
-                .payload_key()
-                .filter(|payload_key| !is_already_interesting_content(payload_key))
-                .map(|payload_key| {
+                .payload_keys()
+                .enumerate()
+                .filter(move |(_, payload_key)| !is_already_interesting_content(payload_key))
+                .map(move |(position, payload_key)| {
                     (
-                        event,
+                        (event, position),
                         (payload_key, if has_builder_creator(event) { 0 } else { 1 }),
                     )
                 })
@@ -44,7 +45,7 @@ where
     //
     // Order to group same payload together so group_by can group events with same payloads.
     // Each payload exists in Number of peers events with `ConsensusMode::Supermajority`.
-    events_to_process.sort_by(|(_, l_key), (_, r_key)| l_key.cmp(&r_key));
+    events_to_process.sort_by_key(|(_, l_key)| *l_key);
     let payload_keys_with_events = events_to_process
         .into_iter()
         .group_by(|(_, (&payload_key, _))| payload_key)
@@ -59,18 +60,18 @@ where
         .iter()
         .filter_map(|(payload_key, events)| {
             // Event created by builder creator is first. Return this event if suitable.
-            if is_interesting_payload(&payload_key) {
+            if is_interesting_payload(payload_key) {
                 events.iter().next().map(|event| (event, payload_key))
             } else {
                 None
             }
         })
-        .map(|(event, payload_key)| {
+        .map(|(&(event, position), payload_key)| {
             (
-                if has_builder_creator(*event) {
-                    event.index_by_creator()
+                if has_builder_creator(event) {
+                    (event.index_by_creator(), position)
                 } else {
-                    usize::MAX
+                    (usize::MAX, 0)
                 },
                 payload_key,
             )
@@ -96,6 +97,7 @@ mod tests {
         observation::{ConsensusMode, ObservationHash},
         peer_list::PeerIndex,
     };
+    use std::option;
 
     lazy_static! {
         /// Hashes for opaque events to use in tests.
@@ -141,8 +143,10 @@ mod tests {
     }
 
     impl<'a> AbstractEventRef<'a> for &'a TestEvent {
-        fn payload_key(self) -> Option<&'a ObservationKey> {
-            self.payload_key.as_ref()
+        type PayloadKeys = option::Iter<'a, ObservationKey>;
+
+        fn payload_keys(self) -> Self::PayloadKeys {
+            self.payload_key.iter()
         }
 
         fn creator(self) -> PeerIndex {
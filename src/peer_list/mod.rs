@@ -30,7 +30,7 @@ use crate::{
 use std::{
     collections::btree_map::{BTreeMap, Entry},
     fmt::{self, Debug, Formatter},
-    iter,
+    iter, mem,
 };
 
 pub(crate) struct PeerList<S: SecretId> {
@@ -38,10 +38,22 @@ pub(crate) struct PeerList<S: SecretId> {
     our_peer: Peer<S::PublicId>,
     peers: Vec<Peer<S::PublicId>>,
     indices: BTreeMap<S::PublicId, PeerIndex>,
+    // Number of our memberships of the section before the current one, i.e. of times we were
+    // removed from it and rejoined it with the same id.
+    our_incarnation: usize,
+    // Indices of our former memberships, added as they are consensused. They hold the events we
+    // created in them like the ones of any other peer.
+    our_former_indices: Vec<PeerIndex>,
 }
 
 impl<S: SecretId> PeerList<S> {
     pub fn new(our_id: S) -> Self {
+        Self::new_rejoining(our_id, 0)
+    }
+
+    /// Creates the peer list of a peer which rejoins the section after `incarnation` memberships
+    /// of it.
+    pub fn new_rejoining(our_id: S, incarnation: usize) -> Self {
         let our_peer = Peer::new(our_id.public_id().clone(), PeerState::inactive());
 
         PeerList {
@@ -49,6 +61,8 @@ impl<S: SecretId> PeerList<S> {
             our_peer,
             peers: Vec::new(),
             indices: BTreeMap::new(),
+            our_incarnation: incarnation,
+            our_former_indices: Vec::new(),
         }
     }
 
@@ -57,15 +71,39 @@ impl<S: SecretId> PeerList<S> {
     }
 
     pub fn our_pub_id(&self) -> &S::PublicId {
-        &self.our_id.public_id()
+        self.our_id.public_id()
     }
 
     pub fn get_index(&self, peer_id: &S::PublicId) -> Option<PeerIndex> {
-        if peer_id == self.our_id.public_id() {
-            Some(PeerIndex::OUR)
-        } else {
-            self.indices.get(peer_id).cloned()
+        if peer_id != self.our_id.public_id() {
+            return self.indices.get(peer_id).cloned();
+        }
+
+        // Until our current membership starts, our id refers to our former membership in force.
+        match self.our_former_indices.last() {
+            Some(&index)
+                if self.our_former_indices.len() < self.our_incarnation
+                    || !self.has_been_removed(index) =>
+            {
+                Some(index)
+            }
+            None if self.our_incarnation > 0 => None,
+            _ => Some(PeerIndex::OUR),
+        }
+    }
+
+    /// Returns the index of the membership of the peer known by `peer_id` which follows
+    /// `incarnation` former ones, if it has been added yet.
+    pub fn membership_index(&self, peer_id: &S::PublicId, incarnation: usize) -> Option<PeerIndex> {
+        if peer_id == self.our_id.public_id() && incarnation == self.our_incarnation {
+            return Some(PeerIndex::OUR);
         }
+
+        self.iter()
+            .skip(1)
+            .filter(|(_, peer)| peer.has_id(peer_id))
+            .nth(incarnation)
+            .map(|(index, _)| index)
     }
 
     pub fn contains(&self, peer_id: &S::PublicId) -> bool {
@@ -124,7 +162,7 @@ impl<S: SecretId> PeerList<S> {
     /// Returns an iterator of peers that we can send gossip to.
     pub fn gossip_recipients<'a>(
         &'a self,
-    ) -> impl Iterator<Item = (PeerIndex, &Peer<S::PublicId>)> + 'a {
+    ) -> impl Iterator<Item = (PeerIndex, &'a Peer<S::PublicId>)> + 'a {
         let iter = if self.our_peer.state().can_send() {
             let iter = self
                 .iter()
@@ -135,10 +173,11 @@ impl<S: SecretId> PeerList<S> {
             None
         };
 
-        iter.into_iter().flat_map(|iter| iter)
+        iter.into_iter().flatten()
     }
 
     /// Return public ids of all peers.
+    #[cfg(all(test, feature = "mock"))]
     pub fn all_ids(&self) -> impl Iterator<Item = (PeerIndex, &S::PublicId)> {
         self.iter().map(|(index, peer)| (index, peer.id()))
     }
@@ -149,13 +188,100 @@ impl<S: SecretId> PeerList<S> {
             .unwrap_or_else(PeerState::inactive)
     }
 
+    /// Returns whether the peer has been removed from the section.
+    pub fn has_been_removed(&self, index: PeerIndex) -> bool {
+        self.get(index).and_then(Peer::removal_event).is_some()
+    }
+
     pub fn our_state(&self) -> PeerState {
         self.our_peer.state()
     }
 
+    /// Returns the number of our memberships of the section before the current one.
+    pub fn our_incarnation(&self) -> usize {
+        self.our_incarnation
+    }
+
+    /// Returns the id the peer at `index` signed its event with the given index-by-creator with.
+    pub fn id_at(&self, index: PeerIndex, index_by_creator: usize) -> Option<&S::PublicId> {
+        self.get(index).map(|peer| peer.id_at(index_by_creator))
+    }
+
+    // Makes `new_id` resolve to the peer at `index`, whose rotation to it has been consensused.
+    // Returns `false` if `new_id` is already taken by another peer.
+    fn add_id_alias(&mut self, index: PeerIndex, new_id: S::PublicId) -> bool {
+        if new_id == *self.our_id.public_id() {
+            return index == PeerIndex::OUR;
+        }
+
+        match self.indices.entry(new_id) {
+            Entry::Occupied(entry) => *entry.get() == index,
+            Entry::Vacant(entry) => {
+                let _ = entry.insert(index);
+                true
+            }
+        }
+    }
+
+    /// Switches the peer at `index` to `new_id` on consensus of its key rotation. Its events
+    /// signed with `new_id` are only accepted from then on.
+    pub fn rotate_key(&mut self, index: PeerIndex, new_id: S::PublicId) {
+        if !self.add_id_alias(index, new_id.clone()) {
+            return;
+        }
+        if let Some(peer) = self.get_known_mut(index) {
+            if !peer.has_id(&new_id) {
+                peer.rotate_id(new_id, None);
+            }
+        }
+    }
+
+    /// Switches to `new_id` on consensus of our key rotation. All our subsequent events are signed
+    /// with it.
+    pub fn rotate_our_key(&mut self, new_id: S) {
+        let old_id = mem::replace(&mut self.our_id, new_id);
+        let _ = self
+            .indices
+            .insert(old_id.public_id().clone(), PeerIndex::OUR);
+
+        let first_index_by_creator = self.our_peer.next_index_by_creator();
+        self.our_peer.rotate_id(
+            self.our_id.public_id().clone(),
+            Some(first_index_by_creator),
+        );
+    }
+
+    /// Records that the peer at `index` signed its event with the given index-by-creator with
+    /// `id`, so its later events are packed with the right id.
+    pub fn record_signing_id(
+        &mut self,
+        index: PeerIndex,
+        id: &S::PublicId,
+        index_by_creator: usize,
+    ) {
+        if index == PeerIndex::OUR {
+            return;
+        }
+
+        if let Some(peer) = self.get_known_mut(index) {
+            peer.record_signing_id(id, index_by_creator);
+        }
+    }
+
     /// Adds a peer in the given state into the map.
+    ///
+    /// If the peer has been removed before, it is added again under a new index, so its new
+    /// events don't clash with the ones it created before the removal.
     pub fn add_peer(&mut self, peer_id: S::PublicId, state: PeerState) -> PeerIndex {
         if peer_id == *self.our_id.public_id() {
+            if self.our_former_indices.len() < self.our_incarnation {
+                // One of our former memberships, which we follow like the one of any other peer.
+                let index = PeerIndex(self.peers.len() + 1);
+                self.peers.push(Peer::new(peer_id, state));
+                self.our_former_indices.push(index);
+                return index;
+            }
+
             log_or_panic!(
                 "{:?} already has self in the peer list",
                 self.our_id.public_id(),
@@ -165,13 +291,25 @@ impl<S: SecretId> PeerList<S> {
         }
 
         match self.indices.entry(peer_id) {
-            Entry::Occupied(entry) => {
-                log_or_panic!(
-                    "{:?} already has {:?} in the peer list",
-                    self.our_id.public_id(),
-                    entry.key()
-                );
-                *entry.get()
+            Entry::Occupied(mut entry) => {
+                let removed = self
+                    .peers
+                    .get(entry.get().0 - 1)
+                    .and_then(Peer::removal_event)
+                    .is_some();
+                if removed {
+                    let index = PeerIndex(self.peers.len() + 1);
+                    self.peers.push(Peer::new(entry.key().clone(), state));
+                    let _ = entry.insert(index);
+                    index
+                } else {
+                    log_or_panic!(
+                        "{:?} already has {:?} in the peer list",
+                        self.our_id.public_id(),
+                        entry.key()
+                    );
+                    *entry.get()
+                }
             }
             Entry::Vacant(entry) => {
                 let index = PeerIndex(self.peers.len() + 1);
@@ -202,7 +340,7 @@ impl<S: SecretId> PeerList<S> {
     /// Returns the index of the last event created by this peer. Returns `None` if cannot find.
     pub fn last_event(&self, peer_index: PeerIndex) -> Option<EventIndex> {
         self.get(peer_index)
-            .and_then(|peer| peer.events().rev().next())
+            .and_then(|peer| peer.events().next_back())
     }
 
     /// Returns the indices of the events at the given index-by-creator.
@@ -260,7 +398,7 @@ impl<S: SecretId> PeerList<S> {
     }
 
     /// Removes last event from its creator.
-    #[cfg(any(all(test, feature = "mock"), feature = "testing"))]
+    #[cfg(all(test, feature = "mock"))]
     pub fn remove_last_event(&mut self, creator: PeerIndex) -> Option<EventIndex> {
         if let Some(peer) = self.get_known_mut(creator) {
             peer.remove_last_event()
@@ -324,6 +462,8 @@ impl PeerList<PeerId> {
             our_peer,
             peers,
             indices,
+            our_incarnation: 0,
+            our_former_indices: Vec::new(),
         }
     }
 }
@@ -335,7 +475,7 @@ pub(crate) enum PeerListChange {
     Remove(PeerIndex),
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "mock"))]
 pub(crate) mod snapshot {
     use super::*;
     use crate::{gossip::EventHash, id::PublicId};
@@ -346,7 +486,6 @@ pub(crate) mod snapshot {
         BTreeMap<P, (PeerState, BTreeSet<(usize, EventHash)>)>,
     );
 
-    #[cfg(feature = "mock")]
     impl<P: PublicId> PeerListSnapshot<P> {
         pub fn new<S: SecretId<PublicId = P>>(peer_list: &PeerList<S>, graph: &Graph<P>) -> Self {
             PeerListSnapshot(
@@ -15,11 +15,15 @@ use itertools::Itertools;
 use std::{
     fmt::{self, Debug, Formatter},
     iter::{self, FromIterator},
+    mem,
 };
 
 #[derive(Debug)]
 pub(crate) struct Peer<P: PublicId> {
     id: P,
+    // Ids this peer signed its events with before rotating its key, each with the index-by-creator
+    // of its first event signed with the next key, if we know it yet.
+    former_ids: Vec<(P, Option<usize>)>,
     presence: Presence,
     pub(super) events: Events,
     pub(super) last_gossiped_event: Option<EventIndex>,
@@ -33,6 +37,7 @@ impl<P: PublicId> Peer<P> {
     pub(super) fn new(id: P, state: PeerState) -> Self {
         Self {
             id,
+            former_ids: Vec::new(),
             presence: Presence::Present(state),
             events: Events::new(),
             last_gossiped_event: None,
@@ -45,6 +50,41 @@ impl<P: PublicId> Peer<P> {
         &self.id
     }
 
+    /// Returns the id this peer signed its event with the given index-by-creator with.
+    pub fn id_at(&self, index_by_creator: usize) -> &P {
+        self.former_ids
+            .iter()
+            .find(|(_, end)| end.is_none_or(|end| index_by_creator < end))
+            .map(|(id, _)| id)
+            .unwrap_or(&self.id)
+    }
+
+    /// Returns whether this peer is or used to be known by `id`.
+    pub fn has_id(&self, id: &P) -> bool {
+        self.id == *id || self.former_ids.iter().any(|(former_id, _)| former_id == id)
+    }
+
+    pub(super) fn rotate_id(&mut self, new_id: P, first_index_by_creator: Option<usize>) {
+        let old_id = mem::replace(&mut self.id, new_id);
+        self.former_ids.push((old_id, first_index_by_creator));
+    }
+
+    // Records that this peer signed its event with the given index-by-creator with `id`. If that's
+    // the id of its consensused key rotation, its former id ends there.
+    pub(super) fn record_signing_id(&mut self, id: &P, index_by_creator: usize) {
+        if self.id == *id {
+            if let Some((_, end)) = self.former_ids.last_mut() {
+                if end.is_none() {
+                    *end = Some(index_by_creator);
+                }
+            }
+        }
+    }
+
+    pub(super) fn next_index_by_creator(&self) -> usize {
+        self.events.len()
+    }
+
     pub fn state(&self) -> PeerState {
         match self.presence {
             Presence::Present(state) => state,
@@ -88,7 +128,7 @@ impl<P: PublicId> Peer<P> {
         self.events.add(index_by_creator, event_index);
     }
 
-    #[cfg(any(all(test, feature = "mock"), feature = "testing"))]
+    #[cfg(all(test, feature = "mock"))]
     pub(super) fn remove_last_event(&mut self) -> Option<EventIndex> {
         self.events.remove_last()
     }
@@ -122,7 +162,7 @@ impl Events {
         self.0.push(Slot::new(event_index))
     }
 
-    #[cfg(any(all(test, feature = "mock"), feature = "testing"))]
+    #[cfg(all(test, feature = "mock"))]
     fn remove_last(&mut self) -> Option<EventIndex> {
         if let Some(slot) = self.0.last_mut() {
             if let Some(index) = slot.rest.pop() {
@@ -135,6 +175,10 @@ impl Events {
         self.0.pop().map(|slot| slot.first)
     }
 
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
     fn iter<'a>(&'a self) -> impl DoubleEndedIterator<Item = EventIndex> + 'a {
         self.0.iter().flat_map(Slot::iter)
     }
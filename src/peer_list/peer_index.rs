@@ -12,21 +12,21 @@ use std::{
     iter::FromIterator,
 };
 
-#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize, Debug)]
 pub(crate) struct PeerIndex(pub(super) usize);
 
 impl PeerIndex {
     /// `PeerIndex` of ourselves.
     pub const OUR: Self = PeerIndex(0);
 
-    #[cfg(any(test, feature = "testing"))]
+    #[cfg(test)]
     pub fn new_test_peer_index(index: usize) -> Self {
         Self(index)
     }
 }
 
 /// Map keyed by `PeerIndex`.
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub(crate) struct PeerIndexMap<T>(Vec<Option<T>>);
 
 impl<T> PeerIndexMap<T> {
@@ -38,10 +38,6 @@ impl<T> PeerIndexMap<T> {
         self.0.get(key.0).and_then(Option::as_ref)
     }
 
-    pub fn get_mut(&mut self, key: PeerIndex) -> Option<&mut T> {
-        self.0.get_mut(key.0).and_then(Option::as_mut)
-    }
-
     pub fn contains_key(&self, key: PeerIndex) -> bool {
         self.0.get(key.0).map(Option::is_some).unwrap_or(false)
     }
@@ -50,7 +46,7 @@ impl<T> PeerIndexMap<T> {
         self.0.iter().all(Option::is_none)
     }
 
-    pub fn iter(&self) -> MapIter<T> {
+    pub fn iter(&self) -> MapIter<'_, T> {
         MapIter {
             map: self,
             current: 0,
@@ -74,7 +70,7 @@ impl<T> PeerIndexMap<T> {
         self.0.clear()
     }
 
-    pub fn entry(&mut self, key: PeerIndex) -> Entry<T> {
+    pub fn entry(&mut self, key: PeerIndex) -> Entry<'_, T> {
         if self.contains_key(key) {
             Entry::Occupied(OccupiedEntry { key, map: self })
         } else {
@@ -211,6 +207,7 @@ impl PeerIndexSet {
         self.0.get(key.0).cloned().unwrap_or(false)
     }
 
+    #[cfg(any(test, feature = "testing"))]
     pub fn is_empty(&self) -> bool {
         self.0.iter().all(|value| !*value)
     }
@@ -219,7 +216,7 @@ impl PeerIndexSet {
         self.0.iter().filter(|value| **value).count()
     }
 
-    pub fn iter(&self) -> SetIter {
+    pub fn iter(&self) -> SetIter<'_> {
         SetIter {
             set: self,
             current: 0,
@@ -243,10 +240,6 @@ impl PeerIndexSet {
             false
         }
     }
-
-    pub fn clear(&mut self) {
-        self.0.clear()
-    }
 }
 
 impl Default for PeerIndexSet {
@@ -18,8 +18,8 @@ use serde::de::DeserializeOwned;
 use std::fmt::{self, Debug, Formatter};
 
 /// A helper struct carrying an `Observation` and a signature of this `Observation`.
-#[serde(bound(deserialize = "T: DeserializeOwned"))]
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(bound(deserialize = "T: DeserializeOwned"))]
 pub struct Vote<T: NetworkEvent, P: PublicId> {
     payload: Observation<T, P>,
     signature: P::Signature,
@@ -67,7 +67,8 @@ impl<T: NetworkEvent, P: PublicId> Debug for Vote<T, P> {
 }
 
 /// Key representing a vote when stored inside the gossip graph.
-#[derive(Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub(crate) struct VoteKey<P: PublicId> {
     payload_key: ObservationKey,
     signature: P::Signature,
@@ -108,6 +109,10 @@ impl<P: PublicId> VoteKey<P> {
     pub fn payload_key(&self) -> &ObservationKey {
         &self.payload_key
     }
+
+    pub fn set_payload_key(&mut self, payload_key: ObservationKey) {
+        self.payload_key = payload_key;
+    }
 }
 
 impl<P: PublicId> Debug for VoteKey<P> {
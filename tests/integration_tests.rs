@@ -6,6 +6,8 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+//! Tests running whole sections of peers over the simulated network.
+
 #![cfg(feature = "testing")]
 #![doc(
     html_logo_url = "https://raw.githubusercontent.com/maidsafe/QA/master/Images/maidsafe_logo.png",
@@ -13,20 +15,19 @@
     html_root_url = "https://docs.rs/parsec"
 )]
 #![forbid(
-    exceeding_bitshifts,
+    arithmetic_overflow,
     mutable_transmutes,
     no_mangle_const_items,
-    unknown_crate_types,
-    warnings
+    unknown_crate_types
 )]
 #![deny(
+    warnings,
     bad_style,
     deprecated,
     improper_ctypes,
     missing_docs,
     non_shorthand_field_patterns,
     overflowing_literals,
-    plugin_as_library,
     stable_features,
     unconditional_recursion,
     unknown_lints,
@@ -47,7 +48,6 @@
     unused_results
 )]
 #![allow(
-    box_pointers,
     missing_copy_implementations,
     missing_debug_implementations,
     variant_size_differences
@@ -367,7 +367,7 @@ fn run_dkgs(
         schedule: dkgs
             .keys()
             .map(|participants| (50, ObservationEvent::StartDkg(participants.clone())))
-            .chain(additional_events.into_iter())
+            .chain(additional_events)
             .collect(),
     };
 
@@ -415,7 +415,7 @@ fn run_dkgs(
         .flat_map(|(participants, dkg_name)| {
             final_peer_ids
                 .iter()
-                .map(move |id| (id.clone(), dkg_name.clone(), participants.contains(&id)))
+                .map(move |id| (id.clone(), dkg_name.clone(), participants.contains(id)))
         })
         .collect();
 
@@ -425,6 +425,30 @@ fn run_dkgs(
          key if it was participating.\nStarted DKGs: {:?}",
         dkgs
     );
+
+    // Observers compute the same public key set as the participants.
+    let mut public_key_sets = BTreeMap::new();
+    for peer in env.network.running_non_malicious_peers() {
+        for block in peer.blocks() {
+            if let Observation::DkgResult {
+                participants,
+                dkg_result,
+            } = block.payload()
+            {
+                let public_key_set = &dkg_result.0.public_key_set;
+                let expected = public_key_sets
+                    .entry(dkg_name(participants))
+                    .or_insert_with(|| public_key_set.clone());
+                assert_eq!(
+                    expected,
+                    public_key_set,
+                    "{:?} has a different public key set for DKG {}",
+                    peer.id(),
+                    dkg_name(participants)
+                );
+            }
+        }
+    }
 }
 
 #[test]